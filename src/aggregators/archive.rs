@@ -0,0 +1,370 @@
+use std::{
+    any::Any,
+    collections::{BTreeMap, HashMap},
+};
+
+use chrono::{DateTime, Local};
+use memchr::memmem;
+
+use crate::{
+    aggregators::Aggregator,
+    encoding,
+    error::Result,
+    format::{Format, lossy_line, quoted_after, wal_segment_name},
+    severity::Severity,
+    timestamp,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+enum ArchiveEventKind {
+    /// archive_command failed, with reason (exit code / signal) and the failed command if known
+    Failure {
+        reason: String,
+        command: Option<String>,
+    },
+    /// "archiving write-ahead log file ... failed too many times"
+    GaveUp(String),
+    /// "archived write-ahead log file ..." (DEBUG1)
+    Success(String),
+    /// Archiver process exited / was restarted
+    ArchiverRestart(String),
+    /// Plain log DETAIL line with the failed archive command
+    Detail(String),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct ArchiveEvent {
+    log_time: DateTime<Local>,
+    pid: Option<String>,
+    kind: ArchiveEventKind,
+}
+
+struct FailureStreak {
+    start: DateTime<Local>,
+    end: DateTime<Local>,
+    failures: u64,
+    segment: Option<String>,
+}
+
+/// Tracks WAL archiving: failed `archive_command` runs, failing segments, failure streaks,
+/// successful archive evidence and archiver restarts.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct ArchiveAggregator {
+    events: Vec<ArchiveEvent>,
+}
+
+impl ArchiveAggregator {
+    pub fn new() -> Self {
+        Self { events: Vec::new() }
+    }
+}
+
+impl Aggregator for ArchiveAggregator {
+    fn update(
+        &mut self,
+        record: &[u8],
+        fmt: &Format,
+        _severity: Severity,
+        log_time: DateTime<Local>,
+    ) -> Result<()> {
+        let Some(message) = fmt.message_from_bytes(record) else {
+            return Ok(());
+        };
+        let detail = fmt.detail_from_bytes(record);
+        let pid = fmt
+            .pid_from_bytes(record)
+            .map(|p| encoding::decode(p).to_string());
+
+        // Plain logs write DETAIL as a separate record
+        if matches!(fmt, Format::Plain)
+            && let Some(detail) = detail
+        {
+            if let Some(command) = failed_command(detail) {
+                self.events.push(ArchiveEvent {
+                    log_time,
+                    pid,
+                    kind: ArchiveEventKind::Detail(command),
+                });
+            }
+            return Ok(());
+        }
+
+        let Some(kind) = classify(message, detail) else {
+            return Ok(());
+        };
+
+        self.events.push(ArchiveEvent {
+            log_time,
+            pid,
+            kind,
+        });
+        Ok(())
+    }
+
+    fn merge_box(&mut self, other: &dyn Aggregator) {
+        let other = other
+            .as_any()
+            .downcast_ref::<ArchiveAggregator>()
+            .expect("Aggregator type mismatch");
+
+        self.events.extend(other.events.iter().cloned());
+    }
+
+    fn print(&mut self) {
+        self.events.sort_by_key(|e| e.log_time);
+        let mut events = std::mem::take(&mut self.events);
+
+        // Attach plain log DETAIL lines to the failure of the same pid
+        let mut pending: HashMap<String, usize> = HashMap::new();
+        for i in 0..events.len() {
+            match &events[i].kind {
+                ArchiveEventKind::Failure { command: None, .. } => {
+                    if let Some(pid) = &events[i].pid {
+                        pending.insert(pid.clone(), i);
+                    }
+                }
+                ArchiveEventKind::Detail(text) => {
+                    let text = text.clone();
+                    if let Some(target) = events[i].pid.as_ref().and_then(|p| pending.remove(p))
+                        && let ArchiveEventKind::Failure { command, .. } = &mut events[target].kind
+                    {
+                        *command = Some(text);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut failures = 0;
+        let mut failing_segments: BTreeMap<String, u64> = BTreeMap::new();
+        let mut failure_reasons: BTreeMap<String, u64> = BTreeMap::new();
+        let mut commands: BTreeMap<String, u64> = BTreeMap::new();
+        let mut gave_up: BTreeMap<String, u64> = BTreeMap::new();
+        let mut restarts: BTreeMap<String, u64> = BTreeMap::new();
+        let mut streaks: Vec<FailureStreak> = Vec::new();
+        let mut current: Option<FailureStreak> = None;
+        let mut first_success: Option<(DateTime<Local>, String)> = None;
+        let mut last_success: Option<(DateTime<Local>, String)> = None;
+
+        for event in events {
+            match event.kind {
+                ArchiveEventKind::Failure { reason, command } => {
+                    failures += 1;
+                    *failure_reasons.entry(reason).or_insert(0) += 1;
+                    let segment = command
+                        .as_deref()
+                        .and_then(|c| wal_segment_name(c.as_bytes()));
+                    if let Some(segment) = &segment {
+                        *failing_segments.entry(segment.clone()).or_insert(0) += 1;
+                    }
+                    if let Some(command) = &command {
+                        *commands.entry(generalize_command(command)).or_insert(0) += 1;
+                    }
+
+                    // The archiver works through segments in order, so a failure on another
+                    // segment means the previously failing one got archived in the meantime
+                    if let Some(streak) = &current
+                        && segment.is_some()
+                        && streak.segment.is_some()
+                        && streak.segment != segment
+                    {
+                        streaks.extend(current.take());
+                    }
+                    let streak = current.get_or_insert_with(|| FailureStreak {
+                        start: event.log_time,
+                        end: event.log_time,
+                        failures: 0,
+                        segment: segment.clone(),
+                    });
+                    streak.end = event.log_time;
+                    streak.failures += 1;
+                    if streak.segment.is_none() {
+                        streak.segment = segment;
+                    }
+                }
+                ArchiveEventKind::GaveUp(segment) => {
+                    *gave_up.entry(segment).or_insert(0) += 1;
+                }
+                ArchiveEventKind::Success(segment) => {
+                    streaks.extend(current.take());
+                    if first_success.is_none() {
+                        first_success = Some((event.log_time, segment.clone()));
+                    }
+                    last_success = Some((event.log_time, segment));
+                }
+                ArchiveEventKind::ArchiverRestart(reason) => {
+                    *restarts.entry(reason).or_insert(0) += 1;
+                }
+                ArchiveEventKind::Detail(_) => {}
+            }
+        }
+        streaks.extend(current.take());
+
+        println!("Archive command failures: {failures}");
+        print_counts("Failure reasons:", &failure_reasons);
+        print_counts("Failing segments:", &failing_segments);
+        print_counts("Segments that failed too many times:", &gave_up);
+
+        if !streaks.is_empty() {
+            println!("Failure streaks:");
+        }
+        for streak in &streaks {
+            println!(
+                "  [{}] - [{}] {:>6} failures  {}",
+                timestamp::display(streak.start),
+                timestamp::display(streak.end),
+                streak.failures,
+                streak.segment.as_deref().unwrap_or("unknown segment")
+            );
+        }
+
+        print_counts("Failed commands:", &commands);
+        print_counts("Archiver restarts:", &restarts);
+
+        match (&first_success, &last_success) {
+            (Some((first_time, first)), Some((last_time, last))) => {
+                println!("Successful archive evidence:");
+                println!("  first: [{}] {first}", timestamp::display(*first_time));
+                println!("  last:  [{}] {last}", timestamp::display(*last_time));
+            }
+            _ => println!(
+                "Successful archive evidence: none (\"archived write-ahead log file\" is logged with log_min_messages = debug1)"
+            ),
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        "archive"
+    }
+
+    fn save_state(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(self)?)
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Aggregator> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+fn print_counts(title: &str, counts: &BTreeMap<String, u64>) {
+    if counts.is_empty() {
+        return;
+    }
+    println!("{title}");
+    let mut entries: Vec<_> = counts.iter().collect();
+    entries.sort_by(|a, b| b.1.cmp(a.1));
+    for (value, count) in entries {
+        println!("{count:>6}  {value}");
+    }
+}
+
+fn classify(message: &[u8], detail: Option<&[u8]>) -> Option<ArchiveEventKind> {
+    if let Some(reason) = message.strip_prefix(b"archive command ") {
+        // failed with exit code N / was terminated by signal N: ... / exited with unrecognized status N
+        let reason = reason.strip_prefix(b"failed with ").unwrap_or(reason);
+        return Some(ArchiveEventKind::Failure {
+            reason: lossy_line(reason),
+            command: detail.and_then(failed_command),
+        });
+    }
+
+    if message.starts_with(b"archiving write-ahead log file")
+        && memmem::find(message, b"failed too many times").is_some()
+    {
+        let segment = quoted_after(message, b"file \"").unwrap_or_else(|| lossy_line(message));
+        return Some(ArchiveEventKind::GaveUp(segment));
+    }
+
+    if message.starts_with(b"archived write-ahead log file") {
+        let segment = quoted_after(message, b"file \"").unwrap_or_else(|| lossy_line(message));
+        return Some(ArchiveEventKind::Success(segment));
+    }
+
+    if message.starts_with(b"archiver process")
+        || message.starts_with(b"archiver was")
+        || message.starts_with(b"restarting archiver")
+    {
+        return Some(ArchiveEventKind::ArchiverRestart(lossy_line(&strip_pid(
+            message,
+        ))));
+    }
+
+    None
+}
+
+fn failed_command(detail: &[u8]) -> Option<String> {
+    detail
+        .strip_prefix(b"The failed archive command was: ")
+        .map(lossy_line)
+}
+
+/// Replaces WAL file names in the command with `%f`, so the same command for
+/// different segments is counted together.
+fn generalize_command(command: &str) -> String {
+    let mut result = command.to_string();
+    while let Some(segment) = wal_segment_name(result.as_bytes()) {
+        result = result.replace(&segment, "%f");
+    }
+    result
+}
+
+/// "archiver process (PID 123) exited with exit code 1" -> "archiver process exited with exit code 1"
+fn strip_pid(message: &[u8]) -> Vec<u8> {
+    if let Some(start) = memmem::find(message, b" (PID ")
+        && let Some(len) = memchr::memchr(b')', &message[start..])
+    {
+        let mut result = message[..start].to_vec();
+        result.extend_from_slice(&message[start + len + 1..]);
+        return result;
+    }
+    message.to_vec()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(
+            classify(
+                b"archive command failed with exit code 1",
+                Some(b"The failed archive command was: cp pg_wal/000000010000000000000003 /mnt/000000010000000000000003"),
+            ),
+            Some(ArchiveEventKind::Failure {
+                reason: "exit code 1".to_string(),
+                command: Some(
+                    "cp pg_wal/000000010000000000000003 /mnt/000000010000000000000003".to_string()
+                ),
+            })
+        );
+        assert_eq!(
+            classify(
+                b"archiving write-ahead log file \"000000010000000000000003\" failed too many times, will try again later",
+                None,
+            ),
+            Some(ArchiveEventKind::GaveUp(
+                "000000010000000000000003".to_string()
+            ))
+        );
+        assert_eq!(
+            classify(b"archiver process (PID 4242) exited with exit code 1", None),
+            Some(ArchiveEventKind::ArchiverRestart(
+                "archiver process exited with exit code 1".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_generalize_command() {
+        assert_eq!(
+            generalize_command("cp pg_wal/000000010000000000000003 /mnt/000000010000000000000003"),
+            "cp pg_wal/%f /mnt/%f"
+        );
+    }
+}
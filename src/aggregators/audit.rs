@@ -0,0 +1,105 @@
+use std::{any::Any, collections::HashMap};
+
+use chrono::{DateTime, Local};
+
+use crate::{
+    aggregators::Aggregator, encoding, error::Result, format::Format, pgaudit::extract_audit,
+    severity::Severity,
+};
+use serde::{Deserialize, Serialize};
+
+/// Counts pgaudit records per class, command, object and session user.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct AuditAggregator {
+    total: u64,
+    by_class: HashMap<String, u64>,
+    by_command: HashMap<String, u64>,
+    by_object: HashMap<String, u64>,
+    by_user: HashMap<String, u64>,
+}
+
+impl AuditAggregator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Aggregator for AuditAggregator {
+    fn update(
+        &mut self,
+        record: &[u8],
+        fmt: &Format,
+        _severity: Severity,
+        _log_time: DateTime<Local>,
+    ) -> Result<()> {
+        let Some(entry) = extract_audit(record, fmt) else {
+            return Ok(());
+        };
+
+        self.total += 1;
+        *self.by_class.entry(entry.class).or_insert(0) += 1;
+        *self.by_command.entry(entry.command).or_insert(0) += 1;
+        if !entry.object_name.is_empty() {
+            let object = format!("{} {}", entry.object_type, entry.object_name);
+            *self.by_object.entry(object).or_insert(0) += 1;
+        }
+        let user = fmt.session_user_from_bytes(record).unwrap_or(b"unknown");
+        *self
+            .by_user
+            .entry(encoding::decode(user).to_string())
+            .or_insert(0) += 1;
+        Ok(())
+    }
+
+    fn merge_box(&mut self, other: &dyn Aggregator) {
+        let other = other
+            .as_any()
+            .downcast_ref::<AuditAggregator>()
+            .expect("Aggregator type mismatch");
+
+        self.total += other.total;
+        for (mine, theirs) in [
+            (&mut self.by_class, &other.by_class),
+            (&mut self.by_command, &other.by_command),
+            (&mut self.by_object, &other.by_object),
+            (&mut self.by_user, &other.by_user),
+        ] {
+            for (key, count) in theirs {
+                *mine.entry(key.clone()).or_insert(0) += count;
+            }
+        }
+    }
+
+    fn print(&mut self) {
+        println!("Total audit records: {}", self.total);
+        for (title, counts) in [
+            ("Audit records by class:", &self.by_class),
+            ("Audit records by command:", &self.by_command),
+            ("Audit records by object:", &self.by_object),
+            ("Audit records by user:", &self.by_user),
+        ] {
+            println!("{title}");
+            let mut entries: Vec<_> = counts.iter().collect();
+            entries.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+            for (key, count) in entries {
+                println!("  {count:>6}  {key}");
+            }
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        "audit"
+    }
+
+    fn save_state(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(self)?)
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Aggregator> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
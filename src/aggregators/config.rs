@@ -0,0 +1,336 @@
+use std::{
+    any::Any,
+    collections::{BTreeMap, HashMap},
+};
+
+use chrono::{DateTime, Local};
+use memchr::memmem;
+
+use crate::{
+    aggregators::Aggregator,
+    encoding,
+    error::Result,
+    format::{Format, lossy_line, quoted_after},
+    severity::Severity,
+    timestamp,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+enum ConfigEventKind {
+    /// "received SIGHUP, reloading configuration files"
+    Reload,
+    /// parameter "x" changed to "y"
+    Changed { name: String, value: String },
+    /// parameter "x" cannot be changed without restarting the server
+    RestartRequired { name: String },
+    /// parameter "x" removed from configuration file, reset to default
+    Removed { name: String },
+    /// invalid value for parameter "x": "y"
+    InvalidValue { name: String, value: String },
+    /// configuration file "f" contains errors; ...
+    FileErrors { file: String, outcome: String },
+    /// ALTER SYSTEM SET x = y / ALTER SYSTEM RESET x, value is None for RESET
+    AlterSystem {
+        name: String,
+        value: Option<String>,
+        user: Option<String>,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct ConfigEvent {
+    log_time: DateTime<Local>,
+    kind: ConfigEventKind,
+}
+
+/// Reconstructs the settings history from reload, parameter change and ALTER SYSTEM messages.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct ConfigAggregator {
+    events: Vec<ConfigEvent>,
+}
+
+impl ConfigAggregator {
+    pub fn new() -> Self {
+        Self { events: Vec::new() }
+    }
+}
+
+impl Aggregator for ConfigAggregator {
+    fn update(
+        &mut self,
+        record: &[u8],
+        fmt: &Format,
+        _severity: Severity,
+        log_time: DateTime<Local>,
+    ) -> Result<()> {
+        let Some(message) = fmt.message_from_bytes(record) else {
+            return Ok(());
+        };
+
+        let kind = if let Some(statement) = alter_system_statement(message) {
+            let user = fmt
+                .session_user_from_bytes(record)
+                .map(|u| encoding::decode(u).to_string());
+            parse_alter_system(statement, user)
+        } else {
+            classify(message)
+        };
+
+        if let Some(kind) = kind {
+            self.events.push(ConfigEvent { log_time, kind });
+        }
+        Ok(())
+    }
+
+    fn merge_box(&mut self, other: &dyn Aggregator) {
+        let other = other
+            .as_any()
+            .downcast_ref::<ConfigAggregator>()
+            .expect("Aggregator type mismatch");
+
+        self.events.extend(other.events.iter().cloned());
+    }
+
+    fn print(&mut self) {
+        self.events.sort_by_key(|e| e.log_time);
+
+        let mut reloads = 0;
+        let mut file_errors: BTreeMap<String, u64> = BTreeMap::new();
+        let mut history: BTreeMap<String, Vec<(DateTime<Local>, String)>> = BTreeMap::new();
+        // ALTER SYSTEM statements (user per parameter) waiting for a reload, and the ones
+        // the last reload applied
+        let mut pending: HashMap<String, Option<String>> = HashMap::new();
+        let mut applying: HashMap<String, Option<String>> = HashMap::new();
+        let mut last_reload: Option<DateTime<Local>> = None;
+
+        for event in &self.events {
+            let time = event.log_time;
+            match &event.kind {
+                ConfigEventKind::Reload => {
+                    reloads += 1;
+                    last_reload = Some(time);
+                    applying = std::mem::take(&mut pending);
+                }
+                ConfigEventKind::Changed { name, value } => {
+                    let altered = applying
+                        .remove(name)
+                        .or_else(|| applying.get("all").cloned())
+                        .or_else(|| pending.remove(name));
+                    let source = match altered {
+                        Some(Some(user)) => format!("ALTER SYSTEM by {user}"),
+                        Some(None) => "ALTER SYSTEM".to_string(),
+                        None if last_reload.is_some() => "configuration reload".to_string(),
+                        None => "configuration file".to_string(),
+                    };
+                    history
+                        .entry(name.clone())
+                        .or_default()
+                        .push((time, format!("changed to \"{value}\" ({source})")));
+                }
+                ConfigEventKind::RestartRequired { name } => {
+                    history
+                        .entry(name.clone())
+                        .or_default()
+                        .push((time, "change pending, requires restart".to_string()));
+                }
+                ConfigEventKind::Removed { name } => {
+                    pending.remove(name);
+                    history.entry(name.clone()).or_default().push((
+                        time,
+                        "removed from configuration file, reset to default".to_string(),
+                    ));
+                }
+                ConfigEventKind::InvalidValue { name, value } => {
+                    history
+                        .entry(name.clone())
+                        .or_default()
+                        .push((time, format!("invalid value \"{value}\" rejected")));
+                }
+                ConfigEventKind::FileErrors { file, outcome } => {
+                    *file_errors.entry(format!("{file}: {outcome}")).or_insert(0) += 1;
+                }
+                ConfigEventKind::AlterSystem { name, value, user } => {
+                    pending.insert(name.clone(), user.clone());
+                    let by = user
+                        .as_ref()
+                        .map(|u| format!(" by {u}"))
+                        .unwrap_or_default();
+                    let action = match value {
+                        Some(value) => format!("ALTER SYSTEM SET {value}{by}"),
+                        None => format!("ALTER SYSTEM RESET{by}"),
+                    };
+                    history
+                        .entry(name.clone())
+                        .or_default()
+                        .push((time, action));
+                }
+            }
+        }
+
+        println!("Configuration reloads: {reloads}");
+        if !file_errors.is_empty() {
+            println!("Configuration file errors:");
+            for (error, count) in &file_errors {
+                println!("  {count:>6}  {error}");
+            }
+        }
+        println!("Parameter history:");
+        for (name, entries) in &history {
+            println!("  {name}");
+            for (time, entry) in entries {
+                println!("    [{}] {entry}", timestamp::display(*time));
+            }
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        "config"
+    }
+
+    fn save_state(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(self)?)
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Aggregator> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+fn classify(message: &[u8]) -> Option<ConfigEventKind> {
+    if message.starts_with(b"received SIGHUP") {
+        return Some(ConfigEventKind::Reload);
+    }
+
+    if message.starts_with(b"parameter \"") {
+        let name = quoted_after(message, b"parameter \"")?;
+        if memmem::find(message, b"\" changed to \"").is_some() {
+            let value = quoted_after(message, b"changed to \"")?;
+            return Some(ConfigEventKind::Changed { name, value });
+        }
+        if memmem::find(message, b"cannot be changed without restarting").is_some() {
+            return Some(ConfigEventKind::RestartRequired { name });
+        }
+        if memmem::find(message, b"removed from configuration file").is_some() {
+            return Some(ConfigEventKind::Removed { name });
+        }
+        return None;
+    }
+
+    if message.starts_with(b"invalid value for parameter \"") {
+        let name = quoted_after(message, b"parameter \"")?;
+        let value = quoted_after(message, b"\": \"").unwrap_or_default();
+        return Some(ConfigEventKind::InvalidValue { name, value });
+    }
+
+    if message.starts_with(b"configuration file \"")
+        && let Some(pos) = memmem::find(message, b"contains errors")
+    {
+        let file = quoted_after(message, b"configuration file \"")?;
+        let outcome = lossy_line(&message[pos + b"contains errors".len()..]);
+        let outcome = outcome.trim_start_matches(';').trim().to_string();
+        return Some(ConfigEventKind::FileErrors { file, outcome });
+    }
+
+    None
+}
+
+/// Returns the statement text following `statement: ALTER SYSTEM`, matched case-insensitively.
+/// Only statements logged by `log_statement` / `log_min_duration_statement` are considered.
+fn alter_system_statement(message: &[u8]) -> Option<&[u8]> {
+    const NEEDLE: &[u8] = b"statement: alter system ";
+    message
+        .windows(NEEDLE.len())
+        .position(|w| w.eq_ignore_ascii_case(NEEDLE))
+        .map(|pos| &message[pos + NEEDLE.len()..])
+}
+
+fn parse_alter_system(statement: &[u8], user: Option<String>) -> Option<ConfigEventKind> {
+    let statement = lossy_line(statement);
+    let statement = statement.trim_end_matches(';');
+    let mut words = statement.split_whitespace();
+    let action = words.next()?.to_ascii_lowercase();
+    let name = words.next()?.trim_matches('"').to_ascii_lowercase();
+
+    match action.as_str() {
+        "set" => {
+            let rest: Vec<&str> = words.collect();
+            let rest = match rest.first() {
+                Some(&"=") => &rest[1..],
+                Some(word) if word.eq_ignore_ascii_case("to") => &rest[1..],
+                _ => &rest[..],
+            };
+            let value = rest.join(" ").trim_start_matches('=').trim().to_string();
+            let value = if value.eq_ignore_ascii_case("default") {
+                None
+            } else {
+                Some(value)
+            };
+            Some(ConfigEventKind::AlterSystem { name, value, user })
+        }
+        "reset" => Some(ConfigEventKind::AlterSystem {
+            name,
+            value: None,
+            user,
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(
+            classify(b"parameter \"work_mem\" changed to \"64MB\""),
+            Some(ConfigEventKind::Changed {
+                name: "work_mem".to_string(),
+                value: "64MB".to_string()
+            })
+        );
+        assert_eq!(
+            classify(
+                b"parameter \"shared_buffers\" cannot be changed without restarting the server"
+            ),
+            Some(ConfigEventKind::RestartRequired {
+                name: "shared_buffers".to_string()
+            })
+        );
+        assert_eq!(
+            classify(b"configuration file \"/etc/postgresql/postgresql.conf\" contains errors; unaffected changes were applied"),
+            Some(ConfigEventKind::FileErrors {
+                file: "/etc/postgresql/postgresql.conf".to_string(),
+                outcome: "unaffected changes were applied".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_alter_system() {
+        let statement = alter_system_statement(b"statement: ALTER SYSTEM SET work_mem = '64MB';");
+        assert_eq!(
+            statement.and_then(|s| parse_alter_system(s, Some("postgres".to_string()))),
+            Some(ConfigEventKind::AlterSystem {
+                name: "work_mem".to_string(),
+                value: Some("'64MB'".to_string()),
+                user: Some("postgres".to_string())
+            })
+        );
+
+        let statement = alter_system_statement(b"statement: alter system reset ALL");
+        assert_eq!(
+            statement.and_then(|s| parse_alter_system(s, None)),
+            Some(ConfigEventKind::AlterSystem {
+                name: "all".to_string(),
+                value: None,
+                user: None
+            })
+        );
+    }
+}
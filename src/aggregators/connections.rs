@@ -2,19 +2,22 @@ use std::{any::Any, collections::HashMap, time::Duration};
 
 use chrono::{DateTime, Local, TimeZone};
 
-use crate::{aggregators::Aggregator, error::Result, format::Format, severity::Severity};
+use crate::{
+    aggregators::Aggregator, encoding, error::Result, format::Format, severity::Severity, timestamp,
+};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct ConnectionsAggregator {
-    total_connection_attempts: u16,
-    total_authenticated: u16,
-    total_authenticated_ssl: u16,
-    connection_failures: u16,
-    connections_by_host: HashMap<String, u16>,
-    connections_by_database: HashMap<String, u16>,
-    connections_by_user: HashMap<String, u16>,
-    connections_by_appname: HashMap<String, u16>,
-    connection_attempts_by_time_bucket: HashMap<String, u16>,
+    total_connection_attempts: u64,
+    total_authenticated: u64,
+    total_authenticated_ssl: u64,
+    connection_failures: u64,
+    connections_by_host: HashMap<String, u64>,
+    connections_by_database: HashMap<String, u64>,
+    connections_by_user: HashMap<String, u64>,
+    connections_by_appname: HashMap<String, u64>,
+    connection_attempts_by_time_bucket: HashMap<String, u64>,
     bucket_interval: Duration,
 }
 
@@ -43,11 +46,11 @@ impl Aggregator for ConnectionsAggregator {
         severity: Severity,
         log_time: DateTime<Local>,
     ) -> Result<()> {
-        let message =
-            fmt.message_from_bytes(record)
-                .ok_or(crate::Error::NotAbleToExtractMessage {
-                    record: String::from_utf8(record.to_vec()).unwrap(),
-                })?;
+        let message = fmt.message_from_bytes(record).ok_or_else(|| {
+            crate::Error::NotAbleToExtractMessage {
+                record: encoding::decode(record).into_owned(),
+            }
+        })?;
         if (severity == Severity::Fatal)
             && (memchr::memmem::find(record, b"password authentication failed").is_some()
                 || memchr::memmem::find(record, b"is not permitted to log in").is_some())
@@ -64,12 +67,12 @@ impl Aggregator for ConnectionsAggregator {
             self.total_connection_attempts += 1;
             let host = Format::host_from_bytes(record).unwrap_or(b"unknown");
             self.connections_by_host
-                .entry(String::from_utf8_lossy(host).to_string())
+                .entry(encoding::decode(host).to_string())
                 .and_modify(|count| *count += 1)
                 .or_insert(1);
 
             let bucket_time = round_floor(log_time, self.bucket_interval)?;
-            let bucket_time_str = bucket_time.to_string();
+            let bucket_time_str = timestamp::display(bucket_time);
             self.connection_attempts_by_time_bucket
                 .entry(bucket_time_str)
                 .and_modify(|count| *count += 1)
@@ -84,19 +87,19 @@ impl Aggregator for ConnectionsAggregator {
 
             let user = Format::user_from_bytes(record).unwrap_or(b"unknown");
             self.connections_by_user
-                .entry(String::from_utf8_lossy(user).to_string())
+                .entry(encoding::decode(user).to_string())
                 .and_modify(|count| *count += 1)
                 .or_insert(1);
 
             let db = Format::db_from_bytes(record).unwrap_or(b"unknown");
             self.connections_by_database
-                .entry(String::from_utf8_lossy(db).to_string())
+                .entry(encoding::decode(db).to_string())
                 .and_modify(|count| *count += 1)
                 .or_insert(1);
 
             let appname = Format::appname_from_bytes(record).unwrap_or(b"unknown");
             self.connections_by_appname
-                .entry(String::from_utf8_lossy(appname).to_string())
+                .entry(encoding::decode(appname).to_string())
                 .and_modify(|count| *count += 1)
                 .or_insert(1);
         }
@@ -177,6 +180,14 @@ impl Aggregator for ConnectionsAggregator {
         }
     }
 
+    fn kind(&self) -> &'static str {
+        "connections"
+    }
+
+    fn save_state(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(self)?)
+    }
+
     fn boxed_clone(&self) -> Box<dyn Aggregator> {
         Box::new(self.clone())
     }
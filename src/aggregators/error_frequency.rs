@@ -2,9 +2,10 @@ use std::{any::Any, collections::HashMap};
 
 use chrono::{DateTime, Local};
 
-use crate::{aggregators::Aggregator, error::Result, format::Format, severity::Severity};
+use crate::{aggregators::Aggregator, encoding, error::Result, format::Format, severity::Severity};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct ErrorFrequencyAggregator {
     // TODO: Check ablity to store u8 arrays directly to avoid UTF-8 conversion overhead
     counts: HashMap<String, u64>,
@@ -28,12 +29,12 @@ impl Aggregator for ErrorFrequencyAggregator {
         _severity: Severity,
         _log_time: DateTime<Local>,
     ) -> Result<()> {
-        let message =
-            fmt.message_from_bytes(record)
-                .ok_or(crate::Error::NotAbleToExtractMessage {
-                    record: String::from_utf8(record.to_vec()).unwrap(),
-                })?;
-        let message = String::from_utf8_lossy(message).to_string();
+        let message = fmt.message_from_bytes(record).ok_or_else(|| {
+            crate::Error::NotAbleToExtractMessage {
+                record: encoding::decode(record).into_owned(),
+            }
+        })?;
+        let message = encoding::decode(message).to_string();
 
         *self.counts.entry(message).or_insert(0) += 1;
         //// This code is executed in threads, so we cannot apply here the top-N logic directly
@@ -76,6 +77,14 @@ impl Aggregator for ErrorFrequencyAggregator {
         }
     }
 
+    fn kind(&self) -> &'static str {
+        "error_frequency"
+    }
+
+    fn save_state(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(self)?)
+    }
+
     fn boxed_clone(&self) -> Box<dyn Aggregator> {
         Box::new(self.clone())
     }
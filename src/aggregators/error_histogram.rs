@@ -2,9 +2,12 @@ use std::{any::Any, collections::BTreeMap, time::Duration};
 
 use chrono::{DateTime, Local, TimeZone};
 
-use crate::{aggregators::Aggregator, error::Result, format::Format, severity::Severity};
+use crate::{
+    aggregators::Aggregator, error::Result, format::Format, severity::Severity, timestamp,
+};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct ErrorHistogramAggregator {
     bucket_width: Duration,
     buckets: BTreeMap<i64, i64>,
@@ -74,7 +77,7 @@ impl Aggregator for ErrorHistogramAggregator {
             if let Some(time) = time {
                 println!(
                     "[{}] {}{} {}",
-                    time.format("%Y-%m-%d %H:%M:%S"),
+                    timestamp::display(time),
                     "#".repeat(filled),
                     "-".repeat(empty),
                     count
@@ -83,6 +86,14 @@ impl Aggregator for ErrorHistogramAggregator {
         }
     }
 
+    fn kind(&self) -> &'static str {
+        "error_histogram"
+    }
+
+    fn save_state(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(self)?)
+    }
+
     fn boxed_clone(&self) -> Box<dyn Aggregator> {
         Box::new(self.clone())
     }
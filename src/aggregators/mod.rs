@@ -1,15 +1,33 @@
+mod archive;
+mod audit;
+mod config;
 mod connections;
 mod error_frequency;
 mod error_histogram;
+mod plans;
+mod query_id;
+mod replication;
+mod slow_queries;
+mod tables;
 mod top_slow_query;
+mod workload;
 
 use std::any::Any;
 
+pub use archive::ArchiveAggregator;
+pub use audit::AuditAggregator;
 use chrono::{DateTime, Local};
+pub use config::ConfigAggregator;
 pub use connections::ConnectionsAggregator;
 pub use error_frequency::ErrorFrequencyAggregator;
 pub use error_histogram::ErrorHistogramAggregator;
+pub use plans::PlansAggregator;
+pub use query_id::QueryIdAggregator;
+pub use replication::ReplicationAggregator;
+pub use slow_queries::SlowQueries;
+pub use tables::{TablesAggregator, TablesOrder};
 pub use top_slow_query::TopSlowQueries;
+pub use workload::WorkloadAggregator;
 
 use crate::{error::Result, format::Format, severity::Severity};
 
@@ -22,7 +40,21 @@ pub trait Aggregator: Send + Sync {
         log_time: DateTime<Local>,
     ) -> Result<()>;
     fn merge_box(&mut self, other: &dyn Aggregator);
+    /// Completes records still waiting for a record of a following chunk, once all
+    /// records are fed. Called by `print` and before the state is saved.
+    fn finish(&mut self) {}
     fn print(&mut self);
+    /// Prints the records of a streaming report that are complete so far, as they come in.
+    /// Records waiting for a record of a following chunk stay until it's merged.
+    fn print_ready(&mut self) {}
+    /// Whether the report lists records rather than summarizing them. When following
+    /// logs such reports are printed as records come in, others are refreshed periodically.
+    fn streams(&self) -> bool {
+        false
+    }
+    /// Name the state is saved under, see `crate::state`
+    fn kind(&self) -> &'static str;
+    fn save_state(&self) -> Result<serde_json::Value>;
     fn boxed_clone(&self) -> Box<dyn Aggregator>;
     fn as_any(&self) -> &dyn Any;
 }
@@ -0,0 +1,279 @@
+use std::{
+    any::Any,
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+
+use chrono::{DateTime, Local};
+
+use crate::{
+    aggregators::Aggregator, duration::extract_duration, error::Result, format::Format,
+    plan::extract_plan, severity::Severity, sql::fingerprint, timestamp,
+};
+use serde::{Deserialize, Serialize};
+
+/// Sequential scans reading at least this many rows are reported
+const LARGE_SEQ_SCAN_ROWS: f64 = 10_000.0;
+/// Estimated vs actual rows differing by this factor are reported
+const MISESTIMATE_FACTOR: f64 = 10.0;
+/// ...unless both estimate and actual rows are below this
+const MISESTIMATE_MIN_ROWS: f64 = 100.0;
+/// Most expensive nodes shown per query
+const EXPENSIVE_NODES: usize = 3;
+
+#[derive(Clone, Serialize, Deserialize)]
+struct PlanShape {
+    executions: u64,
+    total_duration: Duration,
+    first_seen: DateTime<Local>,
+    last_seen: DateTime<Local>,
+    root: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct QueryPlans {
+    query_text: String,
+    executions: u64,
+    total_duration: Duration,
+    max_duration: Duration,
+    shapes: HashMap<String, PlanShape>,
+    /// Most expensive nodes (self time / cost, label) of the slowest execution
+    expensive_nodes: Vec<(f64, String)>,
+    /// Seq Scan label -> max rows scanned
+    large_seq_scans: BTreeMap<String, f64>,
+    /// Node label -> (estimated rows, actual rows) with the worst ratio seen
+    misestimates: BTreeMap<String, (f64, f64)>,
+}
+
+/// Groups auto_explain plans per query fingerprint and points out expensive nodes,
+/// large sequential scans, row misestimates and plan flips.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PlansAggregator {
+    limit: usize,
+    queries: HashMap<String, QueryPlans>,
+}
+
+impl PlansAggregator {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            queries: HashMap::new(),
+        }
+    }
+}
+
+impl Aggregator for PlansAggregator {
+    fn update(
+        &mut self,
+        record: &[u8],
+        fmt: &Format,
+        _severity: Severity,
+        log_time: DateTime<Local>,
+    ) -> Result<()> {
+        let Some(plan) = extract_plan(record, fmt) else {
+            return Ok(());
+        };
+        let duration = extract_duration(record).unwrap_or_default();
+
+        let query = self
+            .queries
+            .entry(fingerprint(&plan.query_text))
+            .or_insert_with(|| QueryPlans {
+                query_text: plan.query_text.clone(),
+                executions: 0,
+                total_duration: Duration::ZERO,
+                max_duration: Duration::ZERO,
+                shapes: HashMap::new(),
+                expensive_nodes: Vec::new(),
+                large_seq_scans: BTreeMap::new(),
+                misestimates: BTreeMap::new(),
+            });
+
+        query.executions += 1;
+        query.total_duration += duration;
+
+        let shape = query
+            .shapes
+            .entry(plan.shape())
+            .or_insert_with(|| PlanShape {
+                executions: 0,
+                total_duration: Duration::ZERO,
+                first_seen: log_time,
+                last_seen: log_time,
+                root: plan.nodes[0].label.clone(),
+            });
+        shape.executions += 1;
+        shape.total_duration += duration;
+        shape.first_seen = shape.first_seen.min(log_time);
+        shape.last_seen = shape.last_seen.max(log_time);
+
+        if duration >= query.max_duration {
+            query.max_duration = duration;
+            let mut nodes: Vec<(f64, String)> = plan
+                .nodes
+                .iter()
+                .enumerate()
+                .map(|(i, n)| (n.self_time(&plan.nodes, i), n.label.clone()))
+                .collect();
+            nodes.sort_by(|a, b| b.0.total_cmp(&a.0));
+            nodes.truncate(EXPENSIVE_NODES);
+            query.expensive_nodes = nodes;
+        }
+
+        for node in &plan.nodes {
+            if node.node_type.ends_with("Seq Scan") && node.rows_scanned() >= LARGE_SEQ_SCAN_ROWS {
+                let rows = query
+                    .large_seq_scans
+                    .entry(node.label.clone())
+                    .or_insert(0.0);
+                *rows = rows.max(node.rows_scanned());
+            }
+
+            if let Some(actual) = node.actual_rows
+                && node.loops > 0.0
+                && misestimate_ratio(node.plan_rows, actual) >= MISESTIMATE_FACTOR
+                && node.plan_rows.max(actual) >= MISESTIMATE_MIN_ROWS
+            {
+                let worst = query
+                    .misestimates
+                    .entry(node.label.clone())
+                    .or_insert((node.plan_rows, actual));
+                if misestimate_ratio(node.plan_rows, actual) > misestimate_ratio(worst.0, worst.1) {
+                    *worst = (node.plan_rows, actual);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn merge_box(&mut self, other: &dyn Aggregator) {
+        let other = other
+            .as_any()
+            .downcast_ref::<PlansAggregator>()
+            .expect("Aggregator type mismatch");
+
+        for (key, theirs) in &other.queries {
+            let Some(mine) = self.queries.get_mut(key) else {
+                self.queries.insert(key.clone(), theirs.clone());
+                continue;
+            };
+
+            mine.executions += theirs.executions;
+            mine.total_duration += theirs.total_duration;
+            if theirs.max_duration > mine.max_duration {
+                mine.max_duration = theirs.max_duration;
+                mine.expensive_nodes.clone_from(&theirs.expensive_nodes);
+            }
+
+            for (shape_key, shape) in &theirs.shapes {
+                mine.shapes
+                    .entry(shape_key.clone())
+                    .and_modify(|s| {
+                        s.executions += shape.executions;
+                        s.total_duration += shape.total_duration;
+                        s.first_seen = s.first_seen.min(shape.first_seen);
+                        s.last_seen = s.last_seen.max(shape.last_seen);
+                    })
+                    .or_insert_with(|| shape.clone());
+            }
+
+            for (label, rows) in &theirs.large_seq_scans {
+                let mine_rows = mine.large_seq_scans.entry(label.clone()).or_insert(0.0);
+                *mine_rows = mine_rows.max(*rows);
+            }
+
+            for (label, (estimated, actual)) in &theirs.misestimates {
+                let worst = mine
+                    .misestimates
+                    .entry(label.clone())
+                    .or_insert((*estimated, *actual));
+                if misestimate_ratio(*estimated, *actual) > misestimate_ratio(worst.0, worst.1) {
+                    *worst = (*estimated, *actual);
+                }
+            }
+        }
+    }
+
+    fn print(&mut self) {
+        let mut queries: Vec<_> = self.queries.values().collect();
+        queries.sort_by_key(|q| std::cmp::Reverse(q.total_duration));
+
+        println!(
+            "Plans for top {} queries by total duration:",
+            queries.len().min(self.limit)
+        );
+        for query in queries.into_iter().take(self.limit) {
+            println!();
+            println!("=== {}", query.query_text);
+            println!(
+                "  executions: {}  total: {:?}  max: {:?}  plans: {}{}",
+                query.executions,
+                query.total_duration,
+                query.max_duration,
+                query.shapes.len(),
+                if query.shapes.len() > 1 {
+                    "  (plan flip)"
+                } else {
+                    ""
+                }
+            );
+
+            let mut shapes: Vec<_> = query.shapes.values().collect();
+            shapes.sort_by_key(|s| s.first_seen);
+            for (i, shape) in shapes.iter().enumerate() {
+                let avg = shape
+                    .total_duration
+                    .checked_div(u32::try_from(shape.executions).unwrap_or(u32::MAX))
+                    .unwrap_or_default();
+                println!(
+                    "  Plan #{} [{} - {}] executions: {}  avg: {avg:?}  {}",
+                    i + 1,
+                    timestamp::display(shape.first_seen),
+                    timestamp::display(shape.last_seen),
+                    shape.executions,
+                    shape.root
+                );
+            }
+
+            println!("  Most expensive nodes (slowest execution):");
+            for (cost, label) in &query.expensive_nodes {
+                println!("    {cost:>12.3}  {label}");
+            }
+
+            if !query.large_seq_scans.is_empty() {
+                println!("  Sequential scans on large row counts:");
+                for (label, rows) in &query.large_seq_scans {
+                    println!("    {rows:>12.0}  {label}");
+                }
+            }
+
+            if !query.misestimates.is_empty() {
+                println!("  Row misestimates (estimated -> actual):");
+                for (label, (estimated, actual)) in &query.misestimates {
+                    println!("    {estimated:>12.0} -> {actual:<12.0} {label}");
+                }
+            }
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        "plans"
+    }
+
+    fn save_state(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(self)?)
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Aggregator> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+fn misestimate_ratio(estimated: f64, actual: f64) -> f64 {
+    estimated.max(actual) / estimated.min(actual).max(1.0)
+}
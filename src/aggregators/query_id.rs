@@ -0,0 +1,141 @@
+use std::{any::Any, cmp::Reverse, collections::HashMap, time::Duration};
+
+use chrono::{DateTime, Local};
+
+use crate::{
+    aggregators::Aggregator,
+    encoding,
+    error::Result,
+    format::Format,
+    severity::Severity,
+    statement::{StatementTracker, TimedStatement, statement_message, statement_phase},
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct QueryIdStats {
+    calls: u64,
+    total: Duration,
+    max: Duration,
+    /// Statement of the slowest execution
+    example: String,
+}
+
+/// Aggregates statement durations per query_id (`compute_query_id`, `%Q`)
+#[derive(Clone, Serialize, Deserialize)]
+pub struct QueryIdAggregator {
+    limit: usize,
+    statements: StatementTracker,
+    stats: HashMap<String, QueryIdStats>,
+}
+
+impl QueryIdAggregator {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            statements: StatementTracker::new(false),
+            stats: HashMap::new(),
+        }
+    }
+}
+
+fn add_statement(
+    stats: &mut HashMap<String, QueryIdStats>,
+    fmt: Format,
+) -> impl FnMut(TimedStatement) {
+    move |statement| {
+        let Some(query_id) = fmt.query_id_from_bytes(&statement.record) else {
+            return;
+        };
+        let entry = stats
+            .entry(encoding::decode(query_id).to_string())
+            .or_default();
+        entry.calls += 1;
+        entry.total += statement.duration;
+        if statement.duration >= entry.max {
+            entry.max = statement.duration;
+            let message = statement_message(&statement.record, &fmt);
+            let sql = statement_phase(message).map_or(message, |(_, sql)| sql);
+            entry.example = encoding::decode(sql).trim().to_string();
+        }
+    }
+}
+
+impl Aggregator for QueryIdAggregator {
+    fn update(
+        &mut self,
+        record: &[u8],
+        fmt: &Format,
+        _severity: Severity,
+        log_time: DateTime<Local>,
+    ) -> Result<()> {
+        self.statements
+            .push(record, fmt, log_time, add_statement(&mut self.stats, *fmt));
+        Ok(())
+    }
+
+    fn merge_box(&mut self, other: &dyn Aggregator) {
+        let other = other
+            .as_any()
+            .downcast_ref::<QueryIdAggregator>()
+            .expect("Aggregator type mismatch");
+
+        let fmt = other.statements.format().unwrap_or(Format::Plain);
+        self.statements
+            .merge(&other.statements, add_statement(&mut self.stats, fmt));
+
+        for (query_id, theirs) in &other.stats {
+            let mine = self.stats.entry(query_id.clone()).or_default();
+            mine.calls += theirs.calls;
+            mine.total += theirs.total;
+            if theirs.max >= mine.max {
+                mine.max = theirs.max;
+                mine.example.clone_from(&theirs.example);
+            }
+        }
+    }
+
+    fn finish(&mut self) {
+        let fmt = self.statements.format().unwrap_or(Format::Plain);
+        self.statements.finish(add_statement(&mut self.stats, fmt));
+    }
+
+    fn print(&mut self) {
+        self.finish();
+
+        let mut items: Vec<_> = self.stats.iter().collect();
+        items.sort_by_key(|(_, s)| Reverse(s.total));
+
+        println!(
+            "Top {} query_ids by total duration:",
+            items.len().min(self.limit)
+        );
+        for (query_id, stats) in items.into_iter().take(self.limit) {
+            let avg = stats
+                .total
+                .checked_div(u32::try_from(stats.calls).unwrap_or(u32::MAX))
+                .unwrap_or_default();
+            println!(
+                "--- query_id {query_id}  calls: {}  total: {:?}  avg: {avg:?}  max: {:?} ---",
+                stats.calls, stats.total, stats.max
+            );
+            println!("{}", stats.example);
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        "query_id"
+    }
+
+    fn save_state(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(self)?)
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Aggregator> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
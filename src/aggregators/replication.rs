@@ -0,0 +1,560 @@
+use std::{
+    any::Any,
+    collections::{BTreeMap, HashMap},
+};
+
+use chrono::{DateTime, Local};
+use memchr::memmem;
+
+use crate::{
+    aggregators::Aggregator,
+    encoding,
+    error::Result,
+    format::{Format, lossy_line, number_after, quoted_after, wal_segment_name},
+    severity::Severity,
+    timestamp,
+};
+use serde::{Deserialize, Serialize};
+
+const NO_PEER: &str = "unknown";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+enum ReplicationEventKind {
+    /// walreceiver started streaming from the primary, on the given timeline
+    Connect(Option<u32>),
+    /// walreceiver lost / ended the stream, with reason
+    Disconnect(String),
+    /// walreceiver was not able to connect to the primary, with reason
+    ConnectFailure(String),
+    /// "requested WAL segment ... has already been removed", with segment name
+    SegmentRemoved(String),
+    /// Switch to a new timeline
+    TimelineSwitch(u32),
+    /// Replication slot invalidated, with optional reason
+    SlotInvalidated(Option<String>),
+    /// Query canceled / session terminated due to recovery conflict, with optional conflict type
+    RecoveryConflict(Option<String>),
+    /// Logical replication worker started for the subscription
+    WorkerStarted,
+    /// Error reported by a logical replication worker
+    ApplyError(String),
+    /// Plain log DETAIL line to be attached to the preceding event of the same pid
+    Detail(String),
+    /// Timeline mentioned by walreceiver / recovery messages without a switch
+    OnTimeline(u32),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct ReplicationEvent {
+    log_time: DateTime<Local>,
+    pid: Option<String>,
+    peer: Option<String>,
+    kind: ReplicationEventKind,
+}
+
+#[derive(Default)]
+struct PeerStats {
+    connects: u64,
+    disconnects: BTreeMap<String, u64>,
+    connect_failures: BTreeMap<String, u64>,
+    removed_segments: Vec<String>,
+    slot_invalidations: BTreeMap<String, u64>,
+    recovery_conflicts: BTreeMap<String, u64>,
+    worker_starts: u64,
+    apply_errors: BTreeMap<String, u64>,
+}
+
+/// Summarizes replication and recovery health: walreceiver connects / disconnects,
+/// removed WAL segments, timeline switches, slot invalidations, recovery conflicts
+/// and logical replication worker errors, grouped per timeline and per peer.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct ReplicationAggregator {
+    events: Vec<ReplicationEvent>,
+}
+
+impl ReplicationAggregator {
+    pub fn new() -> Self {
+        Self { events: Vec::new() }
+    }
+}
+
+impl Aggregator for ReplicationAggregator {
+    fn update(
+        &mut self,
+        record: &[u8],
+        fmt: &Format,
+        severity: Severity,
+        log_time: DateTime<Local>,
+    ) -> Result<()> {
+        let Some(message) = fmt.message_from_bytes(record) else {
+            return Ok(());
+        };
+        let detail = fmt.detail_from_bytes(record);
+        let pid = fmt
+            .pid_from_bytes(record)
+            .map(|p| encoding::decode(p).to_string());
+
+        // Plain logs write DETAIL as a separate record, keep only the ones we care about
+        if matches!(fmt, Format::Plain)
+            && let Some(detail) = detail
+        {
+            if conflict_type(detail).is_some()
+                || slot_invalidation_reason(detail).is_some()
+                || memmem::find(detail, b"End of WAL reached on timeline").is_some()
+            {
+                self.events.push(ReplicationEvent {
+                    log_time,
+                    pid,
+                    peer: None,
+                    kind: ReplicationEventKind::Detail(lossy_line(detail)),
+                });
+            }
+            return Ok(());
+        }
+
+        let Some((kind, peer)) = classify(message, detail, severity) else {
+            return Ok(());
+        };
+
+        self.events.push(ReplicationEvent {
+            log_time,
+            pid,
+            peer,
+            kind,
+        });
+        Ok(())
+    }
+
+    fn merge_box(&mut self, other: &dyn Aggregator) {
+        let other = other
+            .as_any()
+            .downcast_ref::<ReplicationAggregator>()
+            .expect("Aggregator type mismatch");
+
+        self.events.extend(other.events.iter().cloned());
+    }
+
+    fn print(&mut self) {
+        // Chunks are processed in parallel, so order events before replaying them
+        self.events.sort_by_key(|e| e.log_time);
+
+        let mut timeline: Option<u32> = None;
+        let mut switches: Vec<(DateTime<Local>, Option<u32>, u32)> = Vec::new();
+        let mut groups: BTreeMap<Option<u32>, BTreeMap<String, PeerStats>> = BTreeMap::new();
+        let mut last_primary: Option<String> = None;
+        let mut subscription_by_pid: HashMap<String, String> = HashMap::new();
+        // Index of the last event per pid waiting for a DETAIL line
+        let mut pending_detail: HashMap<String, usize> = HashMap::new();
+
+        let mut events = std::mem::take(&mut self.events);
+        for i in 0..events.len() {
+            if let ReplicationEventKind::Detail(text) = &events[i].kind {
+                let text = text.clone();
+                let target = events[i]
+                    .pid
+                    .as_ref()
+                    .and_then(|p| pending_detail.remove(p));
+                if let Some(target) = target {
+                    attach_detail(&mut events[target], &text);
+                }
+                continue;
+            }
+
+            if let Some(pid) = &events[i].pid
+                && matches!(
+                    events[i].kind,
+                    ReplicationEventKind::RecoveryConflict(None)
+                        | ReplicationEventKind::SlotInvalidated(None)
+                        | ReplicationEventKind::Disconnect(_)
+                )
+            {
+                pending_detail.insert(pid.clone(), i);
+            }
+        }
+
+        for event in events {
+            let peer = match &event.kind {
+                ReplicationEventKind::Connect(_)
+                | ReplicationEventKind::Disconnect(_)
+                | ReplicationEventKind::ConnectFailure(_)
+                | ReplicationEventKind::SegmentRemoved(_) => {
+                    if event.peer.is_some() {
+                        last_primary.clone_from(&event.peer);
+                    }
+                    event
+                        .peer
+                        .clone()
+                        .or_else(|| last_primary.clone())
+                        .unwrap_or_else(|| "primary".to_string())
+                }
+                ReplicationEventKind::WorkerStarted => {
+                    if let (Some(pid), Some(peer)) = (&event.pid, &event.peer) {
+                        subscription_by_pid.insert(pid.clone(), peer.clone());
+                    }
+                    event.peer.clone().unwrap_or_else(|| NO_PEER.to_string())
+                }
+                ReplicationEventKind::ApplyError(_) => event
+                    .peer
+                    .clone()
+                    .or_else(|| {
+                        event
+                            .pid
+                            .as_ref()
+                            .and_then(|p| subscription_by_pid.get(p).cloned())
+                    })
+                    .unwrap_or_else(|| NO_PEER.to_string()),
+                _ => event.peer.clone().unwrap_or_else(|| "local".to_string()),
+            };
+
+            match event.kind {
+                ReplicationEventKind::OnTimeline(tli) => {
+                    timeline = Some(tli);
+                }
+                ReplicationEventKind::TimelineSwitch(tli) => {
+                    if timeline != Some(tli) {
+                        switches.push((event.log_time, timeline, tli));
+                    }
+                    timeline = Some(tli);
+                }
+                ReplicationEventKind::Detail(_) => {}
+                kind => {
+                    if let ReplicationEventKind::Connect(Some(tli)) = kind {
+                        timeline = Some(tli);
+                    }
+                    let stats = groups.entry(timeline).or_default().entry(peer).or_default();
+                    match kind {
+                        ReplicationEventKind::Connect(_) => stats.connects += 1,
+                        ReplicationEventKind::Disconnect(reason) => {
+                            *stats.disconnects.entry(reason).or_insert(0) += 1;
+                        }
+                        ReplicationEventKind::ConnectFailure(reason) => {
+                            *stats.connect_failures.entry(reason).or_insert(0) += 1;
+                        }
+                        ReplicationEventKind::SegmentRemoved(segment) => {
+                            stats.removed_segments.push(segment);
+                        }
+                        ReplicationEventKind::SlotInvalidated(reason) => {
+                            *stats
+                                .slot_invalidations
+                                .entry(reason.unwrap_or_else(|| "unknown reason".to_string()))
+                                .or_insert(0) += 1;
+                        }
+                        ReplicationEventKind::RecoveryConflict(conflict) => {
+                            *stats
+                                .recovery_conflicts
+                                .entry(conflict.unwrap_or_else(|| "unknown".to_string()))
+                                .or_insert(0) += 1;
+                        }
+                        ReplicationEventKind::WorkerStarted => stats.worker_starts += 1,
+                        ReplicationEventKind::ApplyError(error) => {
+                            *stats.apply_errors.entry(error).or_insert(0) += 1;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        if !switches.is_empty() {
+            println!("Timeline switches:");
+        }
+        for (log_time, from, to) in &switches {
+            let from = from.map_or_else(|| "?".to_string(), |f| f.to_string());
+            println!("  [{}] {from} -> {to}", timestamp::display(*log_time));
+        }
+
+        for (timeline, peers) in &groups {
+            match timeline {
+                Some(tli) => println!("Timeline {tli}:"),
+                None => println!("Timeline unknown:"),
+            }
+            for (peer, stats) in peers {
+                println!("  Peer {peer}:");
+                if stats.connects > 0 {
+                    println!("    Streaming started: {}", stats.connects);
+                }
+                print_counts("Disconnects", &stats.disconnects);
+                print_counts("Connect failures", &stats.connect_failures);
+                if !stats.removed_segments.is_empty() {
+                    println!(
+                        "    Requested WAL segments already removed: {}",
+                        stats.removed_segments.len()
+                    );
+                    for segment in &stats.removed_segments {
+                        println!("      {segment}");
+                    }
+                }
+                print_counts("Slot invalidations", &stats.slot_invalidations);
+                print_counts("Recovery conflicts", &stats.recovery_conflicts);
+                if stats.worker_starts > 0 {
+                    println!(
+                        "    Logical replication worker starts: {}",
+                        stats.worker_starts
+                    );
+                }
+                print_counts("Logical replication worker errors", &stats.apply_errors);
+            }
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        "replication"
+    }
+
+    fn save_state(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(self)?)
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Aggregator> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+fn print_counts(title: &str, counts: &BTreeMap<String, u64>) {
+    if counts.is_empty() {
+        return;
+    }
+    println!("    {title}: {}", counts.values().sum::<u64>());
+    let mut entries: Vec<_> = counts.iter().collect();
+    entries.sort_by(|a, b| b.1.cmp(a.1));
+    for (reason, count) in entries {
+        println!("      {count:>6}  {reason}");
+    }
+}
+
+fn attach_detail(event: &mut ReplicationEvent, detail: &str) {
+    let bytes = detail.as_bytes();
+    match &mut event.kind {
+        ReplicationEventKind::RecoveryConflict(conflict @ None) => {
+            *conflict = conflict_type(bytes).map(str::to_string);
+        }
+        ReplicationEventKind::SlotInvalidated(reason @ None) => {
+            *reason = slot_invalidation_reason(bytes);
+        }
+        ReplicationEventKind::Disconnect(reason) => {
+            if let Some(tli) = number_after(bytes, b"End of WAL reached on timeline ") {
+                reason.push_str(&format!(" (end of WAL on timeline {tli})"));
+            }
+        }
+        _ => {}
+    }
+}
+
+fn classify(
+    message: &[u8],
+    detail: Option<&[u8]>,
+    severity: Severity,
+) -> Option<(ReplicationEventKind, Option<String>)> {
+    use ReplicationEventKind as K;
+
+    if let Some(pos) = memmem::find(message, b"started streaming WAL from primary") {
+        let tli = number_after(&message[pos..], b"on timeline ");
+        return Some((K::Connect(tli), None));
+    }
+
+    if memmem::find(message, b"has already been removed").is_some()
+        && let Some(segment) = wal_segment_name(message)
+    {
+        return Some((K::SegmentRemoved(segment), primary_peer(message)));
+    }
+
+    if message.starts_with(b"replication terminated by primary server") {
+        let mut reason = "replication terminated by primary server".to_string();
+        if let Some(tli) = detail.and_then(|d| number_after(d, b"End of WAL reached on timeline "))
+        {
+            reason.push_str(&format!(" (end of WAL on timeline {tli})"));
+        }
+        return Some((K::Disconnect(reason), None));
+    }
+    if let Some(reason) = message.strip_prefix(b"could not receive data from WAL stream: ") {
+        return Some((K::Disconnect(lossy_line(reason)), None));
+    }
+    if message.starts_with(b"terminating walreceiver") {
+        return Some((K::Disconnect(lossy_line(message)), None));
+    }
+    if let Some(reason) = message.strip_prefix(b"could not connect to the primary server: ") {
+        return Some((
+            K::ConnectFailure(connect_failure_reason(reason)),
+            primary_peer(reason),
+        ));
+    }
+
+    for needle in [
+        b"selected new timeline ID: ".as_slice(),
+        b"new target timeline is ".as_slice(),
+    ] {
+        if let Some(tli) = number_after(message, needle) {
+            return Some((K::TimelineSwitch(tli), None));
+        }
+    }
+
+    if memmem::find(message, b"invalidating").is_some()
+        && let Some(slot) = quoted_after(message, b"slot \"")
+    {
+        let reason = memmem::find(message, b" because ")
+            .map(|pos| lossy_line(&message[pos + b" because ".len()..]))
+            .or_else(|| detail.and_then(slot_invalidation_reason));
+        return Some((K::SlotInvalidated(reason), Some(format!("slot {slot}"))));
+    }
+
+    if memmem::find(message, b"due to conflict with recovery").is_some() {
+        let conflict = detail.and_then(conflict_type).map(str::to_string);
+        return Some((K::RecoveryConflict(conflict), None));
+    }
+
+    let subscription = quoted_after(message, b"subscription \"");
+    if memmem::find(message, b"logical replication").is_some()
+        && memmem::find(message, b"has started").is_some()
+        && subscription.is_some()
+    {
+        return Some((
+            K::WorkerStarted,
+            subscription.map(|s| format!("subscription {s}")),
+        ));
+    }
+
+    if severity >= Severity::Error
+        && (subscription.is_some()
+            || memmem::find(message, b"logical replication").is_some()
+            || memmem::find(message, b"replication origin").is_some()
+            || memmem::find(message, b"could not start WAL streaming").is_some())
+    {
+        return Some((
+            K::ApplyError(lossy_line(message)),
+            subscription.map(|s| format!("subscription {s}")),
+        ));
+    }
+
+    // Timelines mentioned without a switch, keep track of the current one
+    for needle in [
+        b"End of WAL reached on timeline ".as_slice(),
+        b"on timeline ID ".as_slice(),
+        b"on timeline ".as_slice(),
+    ] {
+        if let Some(tli) = number_after(message, needle) {
+            return Some((K::OnTimeline(tli), None));
+        }
+    }
+
+    None
+}
+
+fn conflict_type(text: &[u8]) -> Option<&'static str> {
+    static CONFLICTS: &[(&[u8], &str)] = &[
+        (b"row versions that must be removed", "snapshot"),
+        (b"holding a relation lock", "lock"),
+        (b"holding shared buffer pin", "bufferpin"),
+        (b"buffer deadlock with recovery", "deadlock"),
+        (b"tablespace that must be dropped", "tablespace"),
+        (b"database that must be dropped", "database"),
+        (b"logical replication slot", "logical slot"),
+    ];
+
+    CONFLICTS
+        .iter()
+        .find(|(needle, _)| memmem::find(text, needle).is_some())
+        .map(|(_, name)| *name)
+}
+
+fn slot_invalidation_reason(text: &[u8]) -> Option<String> {
+    if memmem::find(text, b"exceeds the limit").is_some()
+        || memmem::find(text, b"max_slot_wal_keep_size").is_some()
+    {
+        return Some("max_slot_wal_keep_size exceeded".to_string());
+    }
+    if memmem::find(text, b"conflicted with xid horizon").is_some() {
+        return Some("conflict with xid horizon".to_string());
+    }
+    if memmem::find(text, b"wal_level").is_some() {
+        return Some("insufficient wal_level".to_string());
+    }
+    if memmem::find(text, b"idle_replication_slot_timeout").is_some()
+        || memmem::find(text, b"inactive since").is_some()
+    {
+        return Some("idle timeout".to_string());
+    }
+    None
+}
+
+fn connect_failure_reason(reason: &[u8]) -> String {
+    // "connection to server at "h", port p failed: <reason>"
+    let reason =
+        memmem::find(reason, b"failed: ").map_or(reason, |pos| &reason[pos + b"failed: ".len()..]);
+    lossy_line(reason)
+}
+
+fn primary_peer(message: &[u8]) -> Option<String> {
+    if let Some(host) = quoted_after(message, b"server at \"") {
+        let port = number_after(message, b"port ");
+        return Some(match port {
+            Some(port) => format!("{host}:{port}"),
+            None => host,
+        });
+    }
+    quoted_after(message, b"server on socket \"").or_else(|| quoted_after(message, b"host \""))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let (kind, peer) = classify(
+            b"could not connect to the primary server: connection to server at \"127.0.0.1\", port 5433 failed: server closed the connection unexpectedly",
+            None,
+            Severity::Fatal,
+        )
+        .unwrap();
+        assert_eq!(
+            kind,
+            ReplicationEventKind::ConnectFailure(
+                "server closed the connection unexpectedly".to_string()
+            )
+        );
+        assert_eq!(peer, Some("127.0.0.1:5433".to_string()));
+
+        let (kind, _) = classify(
+            b"could not receive data from WAL stream: ERROR:  requested WAL segment 000000010000000000000003 has already been removed",
+            None,
+            Severity::Fatal,
+        )
+        .unwrap();
+        assert_eq!(
+            kind,
+            ReplicationEventKind::SegmentRemoved("000000010000000000000003".to_string())
+        );
+
+        let (kind, _) = classify(b"selected new timeline ID: 2", None, Severity::Log).unwrap();
+        assert_eq!(kind, ReplicationEventKind::TimelineSwitch(2));
+
+        let (kind, _) = classify(
+            b"terminating connection due to conflict with recovery",
+            Some(b"User was holding shared buffer pin for too long."),
+            Severity::Fatal,
+        )
+        .unwrap();
+        assert_eq!(
+            kind,
+            ReplicationEventKind::RecoveryConflict(Some("bufferpin".to_string()))
+        );
+
+        let (kind, peer) = classify(
+            b"invalidating obsolete replication slot \"standby_1\"",
+            Some(b"The slot's restart_lsn 0/2000000 exceeds the limit by 1024 bytes."),
+            Severity::Log,
+        )
+        .unwrap();
+        assert_eq!(
+            kind,
+            ReplicationEventKind::SlotInvalidated(Some(
+                "max_slot_wal_keep_size exceeded".to_string()
+            ))
+        );
+        assert_eq!(peer, Some("slot standby_1".to_string()));
+    }
+}
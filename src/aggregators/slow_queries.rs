@@ -0,0 +1,118 @@
+use std::{
+    any::Any,
+    io::{self, Write},
+    time::Duration,
+};
+
+use chrono::{DateTime, Local};
+
+use crate::{
+    aggregators::Aggregator,
+    encoding,
+    error::Result,
+    format::Format,
+    severity::Severity,
+    statement::{StatementTracker, TimedStatement},
+    timestamp,
+};
+use serde::{Deserialize, Serialize};
+
+/// Lists statements slower than the threshold in log order. Bare `log_duration` records
+/// get the statement text of their backend attached, and bind parameters can be inlined.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SlowQueries {
+    treshold: Duration,
+    statements: StatementTracker,
+    records: Vec<(DateTime<Local>, Vec<u8>)>,
+}
+
+impl SlowQueries {
+    pub fn new(treshold: Duration, inline_params: bool) -> Self {
+        Self {
+            treshold,
+            statements: StatementTracker::new(inline_params),
+            records: Vec::new(),
+        }
+    }
+}
+
+fn collect(
+    records: &mut Vec<(DateTime<Local>, Vec<u8>)>,
+    treshold: Duration,
+) -> impl FnMut(TimedStatement) {
+    move |statement| {
+        if statement.duration > treshold {
+            records.push((statement.log_time, statement.record.into_owned()));
+        }
+    }
+}
+
+impl Aggregator for SlowQueries {
+    fn update(
+        &mut self,
+        record: &[u8],
+        fmt: &Format,
+        _severity: Severity,
+        log_time: DateTime<Local>,
+    ) -> Result<()> {
+        self.statements.push(
+            record,
+            fmt,
+            log_time,
+            collect(&mut self.records, self.treshold),
+        );
+        Ok(())
+    }
+
+    fn merge_box(&mut self, other: &dyn Aggregator) {
+        let other = other
+            .as_any()
+            .downcast_ref::<SlowQueries>()
+            .expect("Aggregator type mismatch");
+
+        self.statements
+            .merge(&other.statements, collect(&mut self.records, self.treshold));
+        self.records.extend(other.records.iter().cloned());
+    }
+
+    fn finish(&mut self) {
+        self.statements
+            .finish(collect(&mut self.records, self.treshold));
+    }
+
+    fn print(&mut self) {
+        self.finish();
+        self.print_ready();
+    }
+
+    fn print_ready(&mut self) {
+        // Durations paired across chunks are emitted late, stable sort keeps the file order
+        self.records.sort_by_key(|(log_time, _)| *log_time);
+        let mut printed = Vec::new();
+        for (_, record) in self.records.drain(..) {
+            timestamp::write_record(&encoding::printable(&record), &mut printed);
+            printed.push(b'\n');
+        }
+        let _ = io::stdout().write_all(&printed);
+    }
+
+    fn streams(&self) -> bool {
+        true
+    }
+
+    fn kind(&self) -> &'static str {
+        "slow_queries"
+    }
+
+    fn save_state(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(self)?)
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Aggregator> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -0,0 +1,298 @@
+use std::{
+    any::Any,
+    cmp::Reverse,
+    collections::{BTreeSet, HashMap},
+    time::Duration,
+};
+
+use aho_corasick::AhoCorasick;
+use chrono::{DateTime, Local};
+
+use crate::{
+    aggregators::Aggregator,
+    encoding,
+    error::Result,
+    format::{Format, unescape_csv_quotes},
+    severity::Severity,
+    sql::referenced_relations,
+    statement::{Phase, StatementTracker, TimedStatement, statement_message, statement_phase},
+};
+use serde::{Deserialize, Serialize};
+
+type Pid = Option<Vec<u8>>;
+
+/// How the tables are ranked
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TablesOrder {
+    Duration,
+    Calls,
+    Locks,
+}
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+struct TableStats {
+    calls: u64,
+    duration: Duration,
+    lock_waits: u64,
+}
+
+/// Lock wait of a backend, collecting relations from the records following it
+#[derive(Clone, Serialize, Deserialize)]
+struct LockWait {
+    pid: Pid,
+    relations: BTreeSet<String>,
+}
+
+/// Ranks relations referenced by timed statements (FROM, JOIN, INTO, UPDATE, COPY) by
+/// total duration and calls, and counts lock waits, deadlocks and lock timeouts
+/// involving them. Plain logs write the CONTEXT and STATEMENT of a lock wait as separate
+/// records of the same backend, these are collected until the backend logs something else.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TablesAggregator {
+    limit: usize,
+    order: TablesOrder,
+    #[serde(skip, default = "lock_messages")]
+    lock_messages: AhoCorasick,
+    statements: StatementTracker,
+    tables: HashMap<String, TableStats>,
+    open_wait: Option<LockWait>,
+    /// CONTEXT / STATEMENT records at the start of the chunk, continuing a lock wait
+    /// logged in the preceding chunk
+    leading_wait: Option<LockWait>,
+    seen_records: bool,
+}
+
+impl TablesAggregator {
+    pub fn new(limit: usize, order: TablesOrder) -> Self {
+        Self {
+            limit,
+            order,
+            lock_messages: lock_messages(),
+            statements: StatementTracker::new(false),
+            tables: HashMap::new(),
+            open_wait: None,
+            leading_wait: None,
+            seen_records: false,
+        }
+    }
+
+    fn close_wait(&mut self) {
+        if let Some(wait) = self.open_wait.take() {
+            for relation in wait.relations {
+                self.tables.entry(relation).or_default().lock_waits += 1;
+            }
+        }
+    }
+}
+
+fn lock_messages() -> AhoCorasick {
+    static PATTERNS: &[&[u8]] = &[
+        b" still waiting for ",
+        b"deadlock detected",
+        b"due to lock timeout",
+        b"could not obtain lock",
+    ];
+
+    AhoCorasick::builder()
+        .ascii_case_insensitive(true)
+        .build(PATTERNS)
+        .expect("failed to build Aho-Corasick automaton")
+}
+
+fn add_statement(
+    tables: &mut HashMap<String, TableStats>,
+    fmt: Format,
+) -> impl FnMut(TimedStatement) {
+    move |statement| {
+        let message = statement_message(&statement.record, &fmt);
+        let sql = match statement_phase(message) {
+            Some((Phase::Parse | Phase::Bind, _)) | None => return,
+            Some((_, sql)) => sql,
+        };
+        let sql = match fmt {
+            Format::Csv => encoding::decode(&unescape_csv_quotes(sql)).to_string(),
+            Format::Plain => encoding::decode(sql).to_string(),
+        };
+        for relation in referenced_relations(&sql) {
+            let stats = tables.entry(relation).or_default();
+            stats.calls += 1;
+            stats.duration += statement.duration;
+        }
+    }
+}
+
+/// Relations named in `relation "x"` of lock messages and their CONTEXT
+fn quoted_relations(text: &[u8], relations: &mut BTreeSet<String>) {
+    const NEEDLE: &[u8] = b"relation \"";
+    let mut rest = text;
+    while let Some(pos) = memchr::memmem::find(rest, NEEDLE) {
+        rest = &rest[pos + NEEDLE.len()..];
+        let Some(end) = memchr::memchr(b'"', rest) else {
+            break;
+        };
+        relations.insert(encoding::decode(&rest[..end]).to_string());
+        rest = &rest[end..];
+    }
+}
+
+fn statement_relations(sql: &[u8], relations: &mut BTreeSet<String>) {
+    relations.extend(referenced_relations(&encoding::decode(sql)));
+}
+
+impl Aggregator for TablesAggregator {
+    fn update(
+        &mut self,
+        record: &[u8],
+        fmt: &Format,
+        _severity: Severity,
+        log_time: DateTime<Local>,
+    ) -> Result<()> {
+        self.statements
+            .push(record, fmt, log_time, add_statement(&mut self.tables, *fmt));
+
+        let Some(message) = fmt.message_from_bytes(record) else {
+            return Ok(());
+        };
+        let is_lock_message = self.lock_messages.is_match(message);
+
+        if *fmt == Format::Csv {
+            // Context and statement are columns of the same record
+            if is_lock_message {
+                let mut relations = BTreeSet::new();
+                quoted_relations(&unescape_csv_quotes(message), &mut relations);
+                if let Some(context) = fmt.context_from_bytes(record) {
+                    quoted_relations(&unescape_csv_quotes(context), &mut relations);
+                }
+                if let Some(query) = fmt.statement_from_bytes(record) {
+                    statement_relations(&unescape_csv_quotes(query), &mut relations);
+                }
+                for relation in relations {
+                    self.tables.entry(relation).or_default().lock_waits += 1;
+                }
+            }
+            return Ok(());
+        }
+
+        let pid = fmt.pid_from_bytes(record).map(<[u8]>::to_vec);
+        let context = fmt.context_from_bytes(record);
+        let statement = fmt.statement_from_bytes(record);
+        let is_continuation =
+            context.is_some() || statement.is_some() || fmt.detail_from_bytes(record).is_some();
+
+        if is_continuation && !self.seen_records {
+            let wait = self.leading_wait.get_or_insert_with(|| LockWait {
+                pid: pid.clone(),
+                relations: BTreeSet::new(),
+            });
+            if wait.pid == pid {
+                context.inspect(|c| quoted_relations(c, &mut wait.relations));
+                statement.inspect(|s| statement_relations(s, &mut wait.relations));
+            }
+            return Ok(());
+        }
+        self.seen_records = true;
+
+        if is_continuation
+            && let Some(wait) = &mut self.open_wait
+            && wait.pid == pid
+        {
+            context.inspect(|c| quoted_relations(c, &mut wait.relations));
+            statement.inspect(|s| statement_relations(s, &mut wait.relations));
+            return Ok(());
+        }
+
+        self.close_wait();
+        if is_lock_message {
+            let mut relations = BTreeSet::new();
+            quoted_relations(message, &mut relations);
+            self.open_wait = Some(LockWait { pid, relations });
+        }
+
+        Ok(())
+    }
+
+    fn merge_box(&mut self, other: &dyn Aggregator) {
+        let other = other
+            .as_any()
+            .downcast_ref::<TablesAggregator>()
+            .expect("Aggregator type mismatch");
+
+        let fmt = other.statements.format().unwrap_or(Format::Plain);
+        self.statements
+            .merge(&other.statements, add_statement(&mut self.tables, fmt));
+
+        if let (Some(wait), Some(leading)) = (&mut self.open_wait, &other.leading_wait)
+            && wait.pid == leading.pid
+        {
+            wait.relations.extend(leading.relations.iter().cloned());
+        }
+        if other.seen_records {
+            self.close_wait();
+            self.open_wait.clone_from(&other.open_wait);
+            self.seen_records = true;
+        }
+
+        for (relation, theirs) in &other.tables {
+            let mine = self.tables.entry(relation.clone()).or_default();
+            mine.calls += theirs.calls;
+            mine.duration += theirs.duration;
+            mine.lock_waits += theirs.lock_waits;
+        }
+    }
+
+    fn finish(&mut self) {
+        let fmt = self.statements.format().unwrap_or(Format::Plain);
+        self.statements.finish(add_statement(&mut self.tables, fmt));
+        self.close_wait();
+    }
+
+    fn print(&mut self) {
+        self.finish();
+
+        let mut items: Vec<_> = self.tables.iter().collect();
+        items.sort_by_key(|(relation, _)| *relation);
+        match self.order {
+            TablesOrder::Duration => items.sort_by_key(|(_, s)| Reverse(s.duration)),
+            TablesOrder::Calls => items.sort_by_key(|(_, s)| Reverse(s.calls)),
+            TablesOrder::Locks => items.sort_by_key(|(_, s)| Reverse(s.lock_waits)),
+        }
+
+        println!(
+            "Top {} tables by {}:",
+            items.len().min(self.limit),
+            match self.order {
+                TablesOrder::Duration => "total duration",
+                TablesOrder::Calls => "calls",
+                TablesOrder::Locks => "lock waits",
+            }
+        );
+        println!(
+            "{:>16} {:>10} {:>10}  table",
+            "total duration", "calls", "lock waits"
+        );
+        for (relation, stats) in items.into_iter().take(self.limit) {
+            println!(
+                "{:>16} {:>10} {:>10}  {relation}",
+                format!("{:?}", stats.duration),
+                stats.calls,
+                stats.lock_waits
+            );
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        "tables"
+    }
+
+    fn save_state(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(self)?)
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Aggregator> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
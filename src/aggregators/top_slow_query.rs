@@ -1,50 +1,130 @@
-use std::{any::Any, cmp::Reverse, collections::BinaryHeap, time::Duration};
+use std::{
+    any::Any,
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    io::{self, Write},
+    time::Duration,
+};
 
 use chrono::{DateTime, Local};
 
 use crate::{
-    aggregators::Aggregator, duration::extract_duration, error::Result, format::Format,
+    aggregators::Aggregator,
+    duration::extract_duration,
+    encoding,
+    error::Result,
+    format::Format,
     severity::Severity,
+    statement::{Phase, StatementTracker, TimedStatement, statement_phase},
+    timestamp,
 };
+use serde::{Deserialize, Serialize};
+
+/// Calls and total duration of the parse, bind and execute phases of one statement
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct PhaseDurations {
+    parse: (u64, Duration),
+    bind: (u64, Duration),
+    execute: (u64, Duration),
+}
 
-#[derive(Clone)]
+impl PhaseDurations {
+    fn total(&self) -> Duration {
+        self.parse.1 + self.bind.1 + self.execute.1
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct TopSlowQueries {
     limit: usize,
     heap: BinaryHeap<Reverse<(Duration, Vec<u8>)>>,
+    statements: StatementTracker,
+    phases: HashMap<String, PhaseDurations>,
 }
 
 impl TopSlowQueries {
-    pub fn new(limit: usize) -> Self {
+    pub fn new(limit: usize, inline_params: bool) -> Self {
         Self {
             limit,
             heap: BinaryHeap::with_capacity(limit),
+            statements: StatementTracker::new(inline_params),
+            phases: HashMap::new(),
+        }
+    }
+
+    fn update_phases(&mut self, record: &[u8], fmt: &Format, duration: Duration) {
+        let Some((phase, sql)) = fmt.message_from_bytes(record).and_then(statement_phase) else {
+            return;
+        };
+        if phase == Phase::Statement {
+            return;
+        }
+        let entry = self
+            .phases
+            .entry(encoding::decode(sql).trim().to_string())
+            .or_default();
+        let (calls, total) = match phase {
+            Phase::Statement | Phase::Parse => &mut entry.parse,
+            Phase::Bind => &mut entry.bind,
+            Phase::Execute => &mut entry.execute,
+        };
+        *calls += 1;
+        *total += duration;
+    }
+
+    fn print_phases(&self) {
+        if self.phases.is_empty() {
+            return;
+        }
+        let mut statements: Vec<_> = self.phases.iter().collect();
+        statements.sort_by_key(|(_, p)| Reverse(p.total()));
+
+        println!("Parse / bind / execute durations per statement:");
+        for (sql, phases) in statements.into_iter().take(self.limit) {
+            println!(
+                "--- parse: {} x {:?}  bind: {} x {:?}  execute: {} x {:?} ---",
+                phases.parse.0,
+                phases.parse.1,
+                phases.bind.0,
+                phases.bind.1,
+                phases.execute.0,
+                phases.execute.1
+            );
+            println!("{sql}");
         }
     }
 }
 
+fn push_limited(
+    heap: &mut BinaryHeap<Reverse<(Duration, Vec<u8>)>>,
+    limit: usize,
+    statement: TimedStatement,
+) {
+    if heap.len() < limit {
+        heap.push(Reverse((statement.duration, statement.record.into_owned())));
+    } else if let Some(Reverse((min, _))) = heap.peek()
+        && statement.duration > *min
+    {
+        heap.pop();
+        heap.push(Reverse((statement.duration, statement.record.into_owned())));
+    }
+}
+
 impl Aggregator for TopSlowQueries {
     fn update(
         &mut self,
         record: &[u8],
-        _fmt: &Format,
+        fmt: &Format,
         _severity: Severity,
-        _log_time: DateTime<Local>,
+        log_time: DateTime<Local>,
     ) -> Result<()> {
-        let Some(duration) = extract_duration(record) else {
-            return Ok(());
-        };
-
-        if self.heap.len() < self.limit {
-            self.heap.push(Reverse((duration, record.to_vec())));
-            return Ok(());
+        if let Some(duration) = extract_duration(record) {
+            self.update_phases(record, fmt, duration);
         }
 
-        if let Some(Reverse((min, _))) = self.heap.peek()
-            && duration > *min
-        {
-            self.heap.pop();
-            self.heap.push(Reverse((duration, record.to_vec())));
-        }
+        let (heap, limit) = (&mut self.heap, self.limit);
+        self.statements
+            .push(record, fmt, log_time, |s| push_limited(heap, limit, s));
         Ok(())
     }
 
@@ -54,6 +134,9 @@ impl Aggregator for TopSlowQueries {
             .downcast_ref::<TopSlowQueries>()
             .expect("Aggregator type mismatch");
 
+        let (heap, limit) = (&mut self.heap, self.limit);
+        self.statements
+            .merge(&other.statements, |s| push_limited(heap, limit, s));
         for Reverse((duration, record)) in &other.heap {
             if self.heap.len() < self.limit {
                 self.heap.push(Reverse((*duration, record.clone())));
@@ -64,17 +147,48 @@ impl Aggregator for TopSlowQueries {
                 self.heap.push(Reverse((*duration, record.clone())));
             }
         }
+
+        for (sql, phases) in &other.phases {
+            let entry = self.phases.entry(sql.clone()).or_default();
+            for (mine, theirs) in [
+                (&mut entry.parse, phases.parse),
+                (&mut entry.bind, phases.bind),
+                (&mut entry.execute, phases.execute),
+            ] {
+                mine.0 += theirs.0;
+                mine.1 += theirs.1;
+            }
+        }
+    }
+
+    fn finish(&mut self) {
+        let (heap, limit) = (&mut self.heap, self.limit);
+        self.statements.finish(|s| push_limited(heap, limit, s));
     }
 
     fn print(&mut self) {
+        self.finish();
+
         let mut items: Vec<_> = self.heap.drain().collect();
         items.sort_by_key(|Reverse((d, _))| *d);
 
         println!("Top {} slowest queries:", items.len());
         for Reverse((duration, record)) in items.into_iter().rev() {
             println!("--- {duration:?} ---");
-            println!("{}", unsafe { std::str::from_utf8_unchecked(&record) });
+            let mut printed = Vec::new();
+            timestamp::write_record(&encoding::printable(&record), &mut printed);
+            printed.push(b'\n');
+            let _ = io::stdout().write_all(&printed);
         }
+        self.print_phases();
+    }
+
+    fn kind(&self) -> &'static str {
+        "top_slow_queries"
+    }
+
+    fn save_state(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(self)?)
     }
 
     fn boxed_clone(&self) -> Box<dyn Aggregator> {
@@ -0,0 +1,288 @@
+use std::{
+    any::Any,
+    collections::{BTreeMap, HashMap, HashSet},
+    time::Duration,
+};
+
+use chrono::{DateTime, Local, TimeZone};
+
+use crate::{
+    aggregators::Aggregator,
+    duration::extract_duration,
+    encoding,
+    error::Result,
+    format::Format,
+    severity::Severity,
+    sql::{StatementType, statement_type},
+    statement::{Phase, is_bare_duration, is_logged_statement, statement_message, statement_phase},
+    timestamp,
+};
+use serde::{Deserialize, Serialize};
+
+type Pid = Option<Vec<u8>>;
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+struct Stats {
+    count: u64,
+    duration: Duration,
+}
+
+/// Where a statement is accounted
+#[derive(Clone, Serialize, Deserialize)]
+struct Key {
+    statement_type: StatementType,
+    db: String,
+    user: String,
+    bucket: i64,
+}
+
+/// Statement counts and cumulative durations per statement type, database, user and
+/// time bucket. Statements are taken from `log_statement` and `log_min_duration_statement`
+/// records, bare `log_duration` records add their duration to the preceding statement of
+/// the backend. Parse and bind phases of the extended protocol are not counted.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WorkloadAggregator {
+    bucket_width: Duration,
+    by_type: HashMap<StatementType, Stats>,
+    #[serde(with = "crate::state::pairs")]
+    by_db: HashMap<(String, StatementType), Stats>,
+    #[serde(with = "crate::state::pairs")]
+    by_user: HashMap<(String, StatementType), Stats>,
+    buckets: BTreeMap<i64, HashMap<StatementType, Stats>>,
+    /// Last log_statement record per backend, waiting for a bare duration
+    #[serde(with = "crate::state::pairs")]
+    last_statement: HashMap<Pid, Key>,
+    seen_pids: HashSet<Pid>,
+    /// Bare durations of backends whose statement was logged in a preceding chunk
+    orphans: Vec<(Pid, Duration)>,
+}
+
+impl WorkloadAggregator {
+    pub fn new(bucket_width: Duration) -> Self {
+        Self {
+            bucket_width,
+            by_type: HashMap::new(),
+            by_db: HashMap::new(),
+            by_user: HashMap::new(),
+            buckets: BTreeMap::new(),
+            last_statement: HashMap::new(),
+            seen_pids: HashSet::new(),
+            orphans: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, key: &Key, count: u64, duration: Duration) {
+        let stats = [
+            self.by_type.entry(key.statement_type).or_default(),
+            self.by_db
+                .entry((key.db.clone(), key.statement_type))
+                .or_default(),
+            self.by_user
+                .entry((key.user.clone(), key.statement_type))
+                .or_default(),
+            self.buckets
+                .entry(key.bucket)
+                .or_default()
+                .entry(key.statement_type)
+                .or_default(),
+        ];
+        for s in stats {
+            s.count += count;
+            s.duration += duration;
+        }
+    }
+}
+
+impl Aggregator for WorkloadAggregator {
+    fn update(
+        &mut self,
+        record: &[u8],
+        fmt: &Format,
+        _severity: Severity,
+        log_time: DateTime<Local>,
+    ) -> Result<()> {
+        let Some(message) = fmt.message_from_bytes(record) else {
+            return Ok(());
+        };
+        let pid = fmt.pid_from_bytes(record).map(<[u8]>::to_vec);
+        let first_of_backend = self.seen_pids.insert(pid.clone());
+
+        if is_bare_duration(message) {
+            let duration = extract_duration(record).unwrap_or_default();
+            if let Some(key) = self.last_statement.remove(&pid) {
+                self.add(&key, 0, duration);
+            } else if first_of_backend && pid.is_some() {
+                self.orphans.push((pid, duration));
+            }
+            return Ok(());
+        }
+
+        let duration = extract_duration(record);
+        if duration.is_none() && !is_logged_statement(message) {
+            return Ok(());
+        }
+        let sql = statement_message(record, fmt);
+        let sql = match statement_phase(sql) {
+            Some((Phase::Parse | Phase::Bind, _)) => return Ok(()),
+            Some((_, sql)) => sql,
+            None if duration.is_some() => return Ok(()),
+            None => sql.strip_prefix(b"statement: ").unwrap_or(sql),
+        };
+
+        let width = i64::try_from(self.bucket_width.as_secs())
+            .unwrap_or(i64::MAX)
+            .max(1);
+        let key = Key {
+            statement_type: statement_type(&encoding::decode(sql)),
+            db: fmt
+                .session_db_from_bytes(record)
+                .map(|d| encoding::decode(d).to_string())
+                .unwrap_or_default(),
+            user: fmt
+                .session_user_from_bytes(record)
+                .map(|u| encoding::decode(u).to_string())
+                .unwrap_or_default(),
+            bucket: log_time.timestamp() / width * width,
+        };
+        self.add(&key, 1, duration.unwrap_or_default());
+        if duration.is_none() {
+            self.last_statement.insert(pid, key);
+        }
+        Ok(())
+    }
+
+    fn merge_box(&mut self, other: &dyn Aggregator) {
+        let other = other
+            .as_any()
+            .downcast_ref::<WorkloadAggregator>()
+            .expect("Aggregator type mismatch");
+
+        // Partials are merged in file order, resolve durations of statements from earlier chunks
+        for (pid, duration) in &other.orphans {
+            match self.last_statement.remove(pid) {
+                Some(key) => self.add(&key, 0, *duration),
+                None if self.seen_pids.is_empty() => self.orphans.push((pid.clone(), *duration)),
+                None => {}
+            }
+        }
+        self.last_statement
+            .retain(|pid, _| !other.seen_pids.contains(pid));
+        self.last_statement.extend(other.last_statement.clone());
+        self.seen_pids.extend(other.seen_pids.iter().cloned());
+
+        for (statement_type, stats) in &other.by_type {
+            let mine = self.by_type.entry(*statement_type).or_default();
+            mine.count += stats.count;
+            mine.duration += stats.duration;
+        }
+        for (key, stats) in &other.by_db {
+            let mine = self.by_db.entry(key.clone()).or_default();
+            mine.count += stats.count;
+            mine.duration += stats.duration;
+        }
+        for (key, stats) in &other.by_user {
+            let mine = self.by_user.entry(key.clone()).or_default();
+            mine.count += stats.count;
+            mine.duration += stats.duration;
+        }
+        for (bucket, types) in &other.buckets {
+            let mine = self.buckets.entry(*bucket).or_default();
+            for (statement_type, stats) in types {
+                let mine = mine.entry(*statement_type).or_default();
+                mine.count += stats.count;
+                mine.duration += stats.duration;
+            }
+        }
+    }
+
+    fn finish(&mut self) {
+        // Durations of statements never logged can't be accounted anywhere
+        self.orphans.clear();
+        self.last_statement.clear();
+    }
+
+    fn print(&mut self) {
+        let total: u64 = self.by_type.values().map(|s| s.count).sum();
+        println!("Statements by type:");
+        for statement_type in StatementType::ALL {
+            let stats = self
+                .by_type
+                .get(&statement_type)
+                .copied()
+                .unwrap_or_default();
+            if stats.count == 0 {
+                continue;
+            }
+            println!(
+                "  {:<8} {:>8}  {:>5.1}%  {:?}",
+                statement_type.as_str(),
+                stats.count,
+                stats.count as f64 * 100.0 / total as f64,
+                stats.duration
+            );
+        }
+
+        for (title, grouped) in [("database", &self.by_db), ("user", &self.by_user)] {
+            println!("Statements by {title}:");
+            let mut names: BTreeMap<&str, Vec<(StatementType, Stats)>> = BTreeMap::new();
+            for ((name, statement_type), stats) in grouped {
+                names
+                    .entry(name.as_str())
+                    .or_default()
+                    .push((*statement_type, *stats));
+            }
+            for (name, mut types) in names {
+                types.sort_by_key(|(t, _)| *t);
+                let count: u64 = types.iter().map(|(_, s)| s.count).sum();
+                let duration: Duration = types.iter().map(|(_, s)| s.duration).sum();
+                println!(
+                    "  {:<20} {count:>8}  {duration:?}",
+                    if name.is_empty() { "[unknown]" } else { name }
+                );
+                for (statement_type, stats) in types {
+                    println!(
+                        "    {:<8} {:>8}  {:?}",
+                        statement_type.as_str(),
+                        stats.count,
+                        stats.duration
+                    );
+                }
+            }
+        }
+
+        println!("Statements by time bucket:");
+        print!("  {:<19}", "");
+        for statement_type in StatementType::ALL {
+            print!(" {:>8}", statement_type.as_str());
+        }
+        println!("  duration");
+        for (bucket, types) in &self.buckets {
+            let Some(time) = Local.timestamp_opt(*bucket, 0).single() else {
+                continue;
+            };
+            print!("  {}", timestamp::display(time));
+            for statement_type in StatementType::ALL {
+                let count = types.get(&statement_type).map_or(0, |s| s.count);
+                print!(" {count:>8}");
+            }
+            let duration: Duration = types.values().map(|s| s.duration).sum();
+            println!("  {duration:?}");
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        "workload"
+    }
+
+    fn save_state(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(self)?)
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Aggregator> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
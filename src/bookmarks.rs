@@ -0,0 +1,137 @@
+//! Per-file bookmarks for incremental runs (`--since-last-run`): how far each log file
+//! was processed, so that the next run only looks at newly appended bytes.
+
+use std::{
+    fs::{self, Metadata},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Result;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub path: PathBuf,
+    pub inode: u64,
+    pub size: u64,
+    /// Everything before this byte offset is processed, always at a line start
+    pub offset: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Bookmarks {
+    files: Vec<Bookmark>,
+}
+
+impl Bookmarks {
+    /// Loads bookmarks of the previous run, a missing file means a first run
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    pub fn save(&mut self, path: &Path) -> Result<()> {
+        // Rotated away logs and extracted archives
+        self.files.retain(|b| b.path.exists());
+        fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Byte offset to continue the file from. Files are recognized by inode, so a log
+    /// rotated to a new name is continued too, while a new file at the same path or a
+    /// file that got smaller (truncated) is processed from the start.
+    pub fn start_offset(&self, path: &Path, metadata: &Metadata) -> u64 {
+        let inode = inode(metadata);
+        self.files
+            .iter()
+            .find(|b| b.inode == inode && (inode != 0 || b.path == path))
+            .filter(|b| metadata.len() >= b.size)
+            .map_or(0, |b| b.offset)
+    }
+
+    /// Records that the file was processed up to `offset`
+    pub fn update(&mut self, path: &Path, metadata: &Metadata, offset: u64) {
+        let bookmark = Bookmark {
+            path: path.to_path_buf(),
+            inode: inode(metadata),
+            size: metadata.len(),
+            offset,
+        };
+        // Forget what was at the same path or with the same inode before
+        self.files
+            .retain(|b| b.path != bookmark.path && (b.inode != bookmark.inode || b.inode == 0));
+        self.files.push(bookmark);
+    }
+}
+
+#[cfg(unix)]
+pub fn inode(metadata: &Metadata) -> u64 {
+    std::os::unix::fs::MetadataExt::ino(metadata)
+}
+
+/// No inodes, files are recognized by path only
+#[cfg(not(unix))]
+pub fn inode(_metadata: &Metadata) -> u64 {
+    0
+}
+
+/// End of the last complete line, a line still being written is left for the next run
+pub fn processed_until(bytes: &[u8]) -> usize {
+    memchr::memrchr(b'\n', bytes).map_or(0, |p| p + 1)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn test_start_offset() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("postgresql.log");
+        let mut file = fs::File::create(&log).unwrap();
+        file.write_all(b"line 1\nline 2\n").unwrap();
+
+        let mut bookmarks = Bookmarks::default();
+        let metadata = fs::metadata(&log).unwrap();
+        assert_eq!(bookmarks.start_offset(&log, &metadata), 0);
+        bookmarks.update(&log, &metadata, 14);
+
+        // Appended
+        file.write_all(b"line 3\n").unwrap();
+        assert_eq!(
+            bookmarks.start_offset(&log, &fs::metadata(&log).unwrap()),
+            14
+        );
+
+        // Rotated, the old file is continued under its new name
+        let rotated = dir.path().join("postgresql.log.1");
+        fs::rename(&log, &rotated).unwrap();
+        fs::write(&log, b"new line 1\n").unwrap();
+        assert_eq!(
+            bookmarks.start_offset(&log, &fs::metadata(&log).unwrap()),
+            0
+        );
+        assert_eq!(
+            bookmarks.start_offset(&rotated, &fs::metadata(&rotated).unwrap()),
+            14
+        );
+
+        // Truncated
+        fs::write(&rotated, b"x\n").unwrap();
+        assert_eq!(
+            bookmarks.start_offset(&rotated, &fs::metadata(&rotated).unwrap()),
+            0
+        );
+    }
+
+    #[test]
+    fn test_processed_until() {
+        assert_eq!(processed_until(b"line 1\nline 2\nline"), 14);
+        assert_eq!(processed_until(b"partial"), 0);
+    }
+}
@@ -2,16 +2,32 @@ use std::path::PathBuf;
 
 use clap::{Arg, ArgAction, Command, arg, value_parser};
 
-use crate::Severity;
+use crate::{Severity, encoding::Encoding};
 
 pub fn cli() -> Command {
     Command::new("pgweasel")
         .about("A PostgreSQL log parser")
         .version("0.1")
         .arg(arg!(--debug <DEBUG>).short('d').help("Verbose. Show debug information").action(ArgAction::SetTrue))
+        .arg(arg!(--db <DB>).help("Only show records of given session databases, e.g. postgres,app").value_delimiter(','))
         .arg(arg!(--mask <MASK>).short('m').help("Postgres log timestamp mask (e.g. \"2025-05-21 12:57\" - will show all events at 12:57)"))
-        .arg(arg!(--begin <BEGIN>).short('b'))
-        .arg(arg!(--end <END>).short('e'))
+        .arg(arg!(--begin <BEGIN>).short('b').help("Start of the time range, a time or how long ago, e.g. 10m. last:30m counts back from the newest log time"))
+        .arg(arg!(--end <END>).short('e').help("End of the time range, a time or how long ago"))
+        .arg(arg!(--anchor <ANCHOR>).help("What relative times count back from: now, or log-end, the newest log time of the inputs").value_parser(["now", "log-end"]).default_value("now"))
+        .arg(arg!(--window <WINDOW>).help("Time range from a time, e.g. \"2025-05-21 12:50 +15m\", or up to it with -15m").conflicts_with_all(["begin", "end"]))
+        .arg(arg!(--around <AROUND>).help("Time range around a time, e.g. \"2025-05-21 12:50 ±5m\" (or +-5m), ±5m if not given").conflicts_with_all(["begin", "end", "window"]))
+        .arg(arg!(--"log-tz" <ZONE>).help("Time zone of log times: ABBR=ZONE for an ambiguous or unknown zone abbreviation (IST=+05:30), or a ZONE (+05:30, Europe/Helsinki) for times logged without one").action(ArgAction::Append))
+        .arg(arg!(--tz <ZONE>).help("Time zone to print times in and read --begin / --end in, e.g. UTC, +05:30, Europe/Helsinki. Defaults to local time").conflicts_with("utc"))
+        .arg(arg!(--utc).help("Print times in UTC and read --begin / --end in UTC, same as --tz UTC"))
+        .arg(arg!(--encoding <ENCODING>).help("Server encoding of log text that isn't valid UTF-8: utf8, latin1 or win1252. Records are printed converted to UTF-8 with latin1 and win1252").value_parser(value_parser!(Encoding)))
+        .arg(arg!(--format <FORMAT>).help("Log format, detected from the file content by default").value_parser(["auto", "csv", "plain"]))
+        .arg(arg!(--jobs <N>).short('j').help("Number of threads processing the logs, defaults to the number of CPUs").value_parser(value_parser!(u32).range(1..)))
+        .arg(arg!(--follow).short('f').help("Keep reading records appended to the log files, like tail -f. Summarizing commands refresh their report periodically"))
+        .arg(arg!(--refresh <INTERVAL>).help("How often reports are refreshed with --follow, e.g. 5s, 1m. Defaults to 10s"))
+        .arg(arg!(--"since-last-run" <STATE_FILE>).help("Only process what was appended to the log files since the previous run with the same state file, which keeps per-file bookmarks").value_parser(value_parser!(PathBuf)))
+        .arg(arg!(--strict).help("Stop at the first malformed record, they're skipped and listed at the end by default"))
+        .arg(arg!(--"dump-bad" <FILE>).help("Write the malformed records to a file").value_parser(value_parser!(PathBuf)))
+        .arg(arg!(--"save-state" <FILE>).help("Save aggregated state to a file instead of printing the report, to be combined later with the merge command").value_parser(value_parser!(PathBuf)))
         .subcommand_required(true)
         .subcommand(
             Command::new("errors")
@@ -20,14 +36,17 @@ pub fn cli() -> Command {
                 .alias("err")
                 .args_conflicts_with_subcommands(true)
                 .args(level_args())
+                .args(code_args())
                 .args(filelist_args())
                 .subcommand(Command::new("list")
                     .about("Default subcommand of error. Show error messages")
                     .args(level_args())
+                    .args(code_args())
                     .args(filelist_args()))
                 .subcommand(Command::new("top")
                     .about("Shows top most frequent error messages")
                     .args(level_args())
+                    .args(code_args())
                     .arg(arg!(--max <MAX>)
                         .short('m')
                         .help("Max number of top errors to show (default 20)")
@@ -38,6 +57,7 @@ pub fn cli() -> Command {
                     .about("Show histogram of error occurrences over time")
                     .alias("histogram")
                     .args(level_args())
+                    .args(code_args())
                     .arg(arg!(--bucket <INTERVAL>)
                         .short('b')
                         .help("Interval for histogram buckets, e.g. 10s, 1m, 1h. Defaults to 1h")
@@ -62,10 +82,25 @@ pub fn cli() -> Command {
         )
         .subcommand(
             Command::new("slow")
-                .subcommand(Command::new("top").args(filelist_args()))
+                .subcommand(Command::new("top")
+                    .args(slow_args())
+                    .args(filelist_args()))
+                .subcommand(Command::new("queryid")
+                    .about("Aggregate statement durations per query_id (compute_query_id, %Q in log_line_prefix)")
+                    .alias("qid")
+                    .arg(arg!(--id <QUERYID>)
+                        .help("Show all logged records of given query_id instead"))
+                    .arg(arg!(--max <MAX>)
+                        .short('m')
+                        .help("Max number of query_ids to show (default 20)")
+                        .value_parser(value_parser!(usize))
+                        .default_value("20"))
+                    .args(filelist_args()))
                 .args_conflicts_with_subcommands(true)
                 .about("Show queries taking longer than give threshold")
+                .override_usage("pgweasel slow <TRESHOLD> <PATH>... [--params]\n       pgweasel slow <COMMAND>")
                 .arg(arg!(<TRESHOLD>).help("Treshold in format like 10s, 10ms to consider slow query."))
+                .args(slow_args())
                 .args(filelist_args())
         )
         .subcommand(
@@ -88,6 +123,102 @@ pub fn cli() -> Command {
                 .args(filelist_args())
                 .args_conflicts_with_subcommands(true)
         )
+        .subcommand(
+            Command::new("replication")
+                .args_conflicts_with_subcommands(true)
+                .about("Summarize replication and recovery health: walreceiver connects / disconnects, removed WAL segments, timeline switches, slot invalidations, recovery conflicts and logical replication worker errors")
+                .alias("repl")
+                .alias("replica")
+                .alias("recovery")
+                .args(filelist_args())
+        )
+        .subcommand(
+            Command::new("archive")
+                .args_conflicts_with_subcommands(true)
+                .about("Track WAL archiving failures: failing segments, failure streaks, failed archive commands and archiver restarts")
+                .alias("archiver")
+                .alias("arch")
+                .args(filelist_args())
+        )
+        .subcommand(
+            Command::new("config")
+                .args_conflicts_with_subcommands(true)
+                .about("Show configuration change history: reloads, per-parameter value timeline, ALTER SYSTEM statements and configuration file errors")
+                .alias("conf")
+                .alias("settings")
+                .args(filelist_args())
+        )
+        .subcommand(
+            Command::new("audit")
+                .about("Show pgaudit AUDIT records, filtered by class, object and user")
+                .alias("pgaudit")
+                .args_conflicts_with_subcommands(true)
+                .args(audit_args())
+                .args(filelist_args())
+                .subcommand(Command::new("list")
+                    .about("Default subcommand of audit. Show matching audit records")
+                    .args(audit_args())
+                    .args(filelist_args()))
+                .subcommand(Command::new("summary")
+                    .about("Show audit record counts per class, command, object and user")
+                    .alias("sum")
+                    .args(audit_args())
+                    .args(filelist_args()))
+        )
+        .subcommand(
+            Command::new("workload")
+                .args_conflicts_with_subcommands(true)
+                .about("Break down logged statements by type (SELECT, INSERT, UPDATE, DELETE, COPY, DDL, utility, transaction control) per database, user and time bucket. Assumes log_statement=all or log_min_duration_statement=0")
+                .alias("wl")
+                .alias("stmt")
+                .arg(arg!(--bucket <INTERVAL>)
+                    .short('b')
+                    .help("Interval for time series buckets, e.g. 10s, 1m, 1h. Defaults to 1h")
+                    .value_parser(value_parser!(String))
+                    .default_value("1h"))
+                .args(filelist_args())
+        )
+        .subcommand(
+            Command::new("tables")
+                .args_conflicts_with_subcommands(true)
+                .about("Rank tables referenced by logged statements (FROM, JOIN, INSERT INTO, UPDATE, DELETE FROM, COPY) by total duration, calls and lock waits")
+                .alias("table")
+                .alias("relations")
+                .arg(arg!(--max <MAX>)
+                    .short('m')
+                    .help("Max number of tables to show (default 20)")
+                    .value_parser(value_parser!(usize))
+                    .default_value("20"))
+                .arg(arg!(--sort <ORDER>)
+                    .short('s')
+                    .help("Rank tables by total duration, calls or lock waits")
+                    .value_parser(["duration", "calls", "locks"])
+                    .default_value("duration"))
+                .args(filelist_args())
+        )
+        .subcommand(
+            Command::new("plans")
+                .args_conflicts_with_subcommands(true)
+                .about("Analyze auto_explain plans per query: most expensive nodes, sequential scans on large row counts, row misestimates and plan flips")
+                .alias("plan")
+                .alias("explain")
+                .arg(arg!(--max <MAX>)
+                    .short('m')
+                    .help("Max number of queries to show (default 10)")
+                    .value_parser(value_parser!(usize))
+                    .default_value("10"))
+                .args(filelist_args())
+        )
+        .subcommand(
+            Command::new("index")
+                .about("Write a sidecar index (<log>.pgwidx) per log file, to skip blocks of records that can't match in later runs")
+                .args(filelist_args())
+        )
+        .subcommand(
+            Command::new("merge")
+                .about("Combine states saved with --save-state, e.g. on several hosts, and print the report of the command they were saved by")
+                .arg(arg!(<STATE> ..."State files saved with --save-state").value_parser(value_parser!(PathBuf)))
+        )
         .subcommand(
             Command::new("stats")
                 .about("Summary of log events - counts / frequency of errors, connections, checkpoints, autovacuums")
@@ -104,6 +235,36 @@ fn level_args() -> Vec<Arg> {
     ]
 }
 
+fn code_args() -> Vec<Arg> {
+    vec![
+        arg!(--code <SQLSTATE>)
+            .help("Only show records with given SQLSTATE error codes, e.g. 23505,40P01. Needs CSV logs")
+            .value_delimiter(','),
+    ]
+}
+
+fn slow_args() -> Vec<Arg> {
+    vec![
+        arg!(--params "Inline logged bind parameter values ($1 = '42') into the statements")
+            .short('p')
+            .action(ArgAction::SetTrue),
+    ]
+}
+
+fn audit_args() -> Vec<Arg> {
+    vec![
+        arg!(--class <CLASS>)
+            .help("Only show given pgaudit classes, e.g. DDL,ROLE")
+            .value_delimiter(','),
+        arg!(--object <OBJECT>)
+            .help("Only show given objects, e.g. public.accounts")
+            .value_delimiter(','),
+        arg!(--user <USER>)
+            .help("Only show records of given session users")
+            .value_delimiter(','),
+    ]
+}
+
 fn filelist_args() -> Vec<Arg> {
     vec![arg!(<PATH> ..."Log files to analyze").value_parser(clap::value_parser!(PathBuf))]
 }
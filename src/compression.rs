@@ -0,0 +1,204 @@
+//! Streaming decompression of compressed logs and log bundles. The format is detected by
+//! magic bytes, not by file extension, and archives can nest, e.g. a `.tar.gz` of
+//! `.csv.zst` files. Decoded bytes go straight to the record pipeline, nothing is
+//! extracted to disk.
+
+use std::{
+    io::{self, Cursor, ErrorKind, Read},
+    path::{Path, PathBuf},
+};
+
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+use lz4_flex::frame::FrameDecoder;
+use lzma_rust2::XzReader;
+use tar::Archive;
+use zip::read::read_zipfile_from_stream;
+
+/// Bytes read for detection, the tar magic ends at offset 262
+const DETECT_LEN: usize = 262;
+const READ_SIZE: usize = 1 << 20;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Bzip2,
+    Xz,
+    Lz4,
+    Zip,
+    Tar,
+}
+
+impl Compression {
+    pub fn detect(head: &[u8]) -> Option<Self> {
+        Some(match head {
+            [0x1f, 0x8b, ..] => Compression::Gzip,
+            [0x28, 0xb5, 0x2f, 0xfd, ..] => Compression::Zstd,
+            [b'B', b'Z', b'h', ..] => Compression::Bzip2,
+            [0xfd, b'7', b'z', b'X', b'Z', 0x00, ..] => Compression::Xz,
+            [0x04, 0x22, 0x4d, 0x18, ..] => Compression::Lz4,
+            [b'P', b'K', 0x03, 0x04, ..] => Compression::Zip,
+            _ if head.get(257..262) == Some(b"ustar") => Compression::Tar,
+            _ => return None,
+        })
+    }
+}
+
+/// Decoded input, the bytes of a log follow its `Member`
+pub enum Decoded {
+    Member(PathBuf),
+    Data(Vec<u8>),
+}
+
+/// Decodes `reader`, passing each contained log to `emit`. Logs are named after the
+/// input without its compression extension, archive members after `path/member`.
+pub fn decode(
+    path: &Path,
+    reader: &mut dyn Read,
+    emit: &mut dyn FnMut(Decoded) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut head = Vec::with_capacity(DETECT_LEN);
+    Read::take(&mut *reader, DETECT_LEN as u64).read_to_end(&mut head)?;
+    let compression = Compression::detect(&head);
+    let mut reader = Cursor::new(head).chain(reader);
+
+    match compression {
+        Some(Compression::Gzip) => decode(
+            &without_extension(path),
+            &mut MultiGzDecoder::new(reader),
+            emit,
+        ),
+        Some(Compression::Zstd) => decode(
+            &without_extension(path),
+            &mut zstd::Decoder::new(reader)?,
+            emit,
+        ),
+        Some(Compression::Bzip2) => decode(
+            &without_extension(path),
+            &mut MultiBzDecoder::new(reader),
+            emit,
+        ),
+        Some(Compression::Xz) => decode(
+            &without_extension(path),
+            &mut XzReader::new(reader, true),
+            emit,
+        ),
+        Some(Compression::Lz4) => decode(
+            &without_extension(path),
+            &mut FrameDecoder::new(reader),
+            emit,
+        ),
+        Some(Compression::Zip) => {
+            while let Some(mut member) =
+                read_zipfile_from_stream(&mut reader).map_err(io::Error::other)?
+            {
+                let member_path = path.join(member.name());
+                if member.is_file() && is_log(&member_path) {
+                    decode(&member_path, &mut member, emit)?;
+                }
+            }
+            Ok(())
+        }
+        Some(Compression::Tar) => {
+            let mut archive = Archive::new(reader);
+            for member in archive.entries()? {
+                let mut member = member?;
+                let member_path = path.join(member.path()?);
+                if member.header().entry_type().is_file() && is_log(&member_path) {
+                    decode(&member_path, &mut member, emit)?;
+                }
+            }
+            Ok(())
+        }
+        None => {
+            emit(Decoded::Member(path.to_path_buf()))?;
+            loop {
+                let mut block = vec![0; READ_SIZE];
+                let read = match reader.read(&mut block) {
+                    Ok(0) => return Ok(()),
+                    Ok(read) => read,
+                    Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                };
+                block.truncate(read);
+                emit(Decoded::Data(block))?;
+            }
+        }
+    }
+}
+
+/// Name of the decompressed file, `postgresql.csv.zst` -> `postgresql.csv`
+fn without_extension(path: &Path) -> PathBuf {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("tgz") => path.with_extension("tar"),
+        Some("gz" | "zst" | "zstd" | "bz2" | "xz" | "lz4") => path.with_extension(""),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Archived files without extension, like `current_logfiles`, are skipped as when
+/// expanding a directory
+fn is_log(path: &Path) -> bool {
+    without_extension(path).extension().is_some()
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use super::*;
+
+    const LOG: &[u8] = b"2025-05-22 15:15:09.392 EEST [1] ERROR:  x\n";
+
+    fn decoded(path: &str, bytes: &[u8]) -> Vec<(PathBuf, Vec<u8>)> {
+        let mut logs: Vec<(PathBuf, Vec<u8>)> = Vec::new();
+        decode(Path::new(path), &mut &bytes[..], &mut |d| {
+            match d {
+                Decoded::Member(path) => logs.push((path, Vec::new())),
+                Decoded::Data(data) => logs.last_mut().unwrap().1.extend(data),
+            }
+            Ok(())
+        })
+        .unwrap();
+        logs
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(
+            Compression::detect(b"\x1f\x8b\x08"),
+            Some(Compression::Gzip)
+        );
+        assert_eq!(Compression::detect(b"BZh91AY"), Some(Compression::Bzip2));
+        assert_eq!(Compression::detect(LOG), None);
+        assert_eq!(Compression::detect(b""), None);
+    }
+
+    #[test]
+    fn test_decode_nested() {
+        let zstd = zstd::encode_all(LOG, 0).unwrap();
+        let mut tar = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(zstd.len() as u64);
+        header.set_cksum();
+        tar.append_data(&mut header, "pg/postgresql.csv.zst", &zstd[..])
+            .unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_size(2);
+        header.set_cksum();
+        tar.append_data(&mut header, "pg/current_logfiles", &b"x\n"[..])
+            .unwrap();
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        gz.write_all(&tar.into_inner().unwrap()).unwrap();
+
+        assert_eq!(
+            decoded("logs.tgz", &gz.finish().unwrap()),
+            vec![(PathBuf::from("logs.tar/pg/postgresql.csv"), LOG.to_vec())]
+        );
+        assert_eq!(
+            decoded("postgresql.log", LOG),
+            vec![(PathBuf::from("postgresql.log"), LOG.to_vec())]
+        );
+    }
+}
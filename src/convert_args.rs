@@ -1,17 +1,23 @@
 use std::{
     fs::{self, File},
-    io::copy,
-    path::{Path, PathBuf},
+    path::PathBuf,
+    time::Duration,
 };
 
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Local, TimeDelta};
 use clap::ArgMatches;
-use flate2::read::GzDecoder;
+use humantime::parse_duration;
 use log::debug;
-use tempfile::TempDir;
-use zip::ZipArchive;
 
-use crate::{Error, util::time_or_interval_string_to_time};
+use crate::{
+    Error,
+    encoding::{self, Encoding},
+    format::Format,
+    index,
+    output_results::newest_log_time,
+    timestamp::{self, Zone, Zones},
+    util::time_or_interval_string_to_time,
+};
 
 use crate::Result;
 
@@ -27,28 +33,57 @@ pub struct ConvertedArgs {
     pub begin: Option<DateTime<Local>>,
     pub end: Option<DateTime<Local>>,
     pub mask: Option<String>,
+    /// `--db`
+    pub databases: Vec<String>,
+    /// Log format forced with `--format`, detected per file otherwise
+    pub format: Option<Format>,
     pub verbose: bool,
     pub print_details: bool,
+    pub save_state: Option<PathBuf>,
+    pub since_last_run: Option<PathBuf>,
+    pub follow: bool,
+    /// How often reports are refreshed when following
+    pub refresh: Duration,
+    /// Stop at the first malformed record instead of skipping it
+    pub strict: bool,
+    /// File malformed records are written to
+    pub dump_bad: Option<PathBuf>,
 }
 
 impl ConvertedArgs {
     pub fn parse_from_matches(val: ArgMatches) -> Result<Self> {
-        // Parse begin / end flags
-        let begin = if let Some(begin_str) = val.get_one::<String>("begin") {
-            Some(time_or_interval_string_to_time(begin_str, None)?)
-        } else {
-            None
+        // Before any time is parsed
+        timestamp::configure(zones(&val)?);
+        encoding::configure(
+            val.get_one::<Encoding>("encoding")
+                .copied()
+                .unwrap_or_default(),
+        );
+
+        let save_state = val.get_one::<PathBuf>("save-state").cloned();
+        let since_last_run = val.get_one::<PathBuf>("since-last-run").cloned();
+        let format = match val.get_one::<String>("format").map(String::as_str) {
+            Some("csv") => Some(Format::Csv),
+            Some("plain") => Some(Format::Plain),
+            _ => None,
         };
-
-        let end = if let Some(end_str) = val.get_one::<String>("end") {
-            Some(time_or_interval_string_to_time(end_str, None)?)
-        } else {
-            None
+        let follow = val.get_flag("follow");
+        let strict = val.get_flag("strict");
+        let dump_bad = val.get_one::<PathBuf>("dump-bad").cloned();
+        let refresh = match val.get_one::<String>("refresh") {
+            Some(refresh) => parse_duration(refresh)?,
+            None => Duration::from_secs(10),
         };
 
         let mask = val
             .get_one::<String>("mask")
             .map(std::borrow::ToOwned::to_owned);
+        let databases = val
+            .get_many::<String>("db")
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect();
 
         // Initialize logger based on verbose flag
         let mut verbose = false;
@@ -62,22 +97,41 @@ impl ConvertedArgs {
             })
             .init();
 
+        // Files and chunks of files are processed by one pool of threads
+        if let Some(&jobs) = val.get_one::<u32>("jobs") {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(jobs as usize)
+                .build_global()
+                .map_err(Error::custom)?;
+        }
+
         Ok(ConvertedArgs {
             file_list: vec![],
             files: vec![],
-            begin,
-            end,
+            begin: None,
+            end: None,
             mask,
+            databases,
+            format,
             matches: val,
             verbose,
             print_details: true,
+            save_state,
+            since_last_run,
+            follow,
+            refresh,
+            strict,
+            dump_bad,
         })
     }
 
     pub fn expand_dirs(mut self) -> Result<Self> {
         if let Some((_, sub_matches)) = self.matches.subcommand() {
             let paths = sub_matches
-                .get_many::<PathBuf>("PATH")
+                // merge takes state files instead
+                .try_get_many::<PathBuf>("PATH")
+                .ok()
+                .flatten()
                 .into_iter()
                 .flatten()
                 .collect::<Vec<_>>();
@@ -85,7 +139,9 @@ impl ConvertedArgs {
             self.file_list.extend(file_list_to_add);
             if let Some((_, sub_sub_matches)) = sub_matches.subcommand() {
                 let paths = sub_sub_matches
-                    .get_many::<PathBuf>("PATH")
+                    .try_get_many::<PathBuf>("PATH")
+                    .ok()
+                    .flatten()
                     .into_iter()
                     .flatten()
                     .collect::<Vec<_>>();
@@ -107,8 +163,17 @@ impl ConvertedArgs {
                 for entry in fs::read_dir(p)? {
                     let entry = entry?;
                     let path = entry.path();
+                    // Subdirectories aren't descended into. Sidecars of the `index` command.
+                    if !path.is_file()
+                        || path.extension().is_some_and(|ext| ext == index::EXTENSION)
+                    {
+                        continue;
+                    }
                     result.push(path);
                 }
+            } else if p.as_os_str() == STDIN || p.exists() {
+                // stdin, pipes, FIFOs and devices are read as streams
+                result.push(p.clone());
             } else {
                 return Err(Error::FileDoesNotExist { path: p.clone() });
             }
@@ -116,80 +181,148 @@ impl ConvertedArgs {
         Ok(result)
     }
 
-    pub fn expand_archives(mut self) -> Result<Self> {
-        let temp_dir = TempDir::new()?;
-
+    /// Opens the files, compressed ones are decoded while processing
+    pub fn open_files(mut self) -> Result<Self> {
         for path in &self.file_list {
-            match path.extension().and_then(|s| s.to_str()) {
-                Some("gz") => self.files.push(extract_gz(path, temp_dir.path())?),
-                Some("zip") => self.files.extend(extract_zip(path, temp_dir.path())?),
-
-                Some(_r) => {
-                    let file_with_path = FileWithPath {
-                        file: File::open(path)?,
-                        path: path.clone(),
-                    };
-                    self.files.push(file_with_path);
-                }
-                None => {}
-            }
+            let file = match path.extension() {
+                None if path.as_os_str() == STDIN => stdin()?,
+                // Extension-less files in log directories, like `current_logfiles`
+                None if path.is_file() => continue,
+                _ => File::open(path)?,
+            };
+            self.files.push(FileWithPath {
+                file,
+                path: path.clone(),
+            });
         }
 
         Ok(self)
     }
-}
 
-fn extract_gz(src: &Path, temp_dir: &Path) -> Result<FileWithPath> {
-    let file = fs::File::open(src)?;
-    let mut decoder = GzDecoder::new(file);
-
-    let filename = src
-        .file_stem()
-        .ok_or(Error::FailedToExtractStemFromPath)?
-        .to_string_lossy()
-        .to_string();
-    let out_path = temp_dir.join(filename);
-
-    let mut out_file = fs::File::create(&out_path)?;
-    copy(&mut decoder, &mut out_file)?;
-    let reopened = fs::File::open(&out_path)?;
-
-    Ok(FileWithPath {
-        file: reopened,
-        path: out_path,
-    })
-}
-
-fn extract_zip(src: &Path, temp_dir: &Path) -> Result<Vec<FileWithPath>> {
-    let file = fs::File::open(src)?;
-    let mut archive = ZipArchive::new(file)?;
-
-    let mut out_files = Vec::new();
+    /// Resolves `--begin` / `--end`, or the range of `--window` or `--around`. Relative
+    /// times count back from now, or from the newest log time of the inputs with
+    /// `--anchor log-end` or `last:30m`.
+    pub fn resolve_times(mut self) -> Result<Self> {
+        let anchored = self
+            .matches
+            .get_one::<String>("anchor")
+            .is_some_and(|anchor| anchor == "log-end");
+        let [begin, end, window, around] = ["begin", "end", "window", "around"]
+            .map(|id| self.matches.get_one::<String>(id).cloned());
+        let log_end = if [&begin, &end, &window, &around]
+            .into_iter()
+            .flatten()
+            .any(|input| anchored || input.starts_with(LAST))
+        {
+            let log_end = newest_log_time(&self.files)?
+                .ok_or("No log times in the inputs to count relative times back from")?;
+            debug!("Relative times count back from {log_end}");
+            Some(log_end)
+        } else {
+            None
+        };
+        let time = |input: &str| {
+            let (time, reference) = match input.strip_prefix(LAST) {
+                Some(time) => (time, log_end),
+                None => (input, log_end.filter(|_| anchored)),
+            };
+            time_or_interval_string_to_time(time, reference).map_err(|source| {
+                Error::FiledToParseBeginEnd {
+                    input: input.to_string(),
+                    source,
+                }
+            })
+        };
 
-    for i in 0..archive.len() {
-        let mut zip_file = archive.by_index(i)?;
-        if zip_file.is_dir() {
-            continue;
-        }
+        (self.begin, self.end) = if let Some(window) = window {
+            let (start, sign, span) = split_span(&window)
+                .filter(|(_, sign, _)| matches!(*sign, "+" | "-"))
+                .ok_or_else(|| {
+                    Error::custom(format!(
+                        "--window is a time and how long from it, like \"2025-05-21 12:50 +15m\": {window}"
+                    ))
+                })?;
+            let start = time(start)?;
+            match sign {
+                "-" => (Some(start - span), Some(start)),
+                _ => (Some(start), Some(start + span)),
+            }
+        } else if let Some(around) = around {
+            let (center, span) = match split_span(&around) {
+                Some((center, "±" | "+-", span)) => (center, span),
+                Some(_) => {
+                    return Err(Error::custom(format!(
+                        "--around takes a span like ±5m or +-5m: {around}"
+                    )));
+                }
+                None => (around.as_str(), TimeDelta::minutes(5)),
+            };
+            let center = time(center)?;
+            (Some(center - span), Some(center + span))
+        } else {
+            (
+                begin.as_deref().map(time).transpose()?,
+                end.as_deref().map(time).transpose()?,
+            )
+        };
+        debug!("Time range from {:?} to {:?}", self.begin, self.end);
+        Ok(self)
+    }
+}
 
-        let out_path = temp_dir.join(zip_file.name());
+/// Prefix of relative times counting back from the newest log time, `last:30m`
+const LAST: &str = "last:";
+
+/// Splits `2025-05-21 12:50 +15m` into the time, the sign (`+`, `-`, `±` or `+-`) and the
+/// span, None without a span
+fn split_span(input: &str) -> Option<(&str, &str, TimeDelta)> {
+    let (time, span) = input.trim().rsplit_once(' ')?;
+    let sign = ["±", "+-", "+", "-"]
+        .into_iter()
+        .find(|sign| span.starts_with(sign))?;
+    let span = TimeDelta::from_std(parse_duration(&span[sign.len()..]).ok()?).ok()?;
+    Some((time.trim_end(), sign, span))
+}
 
-        if let Some(parent) = out_path.parent() {
-            fs::create_dir_all(parent)?;
+/// Zones set with `--log-tz`, `--tz` and `--utc`
+fn zones(val: &ArgMatches) -> Result<Zones> {
+    let mut zones = Zones::default();
+    for log_tz in val.get_many::<String>("log-tz").into_iter().flatten() {
+        match log_tz.split_once('=') {
+            Some((abbreviation, zone)) => match zone.parse()? {
+                Zone::Fixed(offset) => zones.abbreviations.push((abbreviation.to_string(), offset)),
+                _ => {
+                    return Err(Error::custom(format!(
+                        "An abbreviation stands for an offset, like {abbreviation}=+05:30: {log_tz}"
+                    )));
+                }
+            },
+            None => zones.log = Some(log_tz.parse()?),
         }
+    }
+    if val.get_flag("utc") {
+        zones.output = Some(("UTC".parse()?, "UTC".to_string()));
+    } else if let Some(tz) = val.get_one::<String>("tz") {
+        zones.output = Some((tz.parse()?, tz.to_uppercase()));
+    }
+    Ok(zones)
+}
 
-        let mut out_file = fs::File::create(&out_path)?;
-        copy(&mut zip_file, &mut out_file)?;
-
-        let reopened = fs::File::open(&out_path)?;
+/// Path argument reading the log from standard input
+const STDIN: &str = "-";
 
-        out_files.push(FileWithPath {
-            file: reopened,
-            path: out_path,
-        });
-    }
+#[cfg(unix)]
+fn stdin() -> Result<File> {
+    use std::os::fd::AsFd;
+    Ok(File::from(std::io::stdin().as_fd().try_clone_to_owned()?))
+}
 
-    Ok(out_files)
+#[cfg(windows)]
+fn stdin() -> Result<File> {
+    use std::os::windows::io::AsHandle;
+    Ok(File::from(
+        std::io::stdin().as_handle().try_clone_to_owned()?,
+    ))
 }
 
 // #[cfg(test)]
@@ -212,7 +345,7 @@ fn extract_zip(src: &Path, temp_dir: &Path) -> Result<Vec<FileWithPath>> {
 //             },
 //         };
 //         let convert_args: ConvertedArgs = cli.into();
-//         let convert_args = convert_args.expand_dirs()?.expand_archives()?;
+//         let convert_args = convert_args.expand_dirs()?.open_files()?;
 
 //         // TODO Add checks for expanded list to have appropriate file names and do not contain archive names
 //         println!("File list: {:?}", convert_args.file_list);
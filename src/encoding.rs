@@ -0,0 +1,113 @@
+//! Decoding of log text. Logs are written in the server encoding, mostly UTF-8, but LATIN1
+//! and WIN1252 servers are common, and a log mixes encodings when databases of a cluster
+//! use different ones. Records are processed as bytes, text is decoded where it's printed
+//! or used in a report: as UTF-8 when it's valid UTF-8, else in the `--encoding`.
+
+use std::{borrow::Cow, str::FromStr, sync::OnceLock};
+
+/// Encoding of log text that isn't valid UTF-8
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Encoding {
+    /// Invalid sequences are replaced with `�`, records are printed as they are
+    #[default]
+    Utf8,
+    Latin1,
+    Win1252,
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    /// Names as in PostgreSQL, `UTF8`, `LATIN1` or `WIN1252`, and common aliases
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace(['-', '_'], "").as_str() {
+            "utf8" => Ok(Encoding::Utf8),
+            "latin1" | "iso88591" => Ok(Encoding::Latin1),
+            "win1252" | "windows1252" | "cp1252" => Ok(Encoding::Win1252),
+            _ => Err(format!("Unknown encoding: {s}")),
+        }
+    }
+}
+
+static ENCODING: OnceLock<Encoding> = OnceLock::new();
+
+/// Sets `--encoding`, before any text is decoded
+pub fn configure(encoding: Encoding) {
+    let _ = ENCODING.set(encoding);
+}
+
+fn encoding() -> Encoding {
+    *ENCODING.get_or_init(Encoding::default)
+}
+
+/// Text of log bytes, UTF-8 if they're valid UTF-8, else in the `--encoding`
+pub fn decode(bytes: &[u8]) -> Cow<'_, str> {
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return Cow::Borrowed(text);
+    }
+    match encoding() {
+        Encoding::Utf8 => String::from_utf8_lossy(bytes),
+        Encoding::Latin1 => Cow::Owned(bytes.iter().map(|&b| char::from(b)).collect()),
+        Encoding::Win1252 => Cow::Owned(bytes.iter().map(|&b| win1252(b)).collect()),
+    }
+}
+
+/// A record to print: as it is, or as UTF-8 with `--encoding`
+pub fn printable(record: &[u8]) -> Cow<'_, [u8]> {
+    if encoding() == Encoding::Utf8 {
+        return Cow::Borrowed(record);
+    }
+    match decode(record) {
+        Cow::Borrowed(text) => Cow::Borrowed(text.as_bytes()),
+        Cow::Owned(text) => Cow::Owned(text.into_bytes()),
+    }
+}
+
+/// WIN1252 is LATIN1 with printable characters instead of the C1 controls at 0x80 - 0x9F
+fn win1252(b: u8) -> char {
+    const C1: [char; 32] = [
+        '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž',
+        '\u{8f}', '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}',
+        'ž', 'Ÿ',
+    ];
+    match b {
+        0x80..=0x9f => C1[usize::from(b - 0x80)],
+        _ => char::from(b),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        // Valid UTF-8 is UTF-8 whatever the encoding
+        assert_eq!(decode("größe".as_bytes()), "größe");
+        // `--encoding` isn't set in tests
+        assert_eq!(decode(b"gr\xf6\xdfe"), "gr\u{fffd}\u{fffd}e");
+
+        assert_eq!(
+            b"gr\xf6\xdfe"
+                .iter()
+                .map(|&b| char::from(b))
+                .collect::<String>(),
+            "größe"
+        );
+        assert_eq!(
+            b"\x93quoted\x94 \x80"
+                .iter()
+                .map(|&b| win1252(b))
+                .collect::<String>(),
+            "“quoted” €"
+        );
+    }
+
+    #[test]
+    fn test_encoding() {
+        assert_eq!("UTF8".parse(), Ok(Encoding::Utf8));
+        assert_eq!("iso-8859-1".parse(), Ok(Encoding::Latin1));
+        assert_eq!("WIN1252".parse(), Ok(Encoding::Win1252));
+        assert!("EBCDIC".parse::<Encoding>().is_err());
+    }
+}
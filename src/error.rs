@@ -28,7 +28,6 @@ pub enum Error {
         input: String,
         source: crate::util::TimeParseError,
     },
-    FailedToExtractStemFromPath,
 
     // -- Parsers
     JsonNotYetImplemented,
@@ -40,6 +39,12 @@ pub enum Error {
     },
 
     // -- LogParser
+    MalformedRecord {
+        path: PathBuf,
+        /// Byte offset of the record
+        offset: u64,
+        error: Box<Error>,
+    },
     FailedToRead {
         error: std::io::Error,
     },
@@ -52,6 +57,19 @@ pub enum Error {
         error: csv::Error,
     },
 
+    // -- State
+    NothingToSave,
+    StateVersionMismatch {
+        path: PathBuf,
+        version: u32,
+    },
+    StateKindMismatch {
+        path: PathBuf,
+    },
+    UnknownAggregatorState {
+        kind: String,
+    },
+
     // -- Externals
     #[from]
     Io(std::io::Error),
@@ -59,14 +77,14 @@ pub enum Error {
     #[from]
     ParseInt(ParseIntError),
 
-    #[from]
-    Zip(zip::result::ZipError),
-
     #[from]
     TimeParseError(crate::util::TimeParseError),
 
     #[from]
     DurationError(humantime::DurationError),
+
+    #[from]
+    Json(serde_json::Error),
 }
 
 // region:    --- Custom --- Uncomment if want custom errors
@@ -0,0 +1,101 @@
+use crate::{filters::Filter, format::Format, index::IndexBlock, pgaudit::extract_audit};
+
+/// Matches pgaudit `AUDIT:` records, optionally restricted by class, object and session user.
+/// Empty lists match everything.
+#[derive(Clone, Default)]
+pub struct AuditFilter {
+    classes: Vec<String>,
+    objects: Vec<String>,
+    users: Vec<String>,
+}
+
+impl AuditFilter {
+    pub fn new(classes: Vec<String>, objects: Vec<String>, users: Vec<String>) -> Self {
+        AuditFilter {
+            classes,
+            objects,
+            users,
+        }
+    }
+}
+
+impl Filter for AuditFilter {
+    fn matches(&self, record: &[u8], fmt: &Format) -> bool {
+        let Some(entry) = extract_audit(record, fmt) else {
+            return false;
+        };
+
+        if !self.classes.is_empty()
+            && !self
+                .classes
+                .iter()
+                .any(|c| c.eq_ignore_ascii_case(&entry.class))
+        {
+            return false;
+        }
+
+        if !self.objects.is_empty()
+            && !self
+                .objects
+                .iter()
+                .any(|o| object_matches(&entry.object_name, o))
+        {
+            return false;
+        }
+
+        if !self.users.is_empty() {
+            let Some(user) = fmt.session_user_from_bytes(record) else {
+                return false;
+            };
+            if !self.users.iter().any(|u| u.as_bytes() == user) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn may_match(&self, block: &IndexBlock) -> bool {
+        block.may_have_token("audit")
+            && (self.users.is_empty()
+                || self.users.iter().any(|u| block.may_have_user(u.as_bytes())))
+    }
+}
+
+/// `public.accounts` matches `public.accounts`, `accounts` matches any schema.
+fn object_matches(object_name: &str, wanted: &str) -> bool {
+    object_name.eq_ignore_ascii_case(wanted)
+        || object_name
+            .rsplit_once('.')
+            .is_some_and(|(_, name)| name.eq_ignore_ascii_case(wanted))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_audit_filter() {
+        let record = b"2025-03-10 10:00:00.123 UTC [4321] user=alice,db=shop LOG:  AUDIT: SESSION,1,1,DDL,ALTER TABLE,TABLE,public.accounts,\"alter table accounts add column x int\",<not logged>";
+
+        assert!(AuditFilter::default().matches(record, &Format::Plain));
+        assert!(
+            AuditFilter::new(vec!["ddl".into(), "ROLE".into()], vec![], vec![])
+                .matches(record, &Format::Plain)
+        );
+        assert!(
+            !AuditFilter::new(vec!["READ".into()], vec![], vec![]).matches(record, &Format::Plain)
+        );
+        assert!(
+            AuditFilter::new(vec![], vec!["accounts".into()], vec!["alice".into()])
+                .matches(record, &Format::Plain)
+        );
+        assert!(
+            !AuditFilter::new(vec![], vec![], vec!["bob".into()]).matches(record, &Format::Plain)
+        );
+        assert!(!AuditFilter::default().matches(
+            b"2025-03-10 10:00:00.123 UTC [4321] LOG:  statement: select 1",
+            &Format::Plain
+        ));
+    }
+}
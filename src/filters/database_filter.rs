@@ -0,0 +1,26 @@
+use crate::{filters::Filter, format::Format, index::IndexBlock};
+
+/// Matches records of the given session databases, `--db`
+#[derive(Clone)]
+pub struct DatabaseFilter {
+    databases: Vec<String>,
+}
+
+impl DatabaseFilter {
+    pub fn new(databases: Vec<String>) -> Self {
+        DatabaseFilter { databases }
+    }
+}
+
+impl Filter for DatabaseFilter {
+    fn matches(&self, record: &[u8], fmt: &Format) -> bool {
+        fmt.session_db_from_bytes(record)
+            .is_some_and(|db| self.databases.iter().any(|d| d.as_bytes() == db))
+    }
+
+    fn may_match(&self, block: &IndexBlock) -> bool {
+        self.databases
+            .iter()
+            .any(|d| block.may_have_database(d.as_bytes()))
+    }
+}
@@ -1,15 +1,26 @@
+mod audit_filter;
+mod database_filter;
 mod filter_contains;
-mod filter_slow;
 mod locking_filter;
+mod query_id_filter;
+mod sql_state_filter;
 mod system_filter;
 
+pub use audit_filter::AuditFilter;
+pub use database_filter::DatabaseFilter;
 pub use filter_contains::FilterContains;
-pub use filter_slow::FilterSlow;
 pub use locking_filter::LockingFilter;
+pub use query_id_filter::QueryIdFilter;
+pub use sql_state_filter::SqlStateFilter;
 pub use system_filter::SystemFilter;
 
-use crate::format::Format;
+use crate::{format::Format, index::IndexBlock};
 
-pub trait Filter: Sync {
+pub trait Filter: Send + Sync {
     fn matches(&self, record: &[u8], fmt: &Format) -> bool;
+
+    /// Whether a record of the indexed block can match, blocks that can't are skipped
+    fn may_match(&self, _block: &IndexBlock) -> bool {
+        true
+    }
 }
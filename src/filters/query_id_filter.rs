@@ -0,0 +1,23 @@
+use crate::{filters::Filter, format::Format, index::IndexBlock};
+
+/// Matches all records logged for the given query_id
+#[derive(Clone)]
+pub struct QueryIdFilter {
+    query_id: String,
+}
+
+impl QueryIdFilter {
+    pub fn new(query_id: String) -> Self {
+        QueryIdFilter { query_id }
+    }
+}
+
+impl Filter for QueryIdFilter {
+    fn matches(&self, record: &[u8], fmt: &Format) -> bool {
+        fmt.query_id_from_bytes(record) == Some(self.query_id.as_bytes())
+    }
+
+    fn may_match(&self, block: &IndexBlock) -> bool {
+        block.may_have_query_id(self.query_id.as_bytes())
+    }
+}
@@ -0,0 +1,30 @@
+use crate::{filters::Filter, format::Format, index::IndexBlock};
+
+/// Matches records with the given SQLSTATE error codes, `errors --code`. Only CSV logs
+/// have the code at a known place.
+#[derive(Clone)]
+pub struct SqlStateFilter {
+    codes: Vec<String>,
+}
+
+impl SqlStateFilter {
+    pub fn new(codes: Vec<String>) -> Self {
+        SqlStateFilter { codes }
+    }
+}
+
+impl Filter for SqlStateFilter {
+    fn matches(&self, record: &[u8], fmt: &Format) -> bool {
+        fmt.sql_state_from_bytes(record).is_some_and(|code| {
+            self.codes
+                .iter()
+                .any(|c| c.as_bytes().eq_ignore_ascii_case(code))
+        })
+    }
+
+    fn may_match(&self, block: &IndexBlock) -> bool {
+        self.codes
+            .iter()
+            .any(|c| block.may_have_sql_state(c.to_ascii_uppercase().as_bytes()))
+    }
+}
@@ -2,6 +2,62 @@ pub fn message(record: &[u8]) -> Option<&[u8]> {
     extract_csv_field(record, 14)
 }
 
+pub fn detail(record: &[u8]) -> Option<&[u8]> {
+    extract_csv_field(record, 15).filter(|d| !d.is_empty())
+}
+
+pub fn context(record: &[u8]) -> Option<&[u8]> {
+    extract_csv_field(record, 19).filter(|c| !c.is_empty())
+}
+
+/// Statement that caused the message, the `query` column
+pub fn query(record: &[u8]) -> Option<&[u8]> {
+    extract_csv_field(record, 20).filter(|q| !q.is_empty())
+}
+
+pub fn user(record: &[u8]) -> Option<&[u8]> {
+    extract_csv_field(record, 2).filter(|u| !u.is_empty())
+}
+
+pub fn database(record: &[u8]) -> Option<&[u8]> {
+    extract_csv_field(record, 3).filter(|d| !d.is_empty())
+}
+
+/// `error_severity` column
+pub fn severity(record: &[u8]) -> Option<&[u8]> {
+    extract_csv_field(record, 12)
+}
+
+/// `sql_state_code` column
+pub fn sql_state(record: &[u8]) -> Option<&[u8]> {
+    extract_csv_field(record, 13).filter(|s| !s.is_empty())
+}
+
+pub fn pid(record: &[u8]) -> Option<&[u8]> {
+    extract_csv_field(record, 4).filter(|p| !p.is_empty())
+}
+
+/// `query_id` column, added in PG14
+pub fn query_id(record: &[u8]) -> Option<&[u8]> {
+    extract_csv_field(record, 26)
+        .map(<[u8]>::trim_ascii)
+        .filter(|q| !q.is_empty())
+}
+
+/// CSV log escapes quotes inside a field by doubling them
+pub fn unescape_quotes(field: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(field.len());
+    let mut i = 0;
+    while i < field.len() {
+        result.push(field[i]);
+        if field[i] == b'"' && i + 1 < field.len() && field[i + 1] == b'"' {
+            i += 1;
+        }
+        i += 1;
+    }
+    result
+}
+
 /// Extracts nth field from CSV record
 /// Field index is 1-based.
 fn extract_csv_field(record: &[u8], field_index: usize) -> Option<&[u8]> {
@@ -66,5 +122,7 @@ mod test {
             message(line),
             Some(b"connection received: host=10.203.8.108 port=53096".as_slice())
         );
+        assert_eq!(pid(line), Some(b"1637804".as_slice()));
+        assert_eq!(detail(line), None);
     }
 }
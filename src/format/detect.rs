@@ -0,0 +1,131 @@
+use crate::{format::Format, timestamp::is_record_start};
+
+/// Records looked at when detecting the layout
+const SAMPLE_RECORDS: usize = 5;
+/// Bytes looked at, at most
+const SAMPLE_BYTES: usize = 64 << 10;
+/// csvlog has 22 columns in PostgreSQL 12, 26 in 14+
+const CSV_FIELDS: std::ops::RangeInclusive<usize> = 22..=30;
+
+/// Layout of a log file, as detected from its content
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Layout {
+    Csv,
+    Json,
+    Syslog,
+    Plain,
+}
+
+impl Layout {
+    /// Detects the layout from the first records, None if nothing looks like a log
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        let bytes = &bytes[..bytes.len().min(SAMPLE_BYTES)];
+        let first_line = bytes
+            .split(|&b| b == b'\n')
+            .map(<[u8]>::trim_ascii)
+            .find(|line| !line.is_empty())?;
+
+        if first_line.starts_with(b"{") {
+            return Some(Layout::Json);
+        }
+        if is_syslog_header(first_line) {
+            return Some(Layout::Syslog);
+        }
+        if is_record_start(first_line) {
+            let fields = csv_field_counts(bytes);
+            if !fields.is_empty() && fields.iter().all(|n| CSV_FIELDS.contains(n)) {
+                return Some(Layout::Csv);
+            }
+        }
+        // Output of pg_ctl and the like can precede the first record
+        bytes
+            .split(|&b| b == b'\n')
+            .take(SAMPLE_RECORDS * 10)
+            .any(is_record_start)
+            .then_some(Layout::Plain)
+    }
+
+    /// Format to parse the layout with, None if it's not supported yet
+    pub fn format(self) -> Option<Format> {
+        match self {
+            Layout::Csv => Some(Format::Csv),
+            Layout::Plain => Some(Format::Plain),
+            Layout::Json | Layout::Syslog => None,
+        }
+    }
+}
+
+/// `May 30 11:03:43 host ...` (RFC 3164) or `<134>1 2025-05-30T11:03:43...` (RFC 5424)
+fn is_syslog_header(line: &[u8]) -> bool {
+    const MONTHS: [&[u8]; 12] = [
+        b"Jan", b"Feb", b"Mar", b"Apr", b"May", b"Jun", b"Jul", b"Aug", b"Sep", b"Oct", b"Nov",
+        b"Dec",
+    ];
+    if let Some(rest) = line.strip_prefix(b"<") {
+        return rest
+            .iter()
+            .position(|&b| b == b'>')
+            .is_some_and(|end| end > 0 && rest[..end].iter().all(u8::is_ascii_digit));
+    }
+    line.len() > 16
+        && MONTHS.contains(&&line[..3])
+        && line[3] == b' '
+        && (line[4] == b' ' || line[4].is_ascii_digit())
+        && line[5].is_ascii_digit()
+        && line[6] == b' '
+        && line[9] == b':'
+        && line[12] == b':'
+        && line[15] == b' '
+}
+
+/// Field counts of the first records, quoted fields can contain commas and newlines
+fn csv_field_counts(bytes: &[u8]) -> Vec<usize> {
+    let mut counts = Vec::new();
+    let mut fields = 1;
+    let mut quoted = false;
+    for &b in bytes {
+        match b {
+            b'"' => quoted = !quoted,
+            b',' if !quoted => fields += 1,
+            b'\n' if !quoted => {
+                counts.push(fields);
+                if counts.len() == SAMPLE_RECORDS {
+                    break;
+                }
+                fields = 1;
+            }
+            _ => {}
+        }
+    }
+    counts
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let detect = |path: &str| Layout::detect(&std::fs::read(path).unwrap());
+        assert_eq!(detect("./tests/files/csvlog1.csv"), Some(Layout::Csv));
+        assert_eq!(
+            detect("./tests/files/multiple_lines.csv"),
+            Some(Layout::Csv)
+        );
+        assert_eq!(
+            detect("./tests/files/debian_default2.log"),
+            Some(Layout::Plain)
+        );
+        assert_eq!(
+            detect("./testdata/pgbadger/pg_rawcsv.log"),
+            Some(Layout::Plain)
+        );
+        assert_eq!(
+            Layout::detect(b"1690547999.456 [97674] LOG:  starting PostgreSQL 17devel\n"),
+            Some(Layout::Plain)
+        );
+        assert_eq!(detect("./testdata/pgbench.json"), Some(Layout::Json));
+        assert_eq!(detect("./testdata/syslog1.log"), Some(Layout::Syslog));
+        assert_eq!(Layout::detect(b"pg_ctl: could not start server\n"), None);
+    }
+}
@@ -1,8 +1,15 @@
 mod csv;
+mod detect;
 mod plain;
 
-use crate::severity::Severity;
+pub use csv::unescape_quotes as unescape_csv_quotes;
+pub use detect::Layout;
 
+use serde::{Deserialize, Serialize};
+
+use crate::{encoding, severity::Severity};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Format {
     Csv,
     Plain,
@@ -20,10 +27,26 @@ impl Format {
         }
     }
 
-    pub fn severity_from_string(&self, text: &str) -> Severity {
+    /// Severity from where the record logs it. Plain records with a label that isn't a
+    /// severity, like DETAIL and STATEMENT, are LOG. Records without one are searched for
+    /// a severity label.
+    pub fn severity_from_bytes(&self, record: &[u8]) -> Severity {
+        let label = match self {
+            Format::Plain => crate::format::plain::severity(record),
+            Format::Csv => crate::format::csv::severity(record),
+        };
+        if let Some(label) = label {
+            if let Some(severity) = Severity::from_label(label) {
+                return severity;
+            }
+            if *self == Format::Plain {
+                return Severity::Log;
+            }
+        }
+        let text = encoding::decode(record);
         match self {
-            Format::Csv => Severity::from_csv_string(text),
-            Format::Plain => Severity::from_log_string(text),
+            Format::Plain => Severity::from_log_string(&text),
+            Format::Csv => Severity::from_csv_string(&text),
         }
     }
 
@@ -34,6 +57,73 @@ impl Format {
         }
     }
 
+    pub fn detail_from_bytes<'a>(&self, record: &'a [u8]) -> Option<&'a [u8]> {
+        match self {
+            Format::Plain => crate::format::plain::detail(record),
+            Format::Csv => crate::format::csv::detail(record),
+        }
+    }
+
+    /// CONTEXT of the message: CSV `context` column, plain "CONTEXT:" record
+    pub fn context_from_bytes<'a>(&self, record: &'a [u8]) -> Option<&'a [u8]> {
+        match self {
+            Format::Plain => crate::format::plain::context(record),
+            Format::Csv => crate::format::csv::context(record),
+        }
+    }
+
+    /// Statement the message was logged for: CSV `query` column, plain "STATEMENT:" record
+    pub fn statement_from_bytes<'a>(&self, record: &'a [u8]) -> Option<&'a [u8]> {
+        match self {
+            Format::Plain => crate::format::plain::statement(record),
+            Format::Csv => crate::format::csv::query(record),
+        }
+    }
+
+    pub fn pid_from_bytes<'a>(&self, record: &'a [u8]) -> Option<&'a [u8]> {
+        match self {
+            Format::Plain => crate::format::plain::pid(record),
+            Format::Csv => crate::format::csv::pid(record),
+        }
+    }
+
+    /// SQLSTATE error code, only logged in a known place by CSV logs
+    pub fn sql_state_from_bytes<'a>(&self, record: &'a [u8]) -> Option<&'a [u8]> {
+        match self {
+            Format::Plain => None,
+            Format::Csv => crate::format::csv::sql_state(record),
+        }
+    }
+
+    /// Query identifier (`compute_query_id`), 0 means none was computed for the statement.
+    pub fn query_id_from_bytes<'a>(&self, record: &'a [u8]) -> Option<&'a [u8]> {
+        match self {
+            Format::Plain => crate::format::plain::query_id(record),
+            Format::Csv => crate::format::csv::query_id(record),
+        }
+        .filter(|q| *q != b"0")
+    }
+
+    /// Session user of the record: CSV `user_name` column, `user=` or `user@db` in plain
+    /// log line prefix.
+    pub fn session_user_from_bytes<'a>(&self, record: &'a [u8]) -> Option<&'a [u8]> {
+        match self {
+            Format::Plain => Format::user_from_bytes(record)
+                .or_else(|| crate::format::plain::user_at_db(record).map(|(user, _)| user)),
+            Format::Csv => crate::format::csv::user(record),
+        }
+    }
+
+    /// Session database of the record: CSV `database_name` column, `db=` or `user@db` in
+    /// plain log line prefix.
+    pub fn session_db_from_bytes<'a>(&self, record: &'a [u8]) -> Option<&'a [u8]> {
+        match self {
+            Format::Plain => extract_after_needle(record, b"db=")
+                .or_else(|| crate::format::plain::user_at_db(record).map(|(_, db)| db)),
+            Format::Csv => crate::format::csv::database(record),
+        }
+    }
+
     pub fn host_from_bytes(record: &[u8]) -> Option<&[u8]> {
         extract_after_needle(record, b"host=")
     }
@@ -67,6 +157,46 @@ pub fn extract_after_needle<'a>(record: &'a [u8], needle: &'a [u8]) -> Option<&'
     }
 }
 
+/// Returns the text between `needle` and the next double quote,
+/// e.g. `slot "` in `invalidating obsolete replication slot "s1"`.
+pub fn quoted_after(text: &[u8], needle: &[u8]) -> Option<String> {
+    let start = memchr::memmem::find(text, needle)? + needle.len();
+    let end = memchr::memchr(b'"', &text[start..])?;
+    Some(encoding::decode(&text[start..start + end]).to_string())
+}
+
+/// Parses the unsigned number directly following `needle`.
+pub fn number_after(text: &[u8], needle: &[u8]) -> Option<u32> {
+    let start = memchr::memmem::find(text, needle)? + needle.len();
+    let end = text[start..]
+        .iter()
+        .position(|b| !b.is_ascii_digit())
+        .map_or(text.len(), |p| start + p);
+    std::str::from_utf8(&text[start..end]).ok()?.parse().ok()
+}
+
+/// First line of `text`, trimmed and lossily converted to UTF-8.
+pub fn lossy_line(text: &[u8]) -> String {
+    let end = memchr::memchr(b'\n', text).unwrap_or(text.len());
+    encoding::decode(&text[..end]).trim().to_string()
+}
+
+/// Finds a 24 hex digit WAL segment file name, also inside paths like `pg_wal/0000...`.
+pub fn wal_segment_name(text: &[u8]) -> Option<String> {
+    let mut start = 0;
+    while start + 24 <= text.len() {
+        let len = text[start..]
+            .iter()
+            .take_while(|b| b.is_ascii_hexdigit())
+            .count();
+        if len == 24 && (start == 0 || !text[start - 1].is_ascii_alphanumeric()) {
+            return Some(encoding::decode(&text[start..start + 24]).to_string());
+        }
+        start += len.max(1);
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -79,6 +209,24 @@ mod tests {
         assert_eq!(extracted, b"binsy");
     }
 
+    #[test]
+    fn test_wal_segment_name() {
+        assert_eq!(
+            wal_segment_name(
+                b"cp pg_wal/000000010000000000000003 /mnt/archive/000000010000000000000003"
+            ),
+            Some("000000010000000000000003".to_string())
+        );
+        assert_eq!(
+            wal_segment_name(
+                b"requested WAL segment 00000001000000000000000A has already been removed"
+            ),
+            Some("00000001000000000000000A".to_string())
+        );
+        assert_eq!(wal_segment_name(b"archived 00000002.history"), None);
+        assert_eq!(wal_segment_name(b"0000000100000000000000030"), None);
+    }
+
     #[test]
     fn test_user_extract_after_log() {
         let record = b"2021-02-14 01:34:02 CET [30291]: db=template1,user=postgres,app=[unknown],client=[local] LOG:  connection authorized: user=postgres database=template1 application_name=psql";
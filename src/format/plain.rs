@@ -1,6 +1,8 @@
+/// Message of the record, after the severity's ":  ". The log line prefix can have ": " of
+/// its own, e.g. "[%p]: [%l-1]" of pgbadger and Cloud SQL.
 #[inline]
 pub fn message(record: &[u8]) -> Option<&[u8]> {
-    let mut start = 0;
+    let mut start = memchr::memmem::find(record, b":  ").unwrap_or(0);
     while start + 1 < record.len() {
         if record[start] == b':' && record[start + 1] == b' ' {
             start += 1;
@@ -22,6 +24,116 @@ pub fn message(record: &[u8]) -> Option<&[u8]> {
     None
 }
 
+/// Severity label, the word before the first ":  ". The log line prefix comes before it,
+/// e.g. "UTC-682db26c.535-LOG:  ", and the message after it.
+#[inline]
+pub fn severity(record: &[u8]) -> Option<&[u8]> {
+    let severity_end = memchr::memmem::find(record, b":  ")?;
+    let head = &record[..severity_end];
+    let start = head
+        .iter()
+        .rposition(|b| !b.is_ascii_alphanumeric())
+        .map_or(0, |p| p + 1);
+    Some(&head[start..])
+}
+
+/// Returns the DETAIL text when the record itself is a "DETAIL:" line.
+/// In plain logs DETAIL is a separate record following the main message.
+#[inline]
+pub fn detail(record: &[u8]) -> Option<&[u8]> {
+    // Postgres always separates the severity from the message with ":  "
+    let severity_end = memchr::memmem::find(record, b":  ")?;
+    if !record[..severity_end].ends_with(b"DETAIL") {
+        return None;
+    }
+    let start = severity_end + 3;
+    let end = memchr::memchr(b'\n', &record[start..]).map_or(record.len(), |p| start + p);
+    Some(&record[start..end])
+}
+
+/// Returns the text of a "CONTEXT:" record, up to the end of the (multi-line) record.
+#[inline]
+pub fn context(record: &[u8]) -> Option<&[u8]> {
+    labeled_text(record, b"CONTEXT")
+}
+
+/// Returns the statement of a "STATEMENT:" record, which follows the message it belongs to.
+#[inline]
+pub fn statement(record: &[u8]) -> Option<&[u8]> {
+    labeled_text(record, b"STATEMENT")
+}
+
+fn labeled_text<'a>(record: &'a [u8], label: &[u8]) -> Option<&'a [u8]> {
+    let severity_end = memchr::memmem::find(record, b":  ")?;
+    if !record[..severity_end].ends_with(label) {
+        return None;
+    }
+    Some(record[severity_end + 3..].trim_ascii_end())
+}
+
+/// Extracts the process id from the log line prefix, e.g. "[12345]" in "%t [%p]".
+#[inline]
+pub fn pid(record: &[u8]) -> Option<&[u8]> {
+    let prefix_end = memchr::memmem::find(record, b": ").unwrap_or(record.len());
+    let prefix = &record[..prefix_end];
+
+    let mut i = 0;
+    while let Some(open) = memchr::memchr(b'[', &prefix[i..]) {
+        let start = i + open + 1;
+        let mut end = start;
+        while end < prefix.len() && prefix[end].is_ascii_digit() {
+            end += 1;
+        }
+        if end > start && end < prefix.len() && prefix[end] == b']' {
+            return Some(&prefix[start..end]);
+        }
+        i = start;
+    }
+    None
+}
+
+/// Extracts `user@db` (`%u@%d`) from the log line prefix.
+#[inline]
+pub fn user_at_db(record: &[u8]) -> Option<(&[u8], &[u8])> {
+    let prefix_end = memchr::memmem::find(record, b":  ").unwrap_or(record.len());
+    record[..prefix_end]
+        .split(|&b| b == b' ')
+        .filter_map(|token| {
+            let at = memchr::memchr(b'@', token)?;
+            Some((&token[..at], &token[at + 1..]))
+        })
+        .find(|(user, db)| {
+            let valid = |part: &[u8]| {
+                !part.is_empty()
+                    && part
+                        .iter()
+                        .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-' | b'.'))
+            };
+            valid(user) && valid(db)
+        })
+}
+
+/// Extracts the query identifier from the log line prefix, logged with `%Q` as
+/// e.g. "[queryid=-4623036638694764959]" or "query_id=...".
+#[inline]
+pub fn query_id(record: &[u8]) -> Option<&[u8]> {
+    let prefix_end = memchr::memmem::find(record, b":  ").unwrap_or(record.len());
+    let prefix = &record[..prefix_end];
+
+    let start = [b"queryid=".as_slice(), b"query_id="]
+        .iter()
+        .find_map(|needle| memchr::memmem::find(prefix, needle).map(|p| p + needle.len()))?;
+    let sign = usize::from(prefix.get(start) == Some(&b'-'));
+    let digits = prefix[start + sign..]
+        .iter()
+        .take_while(|b| b.is_ascii_digit())
+        .count();
+    if digits == 0 {
+        return None;
+    }
+    Some(&prefix[start..start + sign + digits])
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -42,5 +154,55 @@ mod test {
             Some(b"disconnection: session time: 0:00:20.034 user=azuresu database=azure_maintenance host=127.0.0.1 port=55304".as_slice()),
             message(line)
         );
+
+        // ": " of the log line prefix, "%m [%p]: [%l-1] db=%d,user=%u"
+        let line = b"2025-05-21 11:00:40.291 UTC [675]: [1-1] db=postgres,user=cloudsqladmin,host=127.0.0.1 LOG:  duration: 4.053 ms  statement: SELECT 1";
+        assert_eq!(
+            Some(b"duration: 4.053 ms  statement: SELECT 1".as_slice()),
+            message(line)
+        );
+    }
+
+    #[test]
+    fn plain_pid_and_detail() {
+        let line = b"2023-08-04 12:20:37.426 UTC [1920035] 031_recovery_conflict.pl DETAIL:  User was holding shared buffer pin for too long.";
+        assert_eq!(Some(b"1920035".as_slice()), pid(line));
+        assert_eq!(
+            Some(b"User was holding shared buffer pin for too long.".as_slice()),
+            detail(line)
+        );
+
+        let line = b"2025-05-21 11:00:40.296 UTC [675]: [3-1] db=postgres,user=cloudsqladmin LOG:  duration: 3.032 ms";
+        assert_eq!(Some(b"675".as_slice()), pid(line));
+        assert_eq!(None, detail(line));
+
+        let line = b"2025-05-21 11:00:40.296 UTC [675]: [4-1] db=postgres,user=cloudsqladmin DETAIL:  parameters: $1 = '1'";
+        assert_eq!(Some(b"parameters: $1 = '1'".as_slice()), detail(line));
+
+        let line =
+            b"2025-05-21 11:01:20 UTC-682db26c.535-LOG:  disconnection: session time: 0:00:20.034";
+        assert_eq!(None, pid(line));
+    }
+
+    #[test]
+    fn plain_query_id() {
+        let line = b"2022-02-17 16:27:19.123 UTC [2129] [queryid=-4623036638694764959] postgres@stackoverflow LOG:  duration: 13556.339 ms  statement: select * from t1;";
+        assert_eq!(Some(b"-4623036638694764959".as_slice()), query_id(line));
+
+        let line =
+            b"2025-05-21 11:00:40.296 UTC [675] LOG:  statement: select 1 where 'queryid=1' = ''";
+        assert_eq!(None, query_id(line));
+    }
+
+    #[test]
+    fn plain_user_at_db() {
+        let line = b"2022-02-17 16:27:19.123 UTC [2129] [queryid=1] postgres@stackoverflow LOG:  statement: select 'a@b';";
+        assert_eq!(
+            Some((b"postgres".as_slice(), b"stackoverflow".as_slice())),
+            user_at_db(line)
+        );
+
+        let line = b"2025-05-21 11:00:40.296 UTC [675] LOG:  statement: select 'a@b'";
+        assert_eq!(None, user_at_db(line));
     }
 }
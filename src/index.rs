@@ -0,0 +1,441 @@
+//! Sidecar index files (`<log>.pgwidx`) written by the `index` command. A log is split
+//! into blocks of records, each summarized by its time range, severity counts and bloom
+//! filters of users, databases, SQLSTATEs and message tokens. Blocks that can't contain a
+//! record passing the filters are skipped without parsing them.
+
+use std::{
+    fs::{self, File},
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local};
+use log::{debug, warn};
+use memmap2::MmapOptions;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Result,
+    bookmarks::inode,
+    compression::Compression,
+    convert_args::FileWithPath,
+    format::Format,
+    output_results::{log_format, record_start_at, record_time, records},
+    timestamp::is_record_start,
+};
+
+/// Bumped whenever the index layout changes, older indexes are ignored
+const INDEX_VERSION: u32 = 3;
+pub const EXTENSION: &str = "pgwidx";
+const BLOCK_SIZE: usize = 4 << 20;
+/// Bytes hashed to recognize the indexed file
+const FINGERPRINT_LEN: usize = 4096;
+const NAME_BLOOM_WORDS: usize = 4;
+const TOKEN_BLOOM_WORDS: usize = 128;
+/// Words shorter than this aren't indexed
+const MIN_TOKEN_LEN: usize = 3;
+
+#[derive(Serialize, Deserialize)]
+pub struct Index {
+    version: u32,
+    inode: u64,
+    fingerprint: u64,
+    format: Format,
+    /// Indexed bytes, the last record is left out as it may still be written
+    size: usize,
+    blocks: Vec<IndexBlock>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct IndexBlock {
+    start: usize,
+    end: usize,
+    /// None if a record has no readable timestamp
+    min_time: Option<DateTime<Local>>,
+    max_time: Option<DateTime<Local>>,
+    /// Records per severity level, see `i32::from(Severity)`
+    severities: Vec<u64>,
+    users: Bloom,
+    databases: Bloom,
+    sql_states: Bloom,
+    tokens: Bloom,
+}
+
+impl IndexBlock {
+    fn summarize(bytes: &[u8], range: Range<usize>, format: Format) -> Self {
+        let mut block = IndexBlock {
+            start: range.start,
+            end: range.end,
+            min_time: None,
+            max_time: None,
+            severities: Vec::new(),
+            users: Bloom::new(NAME_BLOOM_WORDS),
+            databases: Bloom::new(NAME_BLOOM_WORDS),
+            sql_states: Bloom::new(NAME_BLOOM_WORDS),
+            tokens: Bloom::new(TOKEN_BLOOM_WORDS),
+        };
+        let mut times_readable = true;
+
+        for record in records(&bytes[range]) {
+            match record_time(record) {
+                Ok(time) => {
+                    block.min_time = Some(block.min_time.map_or(time, |t| t.min(time)));
+                    block.max_time = Some(block.max_time.map_or(time, |t| t.max(time)));
+                }
+                Err(_) => times_readable = false,
+            }
+
+            let level = usize::try_from(i32::from(format.severity_from_bytes(record))).unwrap_or(0);
+            if block.severities.len() <= level {
+                block.severities.resize(level + 1, 0);
+            }
+            block.severities[level] += 1;
+
+            if let Some(user) = format.session_user_from_bytes(record) {
+                block.users.insert(user);
+            }
+            if let Some(db) = format.session_db_from_bytes(record) {
+                block.databases.insert(db);
+            }
+            if let Some(sql_state) = format.sql_state_from_bytes(record) {
+                block.sql_states.insert(sql_state);
+            }
+            if let Some(query_id) = format.query_id_from_bytes(record) {
+                block.tokens.insert(&query_id_token(query_id));
+            }
+            if let Some(message) = format.message_from_bytes(record) {
+                for token in tokens(message) {
+                    block.tokens.insert(&token);
+                }
+            }
+        }
+
+        if !times_readable {
+            block.min_time = None;
+            block.max_time = None;
+        }
+        block
+    }
+
+    /// Whether records between `begin` and `end` can be in the block
+    pub fn may_overlap(
+        &self,
+        begin: Option<DateTime<Local>>,
+        end: Option<DateTime<Local>>,
+    ) -> bool {
+        let (Some(min_time), Some(max_time)) = (self.min_time, self.max_time) else {
+            return true;
+        };
+        begin.is_none_or(|begin| max_time >= begin) && end.is_none_or(|end| min_time <= end)
+    }
+
+    /// Whether the block has records of at least this severity level
+    pub fn may_have_severity(&self, min_level: i32) -> bool {
+        let min_level = usize::try_from(min_level).unwrap_or(0);
+        self.severities
+            .iter()
+            .skip(min_level)
+            .any(|&count| count > 0)
+    }
+
+    pub fn may_have_user(&self, user: &[u8]) -> bool {
+        self.users.may_contain(user)
+    }
+
+    pub fn may_have_database(&self, database: &[u8]) -> bool {
+        self.databases.may_contain(database)
+    }
+
+    /// SQLSTATEs are only indexed for CSV logs
+    pub fn may_have_sql_state(&self, sql_state: &[u8]) -> bool {
+        self.sql_states.may_contain(sql_state)
+    }
+
+    /// Whether a message of the block can contain the word, case insensitive
+    pub fn may_have_token(&self, word: &str) -> bool {
+        self.tokens
+            .may_contain(word.to_ascii_lowercase().as_bytes())
+    }
+
+    pub fn may_have_query_id(&self, query_id: &[u8]) -> bool {
+        self.tokens.may_contain(&query_id_token(query_id))
+    }
+}
+
+impl Index {
+    /// Byte ranges of the blocks within `range` that `keep` accepts, adjacent ones joined.
+    /// Whatever was appended after indexing is kept too.
+    pub fn kept_ranges(
+        &self,
+        range: Range<usize>,
+        keep: impl Fn(&IndexBlock) -> bool,
+    ) -> Vec<Range<usize>> {
+        let mut kept: Vec<Range<usize>> = Vec::new();
+        for (start, end) in self
+            .blocks
+            .iter()
+            .filter(|b| keep(b))
+            .map(|b| (b.start, b.end))
+            .chain([(self.size, range.end)])
+        {
+            let (start, end) = (start.max(range.start), end.min(range.end));
+            if start >= end {
+                continue;
+            }
+            match kept.last_mut() {
+                Some(last) if last.end == start => last.end = end,
+                _ => kept.push(start..end),
+            }
+        }
+        kept
+    }
+
+    pub fn blocks(&self) -> usize {
+        self.blocks.len()
+    }
+}
+
+/// Writes an index next to each log file
+pub fn write(files: Vec<FileWithPath>, forced: Option<Format>) -> Result<()> {
+    for file_with_path in files {
+        let path = &file_with_path.path;
+        let metadata = file_with_path.file.metadata()?;
+        if !metadata.is_file() || metadata.len() == 0 {
+            warn!("Skipping {}: only log files can be indexed", path.display());
+            continue;
+        }
+        let mmap = unsafe { MmapOptions::new().map(&file_with_path.file)? };
+        if Compression::detect(&mmap).is_some() {
+            warn!(
+                "Skipping {}: compressed logs can't be indexed",
+                path.display()
+            );
+            continue;
+        }
+        let Some(format) = log_format(path, &mmap, forced) else {
+            continue;
+        };
+
+        let size = last_record_start(&mmap);
+        let index = Index {
+            version: INDEX_VERSION,
+            inode: inode(&metadata),
+            fingerprint: fingerprint(&mmap),
+            format,
+            size,
+            blocks: block_ranges(&mmap[..size])
+                .into_par_iter()
+                .map(|range| IndexBlock::summarize(&mmap, range, format))
+                .collect(),
+        };
+        fs::write(index_path(path), serde_json::to_vec(&index)?)?;
+        println!(
+            "Indexed {} in {} blocks: {}",
+            path.display(),
+            index.blocks.len(),
+            index_path(path).display()
+        );
+    }
+    Ok(())
+}
+
+/// Index of the log file if there is an up to date one
+pub fn load(path: &Path, file: &File, bytes: &[u8], format: Format) -> Option<Index> {
+    let index_path = index_path(path);
+    if !index_path.exists() {
+        return None;
+    }
+    let index: Index = match fs::read(&index_path)
+        .map_err(crate::Error::from)
+        .and_then(|bytes| Ok(serde_json::from_slice(&bytes)?))
+    {
+        Ok(index) => index,
+        Err(e) => {
+            warn!("Ignoring index {}: {e:?}", index_path.display());
+            return None;
+        }
+    };
+
+    let current = index.version == INDEX_VERSION
+        && file.metadata().is_ok_and(|m| inode(&m) == index.inode)
+        && index.size <= bytes.len()
+        && index.fingerprint == fingerprint(bytes)
+        && index.format == format;
+    if !current {
+        debug!("Index {} is outdated", index_path.display());
+        return None;
+    }
+    Some(index)
+}
+
+fn index_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(EXTENSION);
+    PathBuf::from(name)
+}
+
+fn fingerprint(bytes: &[u8]) -> u64 {
+    fnv1a(&bytes[..bytes.len().min(FINGERPRINT_LEN)], 0)
+}
+
+/// Blocks of about `BLOCK_SIZE`, ending at record starts
+fn block_ranges(bytes: &[u8]) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    while start < bytes.len() {
+        let end = if start + BLOCK_SIZE >= bytes.len() {
+            bytes.len()
+        } else {
+            record_start_at(bytes, start + BLOCK_SIZE)
+        };
+        ranges.push(start..end);
+        start = end;
+    }
+    ranges
+}
+
+fn last_record_start(bytes: &[u8]) -> usize {
+    let mut end = bytes.len();
+    while let Some(newline) = memchr::memrchr(b'\n', &bytes[..end]) {
+        if is_record_start(&bytes[newline + 1..]) {
+            return newline + 1;
+        }
+        end = newline;
+    }
+    0
+}
+
+/// Lowercased words of a message
+fn tokens(message: &[u8]) -> impl Iterator<Item = Vec<u8>> {
+    message
+        .split(|b| !b.is_ascii_alphanumeric() && *b != b'_')
+        .filter(|word| word.len() >= MIN_TOKEN_LEN)
+        .map(<[u8]>::to_ascii_lowercase)
+}
+
+fn query_id_token(query_id: &[u8]) -> Vec<u8> {
+    [b"query_id=".as_slice(), query_id].concat()
+}
+
+/// Bloom filter with a stable hash, as it's saved to disk
+#[derive(Serialize, Deserialize)]
+struct Bloom {
+    #[serde(with = "hex")]
+    bits: Vec<u64>,
+}
+
+impl Bloom {
+    const HASHES: u64 = 4;
+
+    fn new(words: usize) -> Self {
+        Bloom {
+            bits: vec![0; words],
+        }
+    }
+
+    /// Word and bit mask of each hash of the item
+    fn positions(words: usize, item: &[u8]) -> impl Iterator<Item = (usize, u64)> {
+        let len = (words * 64) as u64;
+        let (h1, h2) = (fnv1a(item, 0), fnv1a(item, 1) | 1);
+        (0..Self::HASHES).map(move |i| {
+            let bit = h1.wrapping_add(i.wrapping_mul(h2)) % len;
+            ((bit / 64) as usize, 1 << (bit % 64))
+        })
+    }
+
+    fn insert(&mut self, item: &[u8]) {
+        for (word, mask) in Self::positions(self.bits.len(), item) {
+            self.bits[word] |= mask;
+        }
+    }
+
+    fn may_contain(&self, item: &[u8]) -> bool {
+        self.bits.is_empty()
+            || Self::positions(self.bits.len(), item)
+                .all(|(word, mask)| self.bits[word] & mask != 0)
+    }
+}
+
+fn fnv1a(bytes: &[u8], seed: u64) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325 ^ seed;
+    for &b in bytes {
+        hash ^= u64::from(b);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// Bloom filter bits as a hex string, a lot more compact than JSON numbers
+mod hex {
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S: Serializer>(bits: &[u64], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&bits.iter().map(|w| format!("{w:016x}")).collect::<String>())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u64>, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        (0..hex.len())
+            .step_by(16)
+            .map(|i| {
+                hex.get(i..i + 16)
+                    .and_then(|word| u64::from_str_radix(word, 16).ok())
+                    .ok_or_else(|| D::Error::custom("invalid bloom filter"))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bloom() {
+        let mut bloom = Bloom::new(NAME_BLOOM_WORDS);
+        bloom.insert(b"postgres");
+        assert!(bloom.may_contain(b"postgres"));
+        assert!(!bloom.may_contain(b"app"));
+
+        let json = serde_json::to_string(&bloom).unwrap();
+        let restored: Bloom = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.bits, bloom.bits);
+    }
+
+    #[test]
+    fn test_summarize() {
+        let bytes = std::fs::read("./tests/files/csvlog1.csv").unwrap();
+        let block = IndexBlock::summarize(&bytes, 0..bytes.len(), Format::Csv);
+        assert!(block.may_have_severity(i32::from(crate::Severity::Error)));
+        assert!(!block.may_have_severity(i32::from(crate::Severity::Fatal)));
+        assert!(block.may_have_user(b"krl"));
+        assert!(block.may_have_sql_state(b"23503"));
+        assert!(block.may_have_database(b"postgres"));
+        assert!(block.may_have_token("Violates"));
+        assert!(!block.may_have_token("deadlock"));
+        assert!(block.may_overlap(None, None));
+    }
+
+    #[test]
+    fn test_kept_ranges() {
+        let bytes = std::fs::read("./tests/files/debian_default2.log").unwrap();
+        let index = Index {
+            version: INDEX_VERSION,
+            inode: 0,
+            fingerprint: 0,
+            format: Format::Plain,
+            size: 200,
+            blocks: vec![
+                IndexBlock::summarize(&bytes, 0..100, Format::Plain),
+                IndexBlock::summarize(&bytes, 100..200, Format::Plain),
+            ],
+        };
+        assert_eq!(index.kept_ranges(0..300, |_| true), vec![0..300]);
+        assert_eq!(
+            index.kept_ranges(50..300, |b| b.start != 100),
+            vec![50..100, 200..300]
+        );
+    }
+}
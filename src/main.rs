@@ -21,10 +21,22 @@
 //!  - [x] slow
 //!   - [x] filter by threshold
 //!   - [x] top slow queries
+//!   - [x] per query_id
 //!   - [ ] stat
 //!  - [ ] stats
 //!  - [x] system
 //!  - [ ] connections
+//!  - [x] replication
+//!  - [x] archive
+//!  - [x] config
+//!  - [x] audit
+//!    - [x] list
+//!    - [x] summary
+//!  - [x] plans
+//!  - [x] workload
+//!  - [x] tables
+//!  - [x] merge saved states
+//!  - [x] follow mode
 
 // Uncomment the following line to enable all clippy lints & pedantic mode
 // #![warn(clippy::all, clippy::pedantic)]
@@ -36,31 +48,43 @@
 // clippy::panic
 // )]
 
-use std::time::Duration;
+use std::{path::PathBuf, time::Duration};
 
 use humantime::parse_duration;
 use log::{debug, error};
 
 use crate::{
     aggregators::{
-        Aggregator, ConnectionsAggregator, ErrorFrequencyAggregator, ErrorHistogramAggregator,
-        TopSlowQueries,
+        Aggregator, ArchiveAggregator, AuditAggregator, ConfigAggregator, ConnectionsAggregator,
+        ErrorFrequencyAggregator, ErrorHistogramAggregator, PlansAggregator, QueryIdAggregator,
+        ReplicationAggregator, SlowQueries, TablesAggregator, TablesOrder, TopSlowQueries,
+        WorkloadAggregator,
     },
     convert_args::ConvertedArgs,
-    filters::{Filter, FilterSlow},
+    filters::{AuditFilter, Filter, QueryIdFilter, SqlStateFilter},
     output_results::output_results,
     severity::Severity,
 };
 
 mod aggregators;
+mod bookmarks;
 mod cli;
+mod compression;
 mod convert_args;
 mod duration;
+mod encoding;
 mod error;
 mod filters;
 mod format;
+mod index;
 mod output_results;
+mod pgaudit;
+mod plan;
 mod severity;
+mod sql;
+mod state;
+mod statement;
+mod timestamp;
 mod util;
 
 pub use self::error::{Error, Result};
@@ -70,7 +94,10 @@ fn main() -> Result<()> {
     let matches = cli.clone().get_matches();
 
     let mut converted_args: ConvertedArgs = ConvertedArgs::parse_from_matches(matches.clone())?;
-    converted_args = converted_args.expand_dirs()?.expand_archives()?;
+    converted_args = converted_args
+        .expand_dirs()?
+        .open_files()?
+        .resolve_times()?;
 
     let mut aggregators: Vec<Box<dyn Aggregator>> = Vec::new();
     let mut filters: Vec<Box<dyn Filter>> = Vec::new();
@@ -78,6 +105,16 @@ fn main() -> Result<()> {
     match matches.subcommand() {
         Some(("errors", sub_matches)) => {
             let error_command = sub_matches.subcommand().unwrap_or(("list", sub_matches));
+            let codes: Vec<String> = error_command
+                .1
+                .get_many::<String>("code")
+                .into_iter()
+                .flatten()
+                .cloned()
+                .collect();
+            if !codes.is_empty() {
+                filters.push(Box::new(SqlStateFilter::new(codes)));
+            }
             match error_command {
                 ("list", list_subcommand) => {
                     output_results(
@@ -144,13 +181,109 @@ fn main() -> Result<()> {
             converted_args.print_details = false;
             output_results(converted_args, Severity::Log, &mut aggregators, &filters)?;
         }
+        Some(("replication", _)) => {
+            aggregators.push(Box::new(ReplicationAggregator::new()));
+            converted_args.print_details = false;
+            output_results(converted_args, Severity::Log, &mut aggregators, &filters)?;
+        }
+        Some(("archive", _)) => {
+            aggregators.push(Box::new(ArchiveAggregator::new()));
+            converted_args.print_details = false;
+            // Successful archiving is only logged on DEBUG1
+            output_results(converted_args, Severity::Debug5, &mut aggregators, &filters)?;
+        }
+        Some(("config", _)) => {
+            aggregators.push(Box::new(ConfigAggregator::new()));
+            converted_args.print_details = false;
+            output_results(converted_args, Severity::Log, &mut aggregators, &filters)?;
+        }
+        Some(("audit", sub_matches)) => {
+            let (audit_command, audit_matches) =
+                sub_matches.subcommand().unwrap_or(("list", sub_matches));
+            let values = |name: &str| -> Vec<String> {
+                audit_matches
+                    .get_many::<String>(name)
+                    .into_iter()
+                    .flatten()
+                    .cloned()
+                    .collect()
+            };
+            filters.push(Box::new(AuditFilter::new(
+                values("class"),
+                values("object"),
+                values("user"),
+            )));
+            if audit_command == "summary" {
+                aggregators.push(Box::new(AuditAggregator::new()));
+                converted_args.print_details = false;
+            }
+            output_results(converted_args, Severity::Log, &mut aggregators, &filters)?;
+        }
+        Some(("workload", sub_matches)) => {
+            let mut interval = Duration::from_hours(1);
+            if let Some(interval_str) = sub_matches.get_one::<String>("bucket") {
+                interval = parse_duration(interval_str)?;
+            }
+            aggregators.push(Box::new(WorkloadAggregator::new(interval)));
+            converted_args.print_details = false;
+            output_results(converted_args, Severity::Log, &mut aggregators, &filters)?;
+        }
+        Some(("tables", sub_matches)) => {
+            let limit = *sub_matches.get_one::<usize>("max").unwrap_or(&20);
+            let order = match sub_matches.get_one::<String>("sort").map(String::as_str) {
+                Some("calls") => TablesOrder::Calls,
+                Some("locks") => TablesOrder::Locks,
+                _ => TablesOrder::Duration,
+            };
+            aggregators.push(Box::new(TablesAggregator::new(limit, order)));
+            converted_args.print_details = false;
+            output_results(converted_args, Severity::Log, &mut aggregators, &filters)?;
+        }
+        Some(("plans", sub_matches)) => {
+            let limit = *sub_matches.get_one::<usize>("max").unwrap_or(&10);
+            aggregators.push(Box::new(PlansAggregator::new(limit)));
+            converted_args.print_details = false;
+            output_results(converted_args, Severity::Log, &mut aggregators, &filters)?;
+        }
+        Some(("index", _)) => {
+            index::write(converted_args.files, converted_args.format)?;
+        }
+        Some(("merge", sub_matches)) => {
+            let paths: Vec<PathBuf> = sub_matches
+                .get_many::<PathBuf>("STATE")
+                .into_iter()
+                .flatten()
+                .cloned()
+                .collect();
+            let mut aggregators = state::load(&paths)?;
+            if let Some(path) = &converted_args.save_state {
+                state::save(path, &aggregators)?;
+            } else {
+                for agg in &mut aggregators {
+                    agg.print();
+                }
+            }
+        }
         Some(("peaks" | "stats", _)) => {
             error!("Not implemented");
         }
         Some(("slow", sub_matches)) => {
-            if let Some(("top", _)) = sub_matches.subcommand() {
+            if let Some(("queryid", queryid_matches)) = sub_matches.subcommand() {
+                if let Some(query_id) = queryid_matches.get_one::<String>("id") {
+                    filters.push(Box::new(QueryIdFilter::new(query_id.clone())));
+                    output_results(converted_args, Severity::Debug5, &mut aggregators, &filters)?;
+                } else {
+                    let limit = *queryid_matches.get_one::<usize>("max").unwrap_or(&20);
+                    aggregators.push(Box::new(QueryIdAggregator::new(limit)));
+                    converted_args.print_details = false;
+                    output_results(converted_args, Severity::Log, &mut aggregators, &filters)?;
+                }
+            } else if let Some(("top", top_matches)) = sub_matches.subcommand() {
                 debug!("Using TopSlowQueryAggregator");
-                aggregators.push(Box::new(TopSlowQueries::new(10)));
+                aggregators.push(Box::new(TopSlowQueries::new(
+                    10,
+                    top_matches.get_flag("params"),
+                )));
                 converted_args.print_details = false;
                 output_results(converted_args, Severity::Log, &mut aggregators, &filters)?;
             } else {
@@ -158,8 +291,14 @@ fn main() -> Result<()> {
                 if let Some(treshold_str) = sub_matches.get_one::<String>("TRESHOLD") {
                     treshold = parse_duration(treshold_str)?;
                 }
-                filters.push(Box::new(FilterSlow::new(treshold)));
-                debug!("Using FilterSlow with treshold {treshold:?}");
+                // Statements are collected and printed once their duration and parameters,
+                // which can be logged in separate records, are known
+                aggregators.push(Box::new(SlowQueries::new(
+                    treshold,
+                    sub_matches.get_flag("params"),
+                )));
+                converted_args.print_details = false;
+                debug!("Using SlowQueries with treshold {treshold:?}");
                 output_results(converted_args, Severity::Log, &mut aggregators, &filters)?;
             }
         }
@@ -0,0 +1,254 @@
+//! Follow mode (`--follow`): keeps reading records appended to the log files, like
+//! `tail -f`. Log files replaced by rotation are reopened, and new files showing up in the
+//! log directories (`log_filename` patterns with a timestamp) are followed too.
+
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::Local;
+use log::{debug, warn};
+
+use crate::{
+    Result,
+    aggregators::Aggregator,
+    bookmarks::inode,
+    format::Format,
+    output_results::{
+        FilterContainer, filter_record, log_format,
+        malformed::{BadRecord, BadRecords},
+    },
+    timestamp::{self, is_record_start},
+};
+
+/// Bytes read to detect the format of a log
+const DETECT_LEN: u64 = 64 << 10;
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// Polls without new data after which the last record is considered complete
+const IDLE_POLLS: u32 = 2;
+
+struct FollowedFile {
+    path: PathBuf,
+    file: File,
+    inode: u64,
+    offset: u64,
+    /// None for logs that can't be parsed
+    format: Option<Format>,
+    /// Last, possibly still growing record
+    pending: Vec<u8>,
+    idle_polls: u32,
+}
+
+impl FollowedFile {
+    fn open(path: &Path, offset: u64, forced: Option<Format>) -> Result<Self> {
+        let mut file = File::open(path)?;
+        let mut head = Vec::new();
+        (&mut file).take(DETECT_LEN).read_to_end(&mut head)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            inode: inode(&file.metadata()?),
+            file,
+            offset,
+            format: log_format(path, &head, forced),
+            pending: Vec::new(),
+            idle_polls: 0,
+        })
+    }
+
+    /// Reads what was appended since the last poll, returns complete records with their
+    /// offset in the file
+    fn poll(&mut self) -> Result<Vec<(u64, Vec<u8>)>> {
+        if self.file.metadata()?.len() < self.offset {
+            debug!("{} was truncated", self.path.display());
+            self.offset = 0;
+            self.pending.clear();
+        }
+
+        self.file.seek(SeekFrom::Start(self.offset))?;
+        let appended = self.file.read_to_end(&mut self.pending)?;
+        self.offset += appended as u64;
+
+        let pending_offset = self.offset - self.pending.len() as u64;
+
+        if appended == 0 {
+            self.idle_polls += 1;
+            if self.idle_polls >= IDLE_POLLS && self.pending.ends_with(b"\n") {
+                return Ok(vec![(pending_offset, std::mem::take(&mut self.pending))]);
+            }
+            return Ok(Vec::new());
+        }
+        self.idle_polls = 0;
+
+        // Every record but the last one is complete once the next one starts
+        let mut records = Vec::new();
+        let mut record_start = 0;
+        let mut line_start = 0;
+        while let Some(newline) = memchr::memchr(b'\n', &self.pending[line_start..]) {
+            let next = line_start + newline + 1;
+            if line_start > record_start && is_record_start(&self.pending[line_start..next]) {
+                records.push((
+                    pending_offset + record_start as u64,
+                    self.pending[record_start..line_start].to_vec(),
+                ));
+                record_start = line_start;
+            }
+            line_start = next;
+        }
+        // A record split over two polls, its next line may have been written already
+        if line_start < self.pending.len()
+            && line_start > record_start
+            && is_record_start(&self.pending[line_start..])
+        {
+            records.push((
+                pending_offset + record_start as u64,
+                self.pending[record_start..line_start].to_vec(),
+            ));
+            record_start = line_start;
+        }
+        self.pending.drain(..record_start);
+
+        Ok(records)
+    }
+
+    /// Whether a new file was created at the path, e.g. by log rotation
+    fn replaced(&self) -> bool {
+        fs::metadata(&self.path).is_ok_and(|m| inode(&m) != self.inode)
+    }
+}
+
+pub(super) fn follow<'a>(
+    files: Vec<(PathBuf, u64)>,
+    filter_container: impl Fn(Format) -> FilterContainer<'a>,
+    aggregators: &mut Vec<Box<dyn Aggregator>>,
+    bad: &mut BadRecords,
+    print_details: bool,
+    refresh: Duration,
+    forced: Option<Format>,
+) -> Result<()> {
+    let mut known: HashSet<PathBuf> = files.iter().map(|(path, _)| path.clone()).collect();
+    let dirs: HashSet<PathBuf> = files
+        .iter()
+        .filter_map(|(path, _)| path.parent())
+        // The parent of a bare file name is empty
+        .map(|dir| {
+            if dir.as_os_str().is_empty() {
+                PathBuf::from(".")
+            } else {
+                dir.to_path_buf()
+            }
+        })
+        .collect();
+    let extensions: HashSet<String> = files
+        .iter()
+        .filter_map(|(path, _)| path.extension())
+        .map(|ext| ext.to_string_lossy().to_string())
+        .collect();
+    let mut followed = files
+        .iter()
+        .map(|(path, offset)| FollowedFile::open(path, *offset, forced))
+        .collect::<Result<Vec<_>>>()?;
+    // Rotated files can show up under a new name
+    let mut inodes: HashSet<u64> = followed.iter().map(|f| f.inode).collect();
+
+    print_report(aggregators);
+    let mut last_refresh = Instant::now();
+    let mut changed = false;
+    loop {
+        if changed && last_refresh.elapsed() >= refresh {
+            print_report(aggregators);
+            last_refresh = Instant::now();
+            changed = false;
+        }
+
+        thread::sleep(POLL_INTERVAL);
+
+        for file in &mut followed {
+            let mut records = file.poll()?;
+            if file.replaced() {
+                // Whatever was written to the old file is read by now
+                debug!("{} was rotated", file.path.display());
+                let pending_offset = file.offset - file.pending.len() as u64;
+                records.push((pending_offset, std::mem::take(&mut file.pending)));
+                *file = FollowedFile::open(&file.path.clone(), 0, forced)?;
+                inodes.insert(file.inode);
+            }
+
+            let Some(format) = file.format else {
+                continue;
+            };
+            let filters = filter_container(format);
+            let mut printed = Vec::new();
+            for (offset, record) in records.into_iter().filter(|(_, r)| !r.is_empty()) {
+                if let Err(error) = filter_record(
+                    &record,
+                    &filters,
+                    aggregators,
+                    print_details.then_some(&mut printed),
+                ) {
+                    let record = BadRecord {
+                        path: file.path.clone(),
+                        offset,
+                        error,
+                        record,
+                    };
+                    if filters.strict {
+                        return Err(record.into_error());
+                    }
+                    // Reported as they come in, the run doesn't end
+                    warn!(
+                        "Skipping malformed record at {}:{offset}: {:?}",
+                        file.path.display(),
+                        record.error
+                    );
+                    bad.add(record)?;
+                }
+                changed = true;
+            }
+            io::stdout().write_all(&printed)?;
+        }
+
+        for dir in &dirs {
+            let Ok(entries) = fs::read_dir(dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let followable = path.is_file()
+                    && path
+                        .extension()
+                        .is_some_and(|ext| extensions.contains(ext.to_string_lossy().as_ref()));
+                if !followable || !known.insert(path.clone()) {
+                    continue;
+                }
+                let file = FollowedFile::open(&path, 0, forced)?;
+                if inodes.insert(file.inode) {
+                    debug!("Following new file {}", path.display());
+                    followed.push(file);
+                }
+            }
+        }
+
+        // Reports listing records are printed as they come in, records waiting for their
+        // parameters or duration stay until the next poll
+        for aggregator in aggregators.iter_mut().filter(|a| a.streams()) {
+            aggregator.print_ready();
+        }
+    }
+}
+
+/// Prints the report so far, aggregators keep on aggregating
+fn print_report(aggregators: &[Box<dyn Aggregator>]) {
+    let mut reports = aggregators.iter().filter(|a| !a.streams()).peekable();
+    if reports.peek().is_none() {
+        return;
+    }
+    println!("--- {} ---", timestamp::display(Local::now()));
+    for aggregator in reports {
+        aggregator.boxed_clone().print();
+    }
+}
@@ -0,0 +1,97 @@
+//! Records that can't be parsed, like a truncated line or binary garbage in a mailing list
+//! archive. They're counted and skipped, so one bad record doesn't cost the whole run, and
+//! listed with their file and byte offset at the end. `--strict` stops at the first one
+//! instead, `--dump-bad` writes them all to a file.
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use log::warn;
+
+use crate::{Error, Result};
+
+/// Malformed records listed at the end of the run, all of them are counted and dumped
+const LISTED: usize = 10;
+
+/// A record that couldn't be parsed
+pub(super) struct BadRecord {
+    pub(super) path: PathBuf,
+    /// Byte offset of the record in the log, in the decoded log if it's compressed
+    pub(super) offset: u64,
+    pub(super) error: Error,
+    pub(super) record: Vec<u8>,
+}
+
+impl BadRecord {
+    /// The error to stop the run with, for `--strict`
+    pub(super) fn into_error(self) -> Error {
+        Error::MalformedRecord {
+            path: self.path,
+            offset: self.offset,
+            error: Box::new(self.error),
+        }
+    }
+}
+
+/// Malformed records of the run
+pub(super) struct BadRecords {
+    /// `--dump-bad`
+    dump: Option<(PathBuf, BufWriter<File>)>,
+    count: usize,
+    listed: Vec<BadRecord>,
+}
+
+impl BadRecords {
+    pub(super) fn new(dump: Option<&Path>) -> Result<Self> {
+        Ok(BadRecords {
+            dump: match dump {
+                Some(path) => Some((path.to_path_buf(), BufWriter::new(File::create(path)?))),
+                None => None,
+            },
+            count: 0,
+            listed: Vec::new(),
+        })
+    }
+
+    pub(super) fn add(&mut self, mut bad: BadRecord) -> Result<()> {
+        self.count += 1;
+        if let Some((_, dump)) = &mut self.dump {
+            dump.write_all(&bad.record)?;
+            if !bad.record.ends_with(b"\n") {
+                dump.write_all(b"\n")?;
+            }
+            // Also with --follow, which is stopped by a signal
+            dump.flush()?;
+        }
+        if self.listed.len() < LISTED {
+            // The error quotes the record
+            bad.record = Vec::new();
+            self.listed.push(bad);
+        }
+        Ok(())
+    }
+
+    /// Lists the malformed records, if there were any
+    pub(super) fn report(&self) {
+        if self.count == 0 {
+            return;
+        }
+        warn!(
+            "Malformed records skipped: {}, --strict stops at the first one instead",
+            self.count
+        );
+        for bad in &self.listed {
+            warn!("  {}:{}: {:?}", bad.path.display(), bad.offset, bad.error);
+        }
+        if self.count > self.listed.len() {
+            warn!("  ... and {} more", self.count - self.listed.len());
+        }
+        match &self.dump {
+            Some((path, _)) => warn!("Malformed records were written to {}", path.display()),
+            None => warn!("--dump-bad FILE writes them to a file"),
+        }
+    }
+}
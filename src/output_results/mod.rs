@@ -1,139 +1,403 @@
+mod follow;
+mod malformed;
+mod schedule;
+mod seek;
+mod stream;
+
+use std::borrow::Cow;
+use std::io::Write;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::time::Instant;
 
 use chrono::{DateTime, Local};
-use log::debug;
+use log::{debug, warn};
 use memmap2::MmapOptions;
 
 use crate::Severity;
 use crate::aggregators::Aggregator;
-use crate::convert_args::ConvertedArgs;
-use crate::filters::{Filter, FilterContains};
-use crate::format::Format;
-use crate::util::parse_timestamp_from_string;
+use crate::bookmarks::{Bookmarks, processed_until};
+use crate::compression::{self, Compression, Decoded};
+use crate::convert_args::{ConvertedArgs, FileWithPath};
+use crate::encoding;
+use crate::filters::{DatabaseFilter, Filter, FilterContains};
+use crate::format::{Format, Layout, lossy_line};
+use crate::index::{self, IndexBlock};
+use crate::output_results::malformed::{BadRecord, BadRecords};
+use crate::output_results::schedule::Scheduler;
+use crate::state;
+use crate::timestamp::{self, is_record_start};
 use rayon::prelude::*;
 
-use crate::Result;
+use crate::{Error, Result};
 
 pub fn output_results(
-    converted_args: ConvertedArgs,
+    mut converted_args: ConvertedArgs,
     min_severity: Severity,
     aggregators: &mut Vec<Box<dyn Aggregator>>,
     filters: &Vec<Box<dyn Filter>>,
 ) -> Result<()> {
     let min_severity_num: i32 = min_severity.into();
-
-    for file_with_path in converted_args.files {
+    let mut bookmarks = match &converted_args.since_last_run {
+        Some(path) => Some(Bookmarks::load(path)?),
+        None => None,
+    };
+    let mut followed: Vec<(PathBuf, u64)> = Vec::new();
+    // Partials start out empty, cloning the aggregators would add what they aggregated
+    // from earlier files and batches once more on merge
+    let templates: Vec<Box<dyn Aggregator>> = aggregators.iter().map(|a| a.boxed_clone()).collect();
+
+    let mut bad = BadRecords::new(converted_args.dump_bad.as_deref())?;
+
+    let timing = Instant::now();
+    let printing = Printing {
+        details: converted_args.print_details,
+        ready: converted_args.save_state.is_none(),
+    };
+    let mut scheduler = Scheduler::new(&templates, printing);
+    for file_with_path in std::mem::take(&mut converted_args.files) {
         if converted_args.verbose {
             debug!("Processing file: {}", file_with_path.path.to_str().unwrap());
         }
 
-        let mut filter_container = FilterContainer {
-            filters: vec![],
-            custom_filters: filters,
-            min_severity: min_severity_num,
-            begin: converted_args.begin,
-            end: converted_args.end,
-            format: Format::from_file_extension(&file_with_path.path.to_string_lossy()),
+        let metadata = file_with_path.file.metadata()?;
+        let mmap = if metadata.is_file() && metadata.len() > 0 {
+            Some(unsafe { MmapOptions::new().map(&file_with_path.file)? })
+        } else {
+            None
         };
+        // Byte offset the regular file was processed until, with bookmark
+        let mut processed = None;
+        if let Some(mmap) = mmap.filter(|m| Compression::detect(m).is_none()) {
+            let Some(format) = log_format(&file_with_path.path, &mmap, converted_args.format)
+            else {
+                continue;
+            };
+            let filter_container =
+                FilterContainer::new(&converted_args, filters, min_severity_num, format);
+            let mut bytes: &[u8] = &mmap;
+            let mut bookmark = None;
+
+            // Only process what was appended since the last run
+            if let Some(bookmarks) = &bookmarks {
+                let path = file_with_path
+                    .path
+                    .canonicalize()
+                    .unwrap_or_else(|_| file_with_path.path.clone());
+                let end = processed_until(bytes);
+                let start = usize::try_from(bookmarks.start_offset(&path, &metadata))
+                    .unwrap_or(usize::MAX)
+                    .min(end);
+                debug!("Continuing {} from byte {start}", path.display());
+                bytes = &bytes[skip_to_record_start(&bytes[..end], start)..end];
+                bookmark = Some(path);
+            }
+            let end = bytes.as_ptr() as usize - mmap.as_ptr() as usize + bytes.len();
+            bytes = seek::time_range(bytes, converted_args.begin, converted_args.end);
+
+            // Blocks of the index that can't match the filters are skipped
+            let start = bytes.as_ptr() as usize - mmap.as_ptr() as usize;
+            let range = start..start + bytes.len();
+            let ranges =
+                match index::load(&file_with_path.path, &file_with_path.file, &mmap, format) {
+                    Some(index) => {
+                        let kept =
+                            index.kept_ranges(range, |block| filter_container.may_match(block));
+                        debug!(
+                            "Index of {} blocks leaves {} of {} bytes",
+                            index.blocks(),
+                            kept.iter().map(ExactSizeIterator::len).sum::<usize>(),
+                            bytes.len()
+                        );
+                        kept
+                    }
+                    None => vec![range],
+                };
+            scheduler.push(
+                &file_with_path.path,
+                mmap,
+                filter_container,
+                ranges,
+                aggregators,
+                &mut bad,
+            )?;
+            processed = Some((end, bookmark));
+        } else {
+            // Keeps the output in input order
+            scheduler.flush(aggregators, &mut bad)?;
+            // Pipes, FIFOs, /proc files and compressed logs are read in batches
+            stream::process_stream(
+                &file_with_path.file,
+                &file_with_path.path,
+                |path, head| {
+                    let format = log_format(path, head, converted_args.format)?;
+                    Some(FilterContainer::new(
+                        &converted_args,
+                        filters,
+                        min_severity_num,
+                        format,
+                    ))
+                },
+                &templates,
+                aggregators,
+                &mut bad,
+                printing,
+            )?;
+            if metadata.is_file() && metadata.len() == 0 {
+                processed = Some((0, None));
+            }
+        }
 
-        let timing = Instant::now();
-
-        let mmap = unsafe { MmapOptions::new().map(&file_with_path.file)? };
-        let bytes: &[u8] = &mmap;
+        if let Some((end, bookmark)) = processed {
+            if converted_args.follow {
+                followed.push((file_with_path.path.clone(), end as u64));
+            }
+            if let (Some(bookmarks), Some(path)) = (&mut bookmarks, bookmark) {
+                bookmarks.update(&path, &file_with_path.file.metadata()?, end as u64);
+            }
+        }
+    }
+    scheduler.flush(aggregators, &mut bad)?;
+    debug!("Finished output in: {:?}", timing.elapsed());
+
+    for agg in &mut *aggregators {
+        if converted_args.save_state.is_some() {
+            agg.finish();
+        } else if converted_args.follow && !agg.streams() {
+            // Reported periodically while following
+        } else if converted_args.follow {
+            // Records still waiting can be completed by what's appended
+            agg.print_ready();
+        } else {
+            agg.print();
+        }
+    }
+    debug!("Finished aggregating in: {:?}", timing.elapsed());
+    bad.report();
+
+    if converted_args.follow {
+        return follow::follow(
+            followed,
+            |format| FilterContainer::new(&converted_args, filters, min_severity_num, format),
+            aggregators,
+            &mut bad,
+            converted_args.print_details,
+            converted_args.refresh,
+            converted_args.format,
+        );
+    }
 
-        let num_threads = rayon::current_num_threads();
-        let chunk_size = bytes.len() / num_threads;
+    if let (Some(bookmarks), Some(path)) = (&mut bookmarks, &converted_args.since_last_run) {
+        bookmarks.save(path)?;
+    }
 
-        let mut ranges = Vec::new();
-        let mut start = 0;
+    if let Some(path) = &converted_args.save_state {
+        state::save(path, aggregators)?;
+        debug!("Saved state to {}", path.display());
+    }
+    Ok(())
+}
 
-        if let Some(mask) = &converted_args.mask {
-            let mask_filter = Box::new(FilterContains::new(mask.clone()));
-            filter_container.filters.push(mask_filter);
-        }
-
-        while start < bytes.len() {
-            let mut end = (start + chunk_size).min(bytes.len());
-
-            // Move end forward until a timestamp-starting line
-            if end < bytes.len() {
-                while end < bytes.len() {
-                    if bytes[end] == b'\n' {
-                        let next = end + 1;
-                        if next < bytes.len() {
-                            let line_end = bytes[next..]
-                                .iter()
-                                .position(|&b| (b == b'\n') && (b == b'\r'))
-                                .map_or(bytes.len(), |p| next + p);
-
-                            if is_record_start(&bytes[next..line_end]) {
-                                break;
-                            }
-                        }
-                    }
-                    end += 1;
+/// Newest log time of the inputs, relative times count back from it with `--anchor log-end`.
+/// Compressed logs are decoded once more for it, standard input and pipes can't be.
+pub fn newest_log_time(files: &[FileWithPath]) -> Result<Option<DateTime<Local>>> {
+    let mut newest = None;
+    for file_with_path in files {
+        let metadata = file_with_path.file.metadata()?;
+        if !metadata.is_file() {
+            return Err(Error::custom(format!(
+                "Can't count relative times back from the end of {}, it can only be read once",
+                file_with_path.path.display()
+            )));
+        }
+        if metadata.len() == 0 {
+            continue;
+        }
+        let mmap = unsafe { MmapOptions::new().map(&file_with_path.file)? };
+        if Compression::detect(&mmap).is_none() {
+            newest = newest.max(seek::newest_time(&mmap));
+            continue;
+        }
+        // Lines can span decoded blocks, not archive members
+        let mut tail = Vec::new();
+        compression::decode(&file_with_path.path, &mut &mmap[..], &mut |decoded| {
+            match decoded {
+                Decoded::Member(_) => {
+                    newest = newest.max(seek::latest_time(&tail));
+                    tail.clear();
                 }
+                Decoded::Data(block) => {
+                    tail.extend_from_slice(&block);
+                    let complete = memchr::memrchr(b'\n', &tail).map_or(0, |p| p + 1);
+                    newest = newest.max(seek::latest_time(&tail[..complete]));
+                    tail.drain(..complete);
+                }
+            }
+            Ok(())
+        })?;
+        newest = newest.max(seek::latest_time(&tail));
+    }
+    Ok(newest)
+}
+
+/// Format of a log: forced with `--format`, else detected from the content, else by file
+/// extension. None for layouts that can't be parsed yet, such logs are skipped.
+pub(crate) fn log_format(path: &Path, head: &[u8], forced: Option<Format>) -> Option<Format> {
+    let (format, reason) = match (forced, Layout::detect(head)) {
+        (Some(format), _) => (format, "--format"),
+        (None, Some(layout)) => match layout.format() {
+            Some(format) => (format, "detected from content"),
+            None => {
+                warn!(
+                    "Skipping {}: {layout:?} logs are not supported yet",
+                    path.display()
+                );
+                return None;
             }
+        },
+        (None, None) => (
+            Format::from_file_extension(&path.to_string_lossy()),
+            "by file extension",
+        ),
+    };
+    debug!("Reading {} as {format:?} ({reason})", path.display());
+    Some(format)
+}
 
-            ranges.push(start..end);
-            start = end + 1;
-        }
+/// Splits `bytes` of the log at `path`, starting at a record at `offset`, into per thread
+/// chunks at record boundaries and feeds the records to the filters and aggregators
+fn process_bytes(
+    bytes: &[u8],
+    path: &Path,
+    offset: u64,
+    templates: &[Box<dyn Aggregator>],
+    filter_container: &FilterContainer,
+    print_details: bool,
+) -> Result<Vec<Partial>> {
+    let chunk_size = bytes.len().div_ceil(rayon::current_num_threads());
+    chunks(bytes, chunk_size)
+        .into_par_iter()
+        .map(|range| {
+            process_chunk(
+                &bytes[range.clone()],
+                path,
+                offset + range.start as u64,
+                filter_container,
+                templates,
+                print_details,
+            )
+        })
+        .collect()
+}
 
-        debug!("File did read in: {:?}", timing.elapsed());
+/// Ranges of `bytes`, starting at a record, of about `size` bytes ending at record boundaries
+fn chunks(bytes: &[u8], size: usize) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    while start < bytes.len() {
+        let end = record_start_at(bytes, (start + size.max(1)).min(bytes.len()));
+        ranges.push(start..end);
+        start = end;
+    }
+    ranges
+}
 
-        let partials: Result<Vec<Vec<Box<dyn Aggregator>>>> = ranges
-            .par_iter()
-            .map(|range| -> Result<Vec<Box<dyn Aggregator>>> {
-                let mut local_aggregators: Vec<Box<dyn Aggregator>> =
-                    aggregators.iter().map(|a| a.boxed_clone()).collect();
+/// What the records of a chunk aggregated to, the records to print and the malformed ones
+/// What's printed while the logs are processed
+#[derive(Clone, Copy)]
+struct Printing {
+    /// Records passing the filters, of commands listing them
+    details: bool,
+    /// Complete records of streaming reports, they're kept in the state with `--save-state`
+    ready: bool,
+}
 
-                let slice = &bytes[range.clone()];
+struct Partial {
+    aggregators: Vec<Box<dyn Aggregator>>,
+    printed: Vec<u8>,
+    bad: Vec<BadRecord>,
+}
 
-                let mut record_start = 0;
-                let mut offset = 0;
+/// Feeds the records of `bytes`, which starts at a record at `offset` of the log at `path`,
+/// to the filters and to aggregators cloned from the templates
+fn process_chunk(
+    bytes: &[u8],
+    path: &Path,
+    offset: u64,
+    filter_container: &FilterContainer,
+    templates: &[Box<dyn Aggregator>],
+    print_details: bool,
+) -> Result<Partial> {
+    let mut local_aggregators: Vec<Box<dyn Aggregator>> =
+        templates.iter().map(|a| a.boxed_clone()).collect();
+    let mut printed = Vec::new();
+    let mut bad = Vec::new();
+    let mut process = |record: &[u8], record_start: usize| {
+        let Err(error) = filter_record(
+            record,
+            filter_container,
+            &mut local_aggregators,
+            print_details.then_some(&mut printed),
+        ) else {
+            return Ok(());
+        };
+        let record = BadRecord {
+            path: path.to_path_buf(),
+            offset: offset + record_start as u64,
+            error,
+            record: record.to_vec(),
+        };
+        if filter_container.strict {
+            return Err(record.into_error());
+        }
+        bad.push(record);
+        Ok(())
+    };
 
-                for line in slice.split(|&b| b == b'\n') {
-                    let line_len = line.len() + 1; // include '\n'
+    let mut record_start = 0;
+    let mut line_start = 0;
 
-                    if is_record_start(line) && offset != 0 {
-                        let record = &slice[record_start..offset];
-                        // debug!("Processing record: {:?} start {} offset {} line_len {}", std::str::from_utf8(record), record_start, offset, line_len);
-                        filter_record(
-                            record,
-                            &filter_container,
-                            &mut local_aggregators,
-                            converted_args.print_details,
-                        )?;
-                        record_start = offset;
-                    }
+    for line in bytes.split(|&b| b == b'\n') {
+        let line_len = line.len() + 1; // include '\n'
 
-                    offset += line_len;
-                }
+        if is_record_start(line) && line_start != 0 {
+            process(&bytes[record_start..line_start], record_start)?;
+            record_start = line_start;
+        }
 
-                // last record in chunk
-                if record_start < slice.len() {
-                    filter_record(
-                        &slice[record_start..slice.len()],
-                        &filter_container,
-                        &mut local_aggregators,
-                        converted_args.print_details,
-                    )?;
-                }
-                Ok(local_aggregators)
-            })
-            .collect();
+        line_start += line_len;
+    }
+
+    // last record in chunk
+    if record_start < bytes.len() {
+        process(&bytes[record_start..], record_start)?;
+    }
+    Ok(Partial {
+        aggregators: local_aggregators,
+        printed,
+        bad,
+    })
+}
 
-        debug!("Finished output in: {:?}", timing.elapsed());
-        let partials = partials?;
-        for partial in partials {
-            for (i, aggregator) in partial.into_iter().enumerate() {
-                aggregators[i].merge_box(aggregator.as_ref());
+/// Merges partials into the aggregators and prints their records, in chunk order
+fn merge_partials(
+    partials: Vec<Partial>,
+    aggregators: &mut [Box<dyn Aggregator>],
+    bad: &mut BadRecords,
+    print_ready: bool,
+) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    for partial in partials {
+        for (i, aggregator) in partial.aggregators.into_iter().enumerate() {
+            aggregators[i].merge_box(aggregator.as_ref());
+            if print_ready {
+                aggregators[i].print_ready();
             }
         }
-        for agg in &mut *aggregators {
-            agg.print();
+        stdout.write_all(&partial.printed)?;
+        for record in partial.bad {
+            bad.add(record)?;
         }
-        debug!("Finished aggregating in: {:?}", timing.elapsed());
     }
     Ok(())
 }
@@ -145,6 +409,46 @@ struct FilterContainer<'a> {
     begin: Option<DateTime<Local>>,
     end: Option<DateTime<Local>>,
     format: Format,
+    /// Malformed records stop the run
+    strict: bool,
+}
+
+impl<'a> FilterContainer<'a> {
+    fn new(
+        converted_args: &ConvertedArgs,
+        custom_filters: &'a Vec<Box<dyn Filter>>,
+        min_severity: i32,
+        format: Format,
+    ) -> Self {
+        let mut filters: Vec<Box<dyn Filter>> = vec![];
+        if let Some(mask) = &converted_args.mask {
+            filters.push(Box::new(FilterContains::new(mask.clone())));
+        }
+        if !converted_args.databases.is_empty() {
+            filters.push(Box::new(DatabaseFilter::new(
+                converted_args.databases.clone(),
+            )));
+        }
+        FilterContainer {
+            custom_filters,
+            filters,
+            min_severity,
+            begin: converted_args.begin,
+            end: converted_args.end,
+            format,
+            strict: converted_args.strict,
+        }
+    }
+}
+
+impl FilterContainer<'_> {
+    /// Whether a record of the indexed block can pass the filters
+    fn may_match(&self, block: &IndexBlock) -> bool {
+        block.may_overlap(self.begin, self.end)
+            && block.may_have_severity(self.min_severity)
+            && self.filters.iter().all(|f| f.may_match(block))
+            && self.custom_filters.iter().all(|f| f.may_match(block))
+    }
 }
 
 #[inline]
@@ -152,8 +456,9 @@ fn filter_record(
     record: &[u8],
     filters: &FilterContainer,
     local_aggregators: &mut Vec<Box<dyn Aggregator>>,
-    print: bool,
+    printed: Option<&mut Vec<u8>>,
 ) -> Result<()> {
+    let record = &*crlf_to_lf(record);
     for filter in &filters.filters {
         if !filter.matches(record, &filters.format) {
             return Ok(());
@@ -161,22 +466,13 @@ fn filter_record(
     }
 
     // Next code is not written as filters to avoid multiple string parsing and degradation of performance
-    let text = unsafe { std::str::from_utf8_unchecked(record) };
-    let severity = filters.format.severity_from_string(text);
+    let severity = filters.format.severity_from_bytes(record);
     let level: i32 = severity.into();
     if level < filters.min_severity {
         return Ok(());
     }
 
-    let mut parts = text.split_whitespace();
-    let ts_str = format!(
-        "{} {} {}",
-        parts.next().ok_or("Missing timestamp first part")?,
-        parts.next().ok_or("Missing timestamp second part")?,
-        parts.next().ok_or("Missing timestamp third part")?
-    );
-
-    let log_time_local = parse_timestamp_from_string(ts_str.as_str())?;
+    let log_time_local = record_time(record)?;
     if filters.begin.is_some_and(|b| log_time_local < b) {
         return Ok(());
     }
@@ -198,12 +494,37 @@ fn filter_record(
         log_time_local,
     )?;
 
-    if print {
-        println!("{text}");
+    if let Some(printed) = printed {
+        timestamp::write_record(&encoding::printable(record), printed);
+        printed.push(b'\n');
     }
     Ok(())
 }
 
+/// The record with LF line ends, it has CRLF ones if the log was written on Windows or went
+/// through it. A `\r` of the last line ends up in messages and printed records otherwise.
+/// A `\r` not followed by `\n` is text and stays.
+fn crlf_to_lf(record: &[u8]) -> Cow<'_, [u8]> {
+    if memchr::memchr(b'\r', record).is_none() {
+        return Cow::Borrowed(record);
+    }
+    let mut lf = Vec::with_capacity(record.len());
+    let mut rest = record;
+    while let Some(at) = memchr::memmem::find(rest, b"\r\n") {
+        lf.extend_from_slice(&rest[..at]);
+        lf.push(b'\n');
+        rest = &rest[at + 2..];
+    }
+    lf.extend_from_slice(rest);
+    Cow::Owned(lf)
+}
+
+/// Log time of a record
+pub(crate) fn record_time(record: &[u8]) -> Result<DateTime<Local>> {
+    timestamp::log_time(record)
+        .ok_or_else(|| format!("Unable to parse timestamp: '{}'", lossy_line(record)).into())
+}
+
 #[inline]
 fn aggragate_record(
     local_aggregators: &mut Vec<Box<dyn Aggregator>>,
@@ -218,25 +539,221 @@ fn aggragate_record(
     Ok(())
 }
 
-#[inline]
-fn is_record_start(record: &[u8]) -> bool {
-    record.len() >= 23
-        && record[4] == b'-'
-        && record[7] == b'-'
-        && record[10] == b' '
-        && record[13] == b':'
-        && record[16] == b':'
-        && (record[19] == b'.' || record[19] == b' ')
+/// Records of `bytes`, each with its trailing newline
+pub(crate) fn records(bytes: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let mut start = 0;
+    std::iter::from_fn(move || {
+        if start >= bytes.len() {
+            return None;
+        }
+        let mut line_start = start;
+        loop {
+            let next = memchr::memchr(b'\n', &bytes[line_start..])
+                .map_or(bytes.len(), |p| line_start + p + 1);
+            if next >= bytes.len() || is_record_start(&bytes[next..]) {
+                let record = &bytes[start..next];
+                start = next;
+                return Some(record);
+            }
+            line_start = next;
+        }
+    })
+}
+
+/// First record starting at or after `offset`, skipping the remaining lines of a record
+/// processed in an earlier run
+fn skip_to_record_start(bytes: &[u8], offset: usize) -> usize {
+    let mut start = offset;
+    while start < bytes.len() {
+        let line_end = memchr::memchr(b'\n', &bytes[start..]).map_or(bytes.len(), |p| start + p);
+        if is_record_start(&bytes[start..line_end]) {
+            break;
+        }
+        start = line_end + 1;
+    }
+    start.min(bytes.len())
+}
+
+/// Start of the first record at or after `offset`, which may be in the middle of a line
+pub(crate) fn record_start_at(bytes: &[u8], offset: usize) -> usize {
+    let line_start = if offset == 0 || bytes[offset - 1] == b'\n' {
+        offset
+    } else {
+        memchr::memchr(b'\n', &bytes[offset..]).map_or(bytes.len(), |p| offset + p + 1)
+    };
+    skip_to_record_start(bytes, line_start)
 }
 
 #[cfg(test)]
 mod test {
+    use std::path::Path;
+
+    use chrono::{NaiveDateTime, TimeZone};
+
+    use crate::compression::{Decoded, decode};
+    use crate::timestamp::Zone;
 
     use super::*;
 
+    /// Logs under `tests/files` and `testdata`, decompressed, with their format
+    fn corpus() -> Vec<(PathBuf, Format, Vec<u8>)> {
+        fn walk(dir: &Path, logs: &mut Vec<(PathBuf, Format, Vec<u8>)>) {
+            let mut entries: Vec<_> = std::fs::read_dir(dir)
+                .unwrap()
+                .map(|e| e.unwrap().path())
+                .collect();
+            entries.sort();
+            for path in entries {
+                if path.is_dir() {
+                    walk(&path, logs);
+                    continue;
+                }
+                let mut bytes = Vec::new();
+                decode(&path, &mut std::fs::File::open(&path).unwrap(), &mut |d| {
+                    if let Decoded::Data(data) = d {
+                        bytes.extend(data);
+                    }
+                    Ok(())
+                })
+                .unwrap();
+                if let Some(format) = Layout::detect(&bytes).and_then(Layout::format) {
+                    logs.push((path, format, bytes));
+                }
+            }
+        }
+        let mut logs = Vec::new();
+        walk(Path::new("./tests/files"), &mut logs);
+        walk(Path::new("./testdata"), &mut logs);
+        logs
+    }
+
+    /// Severity and log time the way they were parsed before the header parser, the time
+    /// as the wall clock time with the zone name
+    fn string_header(record: &[u8], format: Format) -> (Severity, Option<(NaiveDateTime, String)>) {
+        let text = String::from_utf8_lossy(record);
+        let severity = match format {
+            Format::Csv => Severity::from_csv_string(&text),
+            Format::Plain => Severity::from_log_string(&text),
+        };
+        let mut parts = text.split_whitespace();
+        let first = parts.next();
+        // `%n`, epoch seconds with milliseconds
+        if let Some(epoch) = first.and_then(|epoch| DateTime::parse_from_str(epoch, "%s%.3f").ok())
+        {
+            return (severity, Some((epoch.naive_utc(), "UTC".to_string())));
+        }
+        let time = first
+            .zip(parts.next())
+            .zip(parts.next())
+            .and_then(|((date, time), zone)| {
+                let time = NaiveDateTime::parse_from_str(
+                    &format!("{date} {time}"),
+                    "%Y-%m-%d %H:%M:%S%.f",
+                )
+                .ok()?;
+                Some((time, zone_prefix(zone)))
+            });
+        (severity, time)
+    }
+
+    /// `EEST` of `EEST,"user"`, `+08` of `+08:`
+    fn zone_prefix(zone: &str) -> String {
+        let len = match zone.strip_prefix(['+', '-']) {
+            Some(offset) => 1 + offset.chars().take_while(char::is_ascii_digit).count(),
+            None => zone.chars().take_while(char::is_ascii_alphabetic).count(),
+        };
+        zone[..len].to_string()
+    }
+
+    #[test]
+    fn test_header_equivalence() {
+        for (path, format, bytes) in corpus() {
+            for record in records(&bytes) {
+                let (severity, time) = string_header(record, format);
+                // Same wall clock time in the logged zone
+                let time = time.map(|(time, zone)| match zone.parse() {
+                    Ok(Zone::Fixed(offset)) => offset.from_local_datetime(&time).unwrap().into(),
+                    _ => Local.from_local_datetime(&time).unwrap(),
+                });
+                assert_eq!(record_time(record).ok(), time, "{}", path.display());
+                if format.severity_from_bytes(record) != severity {
+                    // Searching the whole record found a severity quoted in the message,
+                    // like "FATAL:  could not start WAL streaming: ERROR:  ..."
+                    let message = format.message_from_bytes(record).unwrap_or_default();
+                    assert!(
+                        format == Format::Plain
+                            && Severity::from_log_string(&String::from_utf8_lossy(message))
+                                == severity,
+                        "{}: {}",
+                        path.display(),
+                        lossy_line(record)
+                    );
+                }
+            }
+        }
+    }
+
+    /// Header parsing throughput, before and after:
+    /// `cargo test --release bench_header -- --ignored --nocapture`
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_header() {
+        const ROUNDS: usize = 20;
+        let corpus = corpus();
+        let records: Vec<_> = corpus
+            .iter()
+            .flat_map(|(_, format, bytes)| records(bytes).map(|record| (*format, record)))
+            .collect();
+
+        let timing = Instant::now();
+        for _ in 0..ROUNDS {
+            for (format, record) in &records {
+                std::hint::black_box(string_header(record, *format));
+            }
+        }
+        let strings = timing.elapsed();
+
+        let timing = Instant::now();
+        for _ in 0..ROUNDS {
+            for (format, record) in &records {
+                std::hint::black_box((
+                    format.severity_from_bytes(record),
+                    record_time(record).ok(),
+                ));
+            }
+        }
+        let bytes = timing.elapsed();
+
+        let total = (records.len() * ROUNDS) as f64;
+        println!(
+            "{} records x {ROUNDS}: strings {:.0} ns/record, header parser {:.0} ns/record, {:.1}x",
+            records.len(),
+            strings.as_nanos() as f64 / total,
+            bytes.as_nanos() as f64 / total,
+            strings.as_secs_f64() / bytes.as_secs_f64()
+        );
+    }
+
     #[test]
     fn test_record_start() {
         let line = b"2025-05-21 11:01:20 UTC-682db26c.535-LOG:  disconnection: session time: 0:00:20.034 user=azuresu database=azure_maintenance host=127.0.0.1 port=55304";
         assert!(is_record_start(line));
     }
+
+    #[test]
+    fn test_crlf_to_lf() {
+        assert!(matches!(crlf_to_lf(b"a\nb\n"), Cow::Borrowed(_)));
+        assert_eq!(&*crlf_to_lf(b"a\r\n\tb\r\n"), b"a\n\tb\n");
+        // A lone \r stays, it's part of the text, also at the end of an unterminated line
+        assert_eq!(&*crlf_to_lf(b"a\rb\r"), b"a\rb\r");
+    }
+
+    #[test]
+    fn test_chunks() {
+        let bytes = b"2025-05-22 15:15:09.392 EEST [1] ERROR:  x\n\tcontinued\n2025-05-22 15:15:09.393 EEST [1] LOG:  y\n";
+        // Chunks end at record boundaries only
+        assert_eq!(chunks(bytes, 10), vec![0..54, 54..bytes.len()]);
+        assert_eq!(chunks(bytes, 1 << 20), vec![0..bytes.len()]);
+        assert!(chunks(b"", 10).is_empty());
+    }
 }
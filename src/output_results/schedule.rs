@@ -0,0 +1,126 @@
+//! Scheduling of mapped logs: whole files and chunks of larger files are one pool of jobs
+//! for the thread pool, so a directory of thousands of small rotated logs keeps all threads
+//! busy just like one large log does. Jobs are run in windows of a few per thread, which
+//! bounds the partial aggregators and printed records held at once, and their results are
+//! merged in input order, so the output doesn't depend on the number of threads.
+
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
+};
+
+use log::debug;
+use memmap2::Mmap;
+use rayon::prelude::*;
+
+use crate::{
+    Result,
+    aggregators::Aggregator,
+    output_results::{
+        FilterContainer, Printing, chunks, malformed::BadRecords, merge_partials, process_chunk,
+    },
+};
+
+/// Most bytes of a job, larger ranges are split at record boundaries
+const CHUNK_SIZE: usize = 4 << 20;
+/// Jobs queued per thread before they're run
+const JOBS_PER_THREAD: usize = 8;
+
+/// A mapped log, unmapped once its last job ran
+struct Log<'a> {
+    path: PathBuf,
+    mmap: Mmap,
+    filters: FilterContainer<'a>,
+}
+
+pub(super) struct Scheduler<'a> {
+    templates: &'a [Box<dyn Aggregator>],
+    printing: Printing,
+    /// Queued jobs in input order
+    jobs: Vec<(Arc<Log<'a>>, Range<usize>)>,
+}
+
+impl<'a> Scheduler<'a> {
+    pub(super) fn new(templates: &'a [Box<dyn Aggregator>], printing: Printing) -> Self {
+        Scheduler {
+            templates,
+            printing,
+            jobs: Vec::new(),
+        }
+    }
+
+    /// Queues the records in `ranges` of a mapped log, which start at a record. Queued jobs
+    /// are run whenever there are enough of them to keep all threads busy.
+    pub(super) fn push(
+        &mut self,
+        path: &Path,
+        mmap: Mmap,
+        filters: FilterContainer<'a>,
+        ranges: Vec<Range<usize>>,
+        aggregators: &mut [Box<dyn Aggregator>],
+        bad: &mut BadRecords,
+    ) -> Result<()> {
+        let log = Arc::new(Log {
+            path: path.to_path_buf(),
+            mmap,
+            filters,
+        });
+        for range in ranges {
+            for chunk in chunks(&log.mmap[range.clone()], CHUNK_SIZE) {
+                let chunk = range.start + chunk.start..range.start + chunk.end;
+                self.jobs.push((Arc::clone(&log), chunk));
+            }
+        }
+
+        let window = rayon::current_num_threads() * JOBS_PER_THREAD;
+        while self.jobs.len() >= window {
+            self.run(window, aggregators, bad)?;
+        }
+        Ok(())
+    }
+
+    /// Runs all queued jobs, before an input that isn't scheduled and at the end
+    pub(super) fn flush(
+        &mut self,
+        aggregators: &mut [Box<dyn Aggregator>],
+        bad: &mut BadRecords,
+    ) -> Result<()> {
+        self.run(self.jobs.len(), aggregators, bad)
+    }
+
+    fn run(
+        &mut self,
+        count: usize,
+        aggregators: &mut [Box<dyn Aggregator>],
+        bad: &mut BadRecords,
+    ) -> Result<()> {
+        if count == 0 {
+            return Ok(());
+        }
+        let timing = Instant::now();
+        let jobs: Vec<_> = self.jobs.drain(..count).collect();
+        let (templates, printing) = (self.templates, self.printing);
+        let partials = jobs
+            .par_iter()
+            .map(|(log, range)| {
+                process_chunk(
+                    &log.mmap[range.clone()],
+                    &log.path,
+                    range.start as u64,
+                    &log.filters,
+                    templates,
+                    printing.details,
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+        merge_partials(partials, aggregators, bad, printing.ready)?;
+        debug!(
+            "Ran {count} jobs of {} bytes in {:?}",
+            jobs.iter().map(|(_, range)| range.len()).sum::<usize>(),
+            timing.elapsed()
+        );
+        Ok(())
+    }
+}
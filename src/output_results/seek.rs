@@ -0,0 +1,216 @@
+//! Seeking to `--begin` / `--end` in a mapped log file by binary search on the record
+//! timestamps, instead of parsing every record before `--begin` and after `--end`. Logs are
+//! written in time order, give or take concurrent backends, so a little slack is allowed.
+//! Files that aren't in time order, e.g. concatenated logs, are processed in full. The
+//! order is sampled over the file and checked around where the search cuts it.
+
+use chrono::{DateTime, Local, TimeDelta};
+use log::debug;
+
+use crate::{
+    output_results::{record_start_at, record_time},
+    timestamp::is_record_start,
+};
+
+/// Records sampled to check that the file is in time order
+const SAMPLES: usize = 64;
+/// How far back timestamps of consecutive records can go, a backend logs with the time it
+/// started writing the record
+const SLACK: TimeDelta = TimeDelta::seconds(60);
+
+/// The part of `bytes` with records between `begin` and `end`, the exact time range is
+/// still checked per record
+pub(super) fn time_range(
+    bytes: &[u8],
+    begin: Option<DateTime<Local>>,
+    end: Option<DateTime<Local>>,
+) -> &[u8] {
+    if begin.is_none() && end.is_none() {
+        return bytes;
+    }
+    if !is_time_ordered(bytes) {
+        debug!("Log is not in time order, scanning all of it");
+        return bytes;
+    }
+
+    let start = begin.map_or(0, |begin| first_record_from(bytes, begin - SLACK));
+    let stop = end.map_or(bytes.len(), |end| first_record_from(bytes, end + SLACK));
+    // Samples miss disorder between them, like several backends writing to one log
+    if begin.is_some_and(|begin| !older_before(bytes, start, begin))
+        || end.is_some_and(|end| !newer_from(bytes, stop, end))
+    {
+        debug!("Log is not in time order around --begin / --end, scanning all of it");
+        return bytes;
+    }
+    let stop = stop.max(start);
+    debug!("Seeked to bytes {start}..{stop} of {}", bytes.len());
+    &bytes[start..stop]
+}
+
+/// Offset of the first record logged at or after `time`
+fn first_record_from(bytes: &[u8], time: DateTime<Local>) -> usize {
+    // Records starting before `low` are older, the first one from `high` on is not
+    let (mut low, mut high) = (0, bytes.len());
+    while low < high {
+        let mid = low + (high - low) / 2;
+        let start = record_start_at(bytes, mid);
+        if start >= high {
+            high = mid;
+            continue;
+        }
+        match time_at(bytes, start) {
+            Some(t) if t < time => low = start + 1,
+            // Records without a readable timestamp are kept
+            _ => high = start,
+        }
+    }
+    record_start_at(bytes, low)
+}
+
+/// Whether the records before `at` are older than `time`, checked back to one older than
+/// the slack before the searched time
+fn older_before(bytes: &[u8], mut at: usize, time: DateTime<Local>) -> bool {
+    while let Some(start) = previous_record(bytes, at) {
+        match time_at(bytes, start) {
+            Some(t) if t >= time => return false,
+            Some(t) if t < time - SLACK * 2 => return true,
+            _ => at = start,
+        }
+    }
+    true
+}
+
+/// Whether the records from `at` on are newer than `time`, checked up to one newer than
+/// the slack after the searched time
+fn newer_from(bytes: &[u8], mut at: usize, time: DateTime<Local>) -> bool {
+    while at < bytes.len() {
+        match time_at(bytes, at) {
+            Some(t) if t <= time => return false,
+            Some(t) if t > time + SLACK * 2 => return true,
+            _ => at = record_start_at(bytes, at + 1),
+        }
+    }
+    true
+}
+
+/// Start of the record before the one starting at `at`
+fn previous_record(bytes: &[u8], at: usize) -> Option<usize> {
+    let mut line_start = at.checked_sub(1)?;
+    while let Some(newline) = memchr::memrchr(b'\n', &bytes[..line_start]) {
+        if is_record_start(&bytes[newline + 1..]) {
+            return Some(newline + 1);
+        }
+        line_start = newline;
+    }
+    Some(0)
+}
+
+fn time_at(bytes: &[u8], start: usize) -> Option<DateTime<Local>> {
+    let line_end = memchr::memchr(b'\n', &bytes[start..]).map_or(bytes.len(), |p| start + p);
+    record_time(&bytes[start..line_end]).ok()
+}
+
+/// Newest log time, of the last record if the file is in time order
+pub(super) fn newest_time(bytes: &[u8]) -> Option<DateTime<Local>> {
+    if !is_time_ordered(bytes) {
+        return latest_time(bytes);
+    }
+    bytes
+        .rsplit(|&b| b == b'\n')
+        .filter(|line| is_record_start(line))
+        .find_map(|line| record_time(line).ok())
+}
+
+/// Newest log time of all records
+pub(super) fn latest_time(bytes: &[u8]) -> Option<DateTime<Local>> {
+    bytes
+        .split(|&b| b == b'\n')
+        .filter(|line| is_record_start(line))
+        .filter_map(|line| record_time(line).ok())
+        .max()
+}
+
+/// Samples records spread over the file, no timestamp may be older than an earlier one
+/// by more than the slack
+fn is_time_ordered(bytes: &[u8]) -> bool {
+    let step = (bytes.len() / SAMPLES).max(1);
+    let mut latest: Option<DateTime<Local>> = None;
+    for offset in (0..bytes.len()).step_by(step) {
+        let start = record_start_at(bytes, offset);
+        let Some(time) = (start < bytes.len())
+            .then(|| time_at(bytes, start))
+            .flatten()
+        else {
+            continue;
+        };
+        if latest.is_some_and(|latest| time < latest - SLACK) {
+            return false;
+        }
+        latest = latest.max(Some(time));
+    }
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use crate::util::parse_timestamp_from_string;
+
+    use super::*;
+
+    fn time(ts: &str) -> Option<DateTime<Local>> {
+        Some(parse_timestamp_from_string(ts).unwrap())
+    }
+
+    #[test]
+    fn test_time_range() {
+        let mut log = String::new();
+        for minute in 0..60 {
+            log.push_str(&format!(
+                "2025-05-22 15:{minute:02}:09.392 EEST [1] LOG:  line\n\tcontinued\n"
+            ));
+        }
+        let bytes = log.as_bytes();
+
+        let range = time_range(
+            bytes,
+            time("2025-05-22 15:30:00 EEST"),
+            time("2025-05-22 15:40:00 EEST"),
+        );
+        // One minute of slack on both sides
+        assert!(range.starts_with(b"2025-05-22 15:29:09.392"));
+        assert!(range.ends_with(b"\n2025-05-22 15:40:09.392 EEST [1] LOG:  line\n\tcontinued\n"));
+
+        assert_eq!(time_range(bytes, None, None), bytes);
+        assert!(time_range(bytes, time("2025-05-22 17:00:00 EEST"), None).is_empty());
+
+        // Concatenated logs are scanned in full
+        let twice = format!("{log}{log}");
+        assert_eq!(
+            time_range(twice.as_bytes(), time("2025-05-22 15:30:00 EEST"), None),
+            twice.as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_unordered_around_cut() {
+        // A record every 10 seconds, one logged late and one early, between the samples
+        let mut log = String::new();
+        for i in 0..600 {
+            let seconds = match i {
+                296 => 3300,
+                449 => 100,
+                _ => i * 10,
+            };
+            log.push_str(&format!(
+                "2025-05-22 {:02}:{:02}:{:02}.000 EEST [1] LOG:  line {i}\n",
+                15 + seconds / 3600,
+                seconds / 60 % 60,
+                seconds % 60
+            ));
+        }
+        let range = time_range(log.as_bytes(), time("2025-05-22 15:51:00 EEST"), None);
+        assert!(String::from_utf8_lossy(range).contains("line 296\n"));
+        let range = time_range(log.as_bytes(), None, time("2025-05-22 16:13:00 EEST"));
+        assert!(String::from_utf8_lossy(range).contains("line 449\n"));
+    }
+}
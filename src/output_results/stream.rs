@@ -0,0 +1,166 @@
+//! Streaming ingestion for inputs that can't be memory mapped: stdin (`-`), pipes, FIFOs
+//! and /proc files, and compressed logs. A reader thread reads and decodes blocks ahead,
+//! records are split off incrementally and handed to the same parallel pipeline in
+//! batches - large ones when the input is faster than processing (`zcat x |`), small ones
+//! for a live source (`kubectl logs -f |`).
+
+use std::{
+    fs::File,
+    io::{self, ErrorKind},
+    path::Path,
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    Result,
+    aggregators::Aggregator,
+    compression::{Decoded, decode},
+    output_results::{
+        FilterContainer, Printing, malformed::BadRecords, merge_partials, process_bytes,
+    },
+    timestamp::is_record_start,
+};
+
+/// Blocks read ahead, bounds memory use when processing can't keep up
+const QUEUED_READS: usize = 64;
+/// Most bytes processed at once
+const BATCH_SIZE: usize = 16 << 20;
+/// Without new input for this long the last record is considered complete
+const IDLE_FLUSH: Duration = Duration::from_millis(500);
+
+pub(super) fn process_stream<'a>(
+    file: &File,
+    path: &Path,
+    filter_container: impl Fn(&Path, &[u8]) -> Option<FilterContainer<'a>>,
+    templates: &[Box<dyn Aggregator>],
+    aggregators: &mut [Box<dyn Aggregator>],
+    bad: &mut BadRecords,
+    printing: Printing,
+) -> Result<()> {
+    let (tx, rx) = mpsc::sync_channel::<std::io::Result<Decoded>>(QUEUED_READS);
+    let mut reader = file.try_clone()?;
+    let mut member = path.to_path_buf();
+    let path = path.to_path_buf();
+    // Not joined: on an error the reader may still be blocked on a live source
+    thread::spawn(move || {
+        let result = decode(&path, &mut reader, &mut |decoded| {
+            tx.send(Ok(decoded))
+                .map_err(|_| io::Error::from(ErrorKind::BrokenPipe))
+        });
+        if let Err(e) = result {
+            let _ = tx.send(Err(e));
+        }
+    });
+
+    // Chosen by the first bytes of each log, None for logs that are skipped
+    let mut filters: Option<Option<FilterContainer>> = None;
+    let mut buffer: Vec<u8> = Vec::new();
+    // Offset of the buffer in the decoded log
+    let mut offset = 0;
+    // Processes the buffer up to `end`, all of it by default
+    let mut process = |buffer: &mut Vec<u8>,
+                       offset: &mut u64,
+                       filters: &mut Option<Option<FilterContainer<'a>>>,
+                       member: &Path,
+                       end: Option<usize>|
+     -> Result<()> {
+        let end = end.unwrap_or(buffer.len());
+        if end == 0 {
+            return Ok(());
+        }
+        let result = match filters.get_or_insert_with(|| filter_container(member, buffer)) {
+            Some(filters) => process_bytes(
+                &buffer[..end],
+                member,
+                *offset,
+                templates,
+                filters,
+                printing.details,
+            )
+            .and_then(|partials| merge_partials(partials, aggregators, bad, printing.ready)),
+            None => Ok(()),
+        };
+        buffer.drain(..end);
+        *offset += end as u64;
+        result
+    };
+    // Read ahead while batching, belongs to the next log
+    let mut next = None;
+    loop {
+        let decoded = match next.take() {
+            Some(decoded) => decoded,
+            None => match rx.recv_timeout(IDLE_FLUSH) {
+                Ok(decoded) => decoded,
+                Err(RecvTimeoutError::Timeout) => {
+                    if buffer.ends_with(b"\n") {
+                        process(&mut buffer, &mut offset, &mut filters, &member, None)?;
+                    }
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            },
+        };
+        match decoded? {
+            Decoded::Data(block) => buffer.extend_from_slice(&block),
+            Decoded::Member(path) => {
+                // The last record of the previous log is complete
+                process(&mut buffer, &mut offset, &mut filters, &member, None)?;
+                member = path;
+                filters = None;
+                offset = 0;
+                continue;
+            }
+        }
+        // Take whatever was read ahead meanwhile
+        while buffer.len() < BATCH_SIZE {
+            match rx.try_recv() {
+                Ok(Ok(Decoded::Data(block))) => buffer.extend_from_slice(&block),
+                Ok(decoded) => {
+                    next = Some(decoded);
+                    break;
+                }
+                Err(_) => break,
+            }
+        }
+
+        let complete = last_record_start(&buffer);
+        process(
+            &mut buffer,
+            &mut offset,
+            &mut filters,
+            &member,
+            Some(complete),
+        )?;
+    }
+
+    process(&mut buffer, &mut offset, &mut filters, &member, None)
+}
+
+/// Start of the last record, which may still continue in the next block
+fn last_record_start(bytes: &[u8]) -> usize {
+    let mut end = bytes.len();
+    while let Some(newline) = memchr::memrchr(b'\n', &bytes[..end]) {
+        let start = newline + 1;
+        if is_record_start(&bytes[start..]) {
+            return start;
+        }
+        end = newline;
+    }
+    0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_last_record_start() {
+        let bytes = b"2025-05-22 15:15:09.392 EEST [1] ERROR:  x\n\tcontinued\n2025-05-22 15:15:09.393 EEST [1] STATEMENT:  select\n\t1\n";
+        assert_eq!(last_record_start(bytes), 54);
+        // Too short to tell
+        assert_eq!(last_record_start(&bytes[..60]), 0);
+        assert_eq!(last_record_start(b"no records\nat all\n"), 0);
+    }
+}
@@ -0,0 +1,99 @@
+use memchr::memmem;
+
+use crate::{
+    encoding,
+    format::{Format, unescape_csv_quotes},
+};
+
+/// Parsed pgaudit payload:
+/// `AUDIT: SESSION,1,1,READ,SELECT,TABLE,public.t,"select * from t",<not logged>`
+#[derive(Debug, Default, PartialEq)]
+pub struct AuditEntry {
+    pub audit_type: String,
+    pub statement_id: u64,
+    pub substatement_id: u64,
+    pub class: String,
+    pub command: String,
+    pub object_type: String,
+    pub object_name: String,
+    pub statement: String,
+    pub parameter: String,
+}
+
+pub fn extract_audit(record: &[u8], fmt: &Format) -> Option<AuditEntry> {
+    let payload = match fmt {
+        Format::Csv => {
+            let message = fmt.message_from_bytes(record)?;
+            // CSV log escapes quotes inside the message field by doubling them
+            unescape_csv_quotes(message.strip_prefix(b"AUDIT: ")?)
+        }
+        Format::Plain => {
+            // Statement can span several lines, so take the whole rest of the record
+            let start = memmem::find(record, b"AUDIT: ")? + b"AUDIT: ".len();
+            record[start..].trim_ascii_end().to_vec()
+        }
+    };
+
+    parse_audit_payload(&payload)
+}
+
+fn parse_audit_payload(payload: &[u8]) -> Option<AuditEntry> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(payload);
+    let row = reader.byte_records().next()?.ok()?;
+
+    let field = |i: usize| {
+        row.get(i)
+            .map(|f| encoding::decode(f).to_string())
+            .unwrap_or_default()
+    };
+    let number = |i: usize| {
+        row.get(i)
+            .and_then(|f| std::str::from_utf8(f).ok())
+            .and_then(|f| f.parse().ok())
+    };
+
+    Some(AuditEntry {
+        audit_type: field(0),
+        statement_id: number(1)?,
+        substatement_id: number(2)?,
+        class: field(3),
+        command: field(4),
+        object_type: field(5),
+        object_name: field(6),
+        statement: field(7),
+        parameter: field(8),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_extract_audit_plain() {
+        let record = b"2025-03-10 10:00:00.123 UTC [4321] user=alice,db=shop LOG:  AUDIT: SESSION,1,1,READ,SELECT,TABLE,public.t,\"select *\n    from t where a = ',' \",<not logged>\n";
+        let entry = extract_audit(record, &Format::Plain).unwrap();
+        assert_eq!(entry.audit_type, "SESSION");
+        assert_eq!(entry.statement_id, 1);
+        assert_eq!(entry.class, "READ");
+        assert_eq!(entry.command, "SELECT");
+        assert_eq!(entry.object_type, "TABLE");
+        assert_eq!(entry.object_name, "public.t");
+        assert_eq!(entry.statement, "select *\n    from t where a = ',' ");
+        assert_eq!(entry.parameter, "<not logged>");
+    }
+
+    #[test]
+    fn test_extract_audit_csv() {
+        let record = b"2025-03-10 10:00:00.123 UTC,\"alice\",\"shop\",4321,\"[local]\",67cebc00.10e1,1,\"CREATE ROLE\",2025-03-10 10:00:00 UTC,3/7,0,LOG,00000,\"AUDIT: SESSION,2,1,ROLE,CREATE ROLE,,,\"\"create role bob\"\",<none>\",,,,,,,,,\"psql\",\"client backend\",,0";
+        let entry = extract_audit(record, &Format::Csv).unwrap();
+        assert_eq!(entry.class, "ROLE");
+        assert_eq!(entry.command, "CREATE ROLE");
+        assert_eq!(entry.object_name, "");
+        assert_eq!(entry.statement, "create role bob");
+        assert_eq!(entry.parameter, "<none>");
+    }
+}
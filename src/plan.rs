@@ -0,0 +1,314 @@
+//! auto_explain output parsing: "duration: X ms  plan:" records in text or JSON format.
+
+use memchr::memmem;
+use serde_json::Value;
+
+use crate::{
+    encoding,
+    format::{Format, unescape_csv_quotes},
+};
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PlanNode {
+    /// Node description, e.g. "Seq Scan on public.pgbench_branches"
+    pub label: String,
+    pub node_type: String,
+    pub depth: usize,
+    pub parent: Option<usize>,
+    pub total_cost: f64,
+    pub plan_rows: f64,
+    pub actual_total_time: Option<f64>,
+    pub actual_rows: Option<f64>,
+    pub loops: f64,
+    pub rows_removed_by_filter: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Plan {
+    pub query_text: String,
+    /// Nodes in pre-order, children reference their parent by index
+    pub nodes: Vec<PlanNode>,
+}
+
+impl PlanNode {
+    /// Time spent in this node excluding its children, falls back to cost if the
+    /// plan was logged without ANALYZE.
+    pub fn self_time(&self, nodes: &[PlanNode], index: usize) -> f64 {
+        let total = |n: &PlanNode| {
+            n.actual_total_time
+                .map_or(n.total_cost, |t| t * n.loops.max(1.0))
+        };
+        let children: f64 = nodes
+            .iter()
+            .filter(|n| n.parent == Some(index))
+            .map(total)
+            .sum();
+        (total(self) - children).max(0.0)
+    }
+
+    /// Rows the node read: produced rows plus rows removed by filter, over all loops.
+    pub fn rows_scanned(&self) -> f64 {
+        let loops = self.loops.max(1.0);
+        self.actual_rows.unwrap_or(self.plan_rows) * loops + self.rows_removed_by_filter * loops
+    }
+}
+
+impl Plan {
+    /// Node types with their depth, used to tell different plans of the same query apart.
+    pub fn shape(&self) -> String {
+        self.nodes
+            .iter()
+            .map(|n| format!("{}{}", "  ".repeat(n.depth), n.label))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+pub fn extract_plan(record: &[u8], fmt: &Format) -> Option<Plan> {
+    let plan_text = match fmt {
+        Format::Csv => {
+            let message = fmt.message_from_bytes(record)?;
+            let start = memmem::find(message, b"plan:")? + b"plan:".len();
+            unescape_csv_quotes(&message[start..])
+        }
+        Format::Plain => {
+            let start = memmem::find(record, b"  plan:")? + b"  plan:".len();
+            record[start..].to_vec()
+        }
+    };
+    let plan_text = encoding::decode(&plan_text);
+    let plan_text = plan_text.trim();
+
+    if plan_text.starts_with('{') {
+        parse_json_plan(plan_text)
+    } else {
+        parse_text_plan(plan_text)
+    }
+}
+
+fn parse_json_plan(text: &str) -> Option<Plan> {
+    let value: Value = serde_json::from_str(text).ok()?;
+    let mut plan = Plan {
+        query_text: value
+            .get("Query Text")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        nodes: Vec::new(),
+    };
+    add_json_node(value.get("Plan")?, 0, None, &mut plan.nodes);
+    Some(plan)
+}
+
+fn add_json_node(node: &Value, depth: usize, parent: Option<usize>, nodes: &mut Vec<PlanNode>) {
+    let number = |key: &str| node.get(key).and_then(Value::as_f64);
+    let text = |key: &str| node.get(key).and_then(Value::as_str);
+
+    let node_type = text("Node Type").unwrap_or("Unknown").to_string();
+    let mut label = node_type.clone();
+    if let Some(index) = text("Index Name") {
+        label.push_str(&format!(" using {index}"));
+    }
+    if let Some(relation) = text("Relation Name") {
+        match text("Schema") {
+            Some(schema) => label.push_str(&format!(" on {schema}.{relation}")),
+            None => label.push_str(&format!(" on {relation}")),
+        }
+    }
+
+    let index = nodes.len();
+    nodes.push(PlanNode {
+        label,
+        node_type,
+        depth,
+        parent,
+        total_cost: number("Total Cost").unwrap_or_default(),
+        plan_rows: number("Plan Rows").unwrap_or_default(),
+        actual_total_time: number("Actual Total Time"),
+        actual_rows: number("Actual Rows"),
+        loops: number("Actual Loops").unwrap_or(1.0),
+        rows_removed_by_filter: number("Rows Removed by Filter").unwrap_or_default(),
+    });
+
+    if let Some(children) = node.get("Plans").and_then(Value::as_array) {
+        for child in children {
+            add_json_node(child, depth + 1, Some(index), nodes);
+        }
+    }
+}
+
+fn parse_text_plan(text: &str) -> Option<Plan> {
+    let mut plan = Plan::default();
+    // (indent, node index) of the current path from the root
+    let mut stack: Vec<(usize, usize)> = Vec::new();
+
+    for line in text.lines() {
+        // Plain logs indent plan lines with a tab
+        let line = line.strip_prefix('\t').unwrap_or(line);
+        let content = line.trim_start();
+        let mut indent = line.len() - content.len();
+
+        if let Some(query) = content.strip_prefix("Query Text:") {
+            plan.query_text = query.trim().to_string();
+            continue;
+        }
+
+        let is_child = content.starts_with("->");
+        let is_node = is_child || content.contains("(cost=") || content.contains("(actual ");
+        if !is_node {
+            if let Some(rows) = content.strip_prefix("Rows Removed by Filter:")
+                && let Some(&(_, last)) = stack.last()
+            {
+                plan.nodes[last].rows_removed_by_filter = rows.trim().parse().unwrap_or_default();
+            }
+            continue;
+        }
+
+        let description = if is_child {
+            indent += 2;
+            content.trim_start_matches("->").trim_start()
+        } else {
+            content
+        };
+        let label = description
+            .split("  (")
+            .next()
+            .unwrap_or(description)
+            .trim()
+            .to_string();
+
+        while stack.last().is_some_and(|&(i, _)| i >= indent) {
+            stack.pop();
+        }
+        let parent = stack.last().map(|&(_, index)| index);
+
+        let cost = section(description, "(cost=");
+        let actual = section(description, "(actual ");
+        let index = plan.nodes.len();
+        plan.nodes.push(PlanNode {
+            node_type: node_type(&label),
+            label,
+            depth: stack.len(),
+            parent,
+            total_cost: cost.and_then(|c| value_after(c, "..")).unwrap_or_default(),
+            plan_rows: cost
+                .and_then(|c| value_after(c, "rows="))
+                .unwrap_or_default(),
+            actual_total_time: actual
+                .and_then(|a| a.strip_prefix("time="))
+                .and_then(|t| t.split_once(".."))
+                .and_then(|(_, t)| leading_number(t)),
+            actual_rows: actual.and_then(|a| value_after(a, "rows=")),
+            loops: actual.and_then(|a| value_after(a, "loops=")).unwrap_or(1.0),
+            rows_removed_by_filter: 0.0,
+        });
+        stack.push((indent, index));
+    }
+
+    if plan.nodes.is_empty() {
+        None
+    } else {
+        Some(plan)
+    }
+}
+
+/// "Index Scan using x on t" -> "Index Scan"
+fn node_type(label: &str) -> String {
+    label
+        .split(" on ")
+        .next()
+        .unwrap_or(label)
+        .split(" using ")
+        .next()
+        .unwrap_or(label)
+        .trim()
+        .to_string()
+}
+
+fn section<'a>(text: &'a str, start: &str) -> Option<&'a str> {
+    let from = text.find(start)? + start.len();
+    let to = text[from..].find(')').map_or(text.len(), |p| from + p);
+    Some(&text[from..to])
+}
+
+fn value_after(text: &str, needle: &str) -> Option<f64> {
+    let from = text.find(needle)? + needle.len();
+    leading_number(&text[from..])
+}
+
+fn leading_number(text: &str) -> Option<f64> {
+    let end = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(text.len());
+    text[..end].parse().ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_text_plan() {
+        let record = b"2025-06-03 15:03:32.515 EEST [3718518] LOG:  duration: 0.156 ms  plan:
+\tQuery Text: select count(*) from pgbench_branches
+\tAggregate  (cost=6.25..6.26 rows=1 width=8) (actual time=0.140..0.141 rows=1 loops=1)
+\t  Output: count(*)
+\t  ->  Seq Scan on public.pgbench_branches  (cost=0.00..6.00 rows=100 width=0) (actual time=0.034..0.125 rows=100 loops=1)
+\t        Output: bid, bbalance, filler
+\t        Rows Removed by Filter: 900
+";
+        let plan = extract_plan(record, &Format::Plain).unwrap();
+        assert_eq!(plan.query_text, "select count(*) from pgbench_branches");
+        assert_eq!(plan.nodes.len(), 2);
+        assert_eq!(plan.nodes[0].label, "Aggregate");
+        assert_eq!(plan.nodes[1].label, "Seq Scan on public.pgbench_branches");
+        assert_eq!(plan.nodes[1].node_type, "Seq Scan");
+        assert_eq!(plan.nodes[1].parent, Some(0));
+        assert_eq!(plan.nodes[1].actual_total_time, Some(0.125));
+        assert_eq!(plan.nodes[1].plan_rows, 100.0);
+        assert_eq!(plan.nodes[1].rows_scanned(), 1000.0);
+        assert!((plan.nodes[0].self_time(&plan.nodes, 0) - 0.016).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_json_plan() {
+        let record = b"2025-06-03 15:03:32.515 EEST [3718518] LOG:  duration: 0.156 ms  plan:
+\t{
+\t  \"Query Text\": \"select * from t where a = 1\",
+\t  \"Plan\": {
+\t    \"Node Type\": \"Seq Scan\",
+\t    \"Relation Name\": \"t\",
+\t    \"Total Cost\": 35.50,
+\t    \"Plan Rows\": 10,
+\t    \"Actual Total Time\": 25.5,
+\t    \"Actual Rows\": 50000,
+\t    \"Actual Loops\": 1,
+\t    \"Rows Removed by Filter\": 100
+\t  }
+\t}
+";
+        let plan = extract_plan(record, &Format::Plain).unwrap();
+        assert_eq!(plan.query_text, "select * from t where a = 1");
+        assert_eq!(plan.nodes.len(), 1);
+        assert_eq!(plan.nodes[0].label, "Seq Scan on t");
+        assert_eq!(plan.nodes[0].actual_rows, Some(50000.0));
+        assert_eq!(plan.nodes[0].rows_scanned(), 50100.0);
+    }
+
+    #[test]
+    fn test_csv_plan_without_analyze() {
+        let record = b"2025-05-19 13:33:11.611 EEST,\"krl\",\"postgres\",1569302,\"127.0.0.1:58326\",682b08c8.17f216,1,\"SELECT\",2025-05-19 13:32:40 EEST,13/12,0,LOG,00000,\"duration: 118.775 ms  plan:
+Query Text: select * from pgbench_accounts where aid = 334545 ;
+Gather  (cost=1000.00..217018.62 rows=1 width=97)
+  Workers Planned: 2
+  ->  Parallel Seq Scan on pgbench_accounts  (cost=0.00..216018.52 rows=1 width=97)
+        Filter: (aid = 334545)
+JIT:
+  Functions: 2\",,,,,,,,,\"psql\",\"client backend\",,0";
+        let plan = extract_plan(record, &Format::Csv).unwrap();
+        assert_eq!(plan.nodes.len(), 2);
+        assert_eq!(plan.nodes[1].label, "Parallel Seq Scan on pgbench_accounts");
+        assert_eq!(plan.nodes[1].total_cost, 216018.52);
+        assert_eq!(plan.nodes[1].actual_rows, None);
+    }
+}
@@ -17,6 +17,25 @@ pub enum Severity {
 }
 
 impl Severity {
+    /// Severity named by a label like `ERROR`, as logged
+    pub fn from_label(label: &[u8]) -> Option<Self> {
+        Some(match label {
+            b"LOG" => Severity::Log,
+            b"ERROR" => Severity::Error,
+            b"INFO" => Severity::Info,
+            b"NOTICE" => Severity::Notice,
+            b"WARNING" => Severity::Warning,
+            b"DEBUG5" => Severity::Debug5,
+            b"DEBUG4" => Severity::Debug4,
+            b"DEBUG3" => Severity::Debug3,
+            b"DEBUG2" => Severity::Debug2,
+            b"DEBUG1" => Severity::Debug1,
+            b"FATAL" => Severity::Fatal,
+            b"PANIC" => Severity::Panic,
+            _ => return None,
+        })
+    }
+
     pub fn from_csv_string(str: &str) -> Self {
         if str.contains(",LOG,") {
             return Severity::Log;
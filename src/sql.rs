@@ -0,0 +1,578 @@
+//! Lightweight SQL tokenizer, good enough to normalize logged statements
+//! and to pick keywords / relation names out of them. Not a parser.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token<'a> {
+    /// Keyword or unquoted identifier, as written
+    Word(&'a str),
+    /// "Quoted identifier", without the quotes
+    QuotedIdent(&'a str),
+    /// 'string', E'string', $$string$$ or $tag$string$tag$ literal
+    String,
+    Number(&'a str),
+    /// $1 style bind parameter
+    Param(&'a str),
+    /// Operators and punctuation, e.g. `(`, `,`, `.`, `::`, `>=`
+    Symbol(&'a str),
+}
+
+pub fn tokenize(sql: &str) -> Vec<Token<'_>> {
+    let bytes = sql.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let b = bytes[i];
+        match b {
+            _ if b.is_ascii_whitespace() => i += 1,
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i += 2;
+                while i + 1 < bytes.len() && !(bytes[i] == b'*' && bytes[i + 1] == b'/') {
+                    i += 1;
+                }
+                i = (i + 2).min(bytes.len());
+            }
+            b'\'' => {
+                i = skip_string(bytes, i);
+                tokens.push(Token::String);
+            }
+            b'e' | b'E' | b'x' | b'X' | b'b' | b'B' | b'n' | b'N'
+                if bytes.get(i + 1) == Some(&b'\'') =>
+            {
+                i = skip_string(bytes, i + 1);
+                tokens.push(Token::String);
+            }
+            b'"' => {
+                let start = i + 1;
+                i = start;
+                while i < bytes.len() {
+                    if bytes[i] == b'"' {
+                        if bytes.get(i + 1) == Some(&b'"') {
+                            i += 2;
+                            continue;
+                        }
+                        break;
+                    }
+                    i += 1;
+                }
+                tokens.push(Token::QuotedIdent(&sql[start..i.min(bytes.len())]));
+                i = (i + 1).min(bytes.len());
+            }
+            b'$' if bytes.get(i + 1).is_some_and(u8::is_ascii_digit) => {
+                let start = i;
+                i += 1;
+                while i < bytes.len() && bytes[i].is_ascii_digit() {
+                    i += 1;
+                }
+                tokens.push(Token::Param(&sql[start..i]));
+            }
+            b'$' => {
+                // Dollar quoting: $$...$$ or $tag$...$tag$
+                let tag_end = bytes[i + 1..]
+                    .iter()
+                    .position(|c| !(c.is_ascii_alphanumeric() || *c == b'_'))
+                    .map(|p| i + 1 + p);
+                match tag_end {
+                    Some(end) if bytes[end] == b'$' => {
+                        let tag = &bytes[i..=end];
+                        let body = end + 1;
+                        i = memchr::memmem::find(&bytes[body..], tag)
+                            .map_or(bytes.len(), |p| body + p + tag.len());
+                        tokens.push(Token::String);
+                    }
+                    _ => {
+                        tokens.push(Token::Symbol(&sql[i..=i]));
+                        i += 1;
+                    }
+                }
+            }
+            _ if b.is_ascii_digit()
+                || (b == b'.' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit)) =>
+            {
+                let start = i;
+                while i < bytes.len()
+                    && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'.' || bytes[i] == b'_')
+                {
+                    i += 1;
+                }
+                tokens.push(Token::Number(&sql[start..i]));
+            }
+            _ if b.is_ascii_alphabetic() || b == b'_' || b >= 0x80 => {
+                let start = i;
+                while i < bytes.len()
+                    && (bytes[i].is_ascii_alphanumeric()
+                        || bytes[i] == b'_'
+                        || bytes[i] == b'$'
+                        || bytes[i] >= 0x80)
+                {
+                    i += 1;
+                }
+                tokens.push(Token::Word(&sql[start..i]));
+            }
+            _ => {
+                let start = i;
+                i += 1;
+                const OPERATOR_CHARS: &[u8] = b"+-*/<>=~!@#%^&|`?:";
+                if OPERATOR_CHARS.contains(&b) {
+                    while i < bytes.len() && OPERATOR_CHARS.contains(&bytes[i]) {
+                        i += 1;
+                    }
+                }
+                tokens.push(Token::Symbol(&sql[start..i]));
+            }
+        }
+    }
+
+    tokens
+}
+
+fn skip_string(bytes: &[u8], quote: usize) -> usize {
+    let mut i = quote + 1;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' {
+            i += 2;
+            continue;
+        }
+        if bytes[i] == b'\'' {
+            if bytes.get(i + 1) == Some(&b'\'') {
+                i += 2;
+                continue;
+            }
+            return i + 1;
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+/// Normalizes a statement so executions with different constants group together:
+/// literals and bind parameters become `?`, lists of them collapse to one,
+/// keywords and identifiers are lowercased and whitespace / comments are dropped.
+pub fn fingerprint(sql: &str) -> String {
+    let mut parts: Vec<String> = Vec::new();
+
+    for token in tokenize(sql) {
+        let part = match token {
+            Token::Word(w) => w.to_lowercase(),
+            Token::QuotedIdent(q) => format!("\"{q}\""),
+            Token::String | Token::Number(_) | Token::Param(_) => "?".to_string(),
+            Token::Symbol(";") => continue,
+            Token::Symbol(s) => s.to_string(),
+        };
+
+        // "?, ?, ?" -> "?"
+        let len = parts.len();
+        if part == "?" && len >= 2 && parts[len - 1] == "," && parts[len - 2] == "?" {
+            parts.pop();
+            continue;
+        }
+        parts.push(part);
+    }
+
+    let mut result = String::new();
+    let mut previous = "";
+    for part in &parts {
+        let glue = matches!(part.as_str(), "," | ")" | "." | "::" | "[" | "]")
+            || matches!(previous, "(" | "." | "::" | "[")
+            || previous.is_empty();
+        if !glue {
+            result.push(' ');
+        }
+        result.push_str(part);
+        previous = part;
+    }
+    result
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum StatementType {
+    Select,
+    Insert,
+    Update,
+    Delete,
+    Copy,
+    Ddl,
+    Utility,
+    Transaction,
+}
+
+impl StatementType {
+    pub const ALL: [StatementType; 8] = [
+        StatementType::Select,
+        StatementType::Insert,
+        StatementType::Update,
+        StatementType::Delete,
+        StatementType::Copy,
+        StatementType::Ddl,
+        StatementType::Utility,
+        StatementType::Transaction,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StatementType::Select => "SELECT",
+            StatementType::Insert => "INSERT",
+            StatementType::Update => "UPDATE",
+            StatementType::Delete => "DELETE",
+            StatementType::Copy => "COPY",
+            StatementType::Ddl => "DDL",
+            StatementType::Utility => "UTILITY",
+            StatementType::Transaction => "TCL",
+        }
+    }
+}
+
+/// Classifies a statement by its leading keyword, for `WITH` by the statement following the CTEs.
+pub fn statement_type(sql: &str) -> StatementType {
+    let tokens = tokenize(sql);
+    let mut words = tokens.iter().filter_map(|t| match t {
+        Token::Word(w) => Some(w.to_ascii_uppercase()),
+        _ => None,
+    });
+    let Some(first) = words.next() else {
+        return StatementType::Utility;
+    };
+
+    match first.as_str() {
+        // Cursors are declared and fetched for reading
+        "SELECT" | "VALUES" | "TABLE" | "DECLARE" | "FETCH" => StatementType::Select,
+        "INSERT" => StatementType::Insert,
+        "UPDATE" | "MERGE" => StatementType::Update,
+        "DELETE" => StatementType::Delete,
+        "COPY" => StatementType::Copy,
+        "CREATE" | "ALTER" | "DROP" | "TRUNCATE" | "COMMENT" | "GRANT" | "REVOKE" | "SECURITY"
+        | "IMPORT" => StatementType::Ddl,
+        "BEGIN" | "START" | "COMMIT" | "END" | "ROLLBACK" | "ABORT" | "SAVEPOINT" | "RELEASE" => {
+            StatementType::Transaction
+        }
+        "PREPARE" if words.next().as_deref() == Some("TRANSACTION") => StatementType::Transaction,
+        "WITH" => {
+            // First data modifying / reading keyword outside of the CTE bodies
+            let mut depth = 0;
+            for token in &tokens {
+                match token {
+                    Token::Symbol("(") => depth += 1,
+                    Token::Symbol(")") => depth -= 1,
+                    Token::Word(w) if depth == 0 => match w.to_ascii_uppercase().as_str() {
+                        "SELECT" | "VALUES" | "TABLE" => return StatementType::Select,
+                        "INSERT" => return StatementType::Insert,
+                        "UPDATE" | "MERGE" => return StatementType::Update,
+                        "DELETE" => return StatementType::Delete,
+                        _ => {}
+                    },
+                    _ => {}
+                }
+            }
+            StatementType::Select
+        }
+        _ => StatementType::Utility,
+    }
+}
+
+/// Keywords ending a relation list, never taken as relation names or aliases
+const NOT_A_NAME: &[&str] = &[
+    "AS",
+    "CROSS",
+    "DEFAULT",
+    "DO",
+    "EXCEPT",
+    "FETCH",
+    "FOR",
+    "FROM",
+    "FULL",
+    "GROUP",
+    "HAVING",
+    "INNER",
+    "INTERSECT",
+    "INTO",
+    "JOIN",
+    "LATERAL",
+    "LEFT",
+    "LIMIT",
+    "NATURAL",
+    "OFFSET",
+    "ON",
+    "ORDER",
+    "OUTER",
+    "OVERRIDING",
+    "PROGRAM",
+    "RETURNING",
+    "RIGHT",
+    "SELECT",
+    "SET",
+    "STDIN",
+    "STDOUT",
+    "TABLESAMPLE",
+    "TO",
+    "UNION",
+    "USING",
+    "VALUES",
+    "WHERE",
+    "WINDOW",
+    "WITH",
+];
+
+fn is_name(word: &str) -> bool {
+    !NOT_A_NAME.iter().any(|k| k.eq_ignore_ascii_case(word))
+}
+
+/// Relations referenced after FROM, JOIN, INTO, UPDATE and COPY, lowercased unless quoted,
+/// in order of appearance and without duplicates. Function calls and FROM inside
+/// expressions like `extract(year FROM ts)` are skipped.
+pub fn referenced_relations(sql: &str) -> Vec<String> {
+    let tokens = tokenize(sql);
+    let mut relations: Vec<String> = Vec::new();
+    // Per open parenthesis: whether it holds an expression rather than a subquery
+    let mut parens: Vec<bool> = Vec::new();
+    let mut i = 0;
+
+    let keyword = |i: usize, k: &str| matches!(tokens.get(i), Some(Token::Word(w)) if w.eq_ignore_ascii_case(k));
+
+    while i < tokens.len() {
+        match &tokens[i] {
+            Token::Symbol("(") => {
+                let subquery =
+                    keyword(i + 1, "SELECT") || keyword(i + 1, "WITH") || keyword(i + 1, "VALUES");
+                let after_word = i > 0 && matches!(tokens[i - 1], Token::Word(_));
+                parens.push(after_word && !subquery);
+                i += 1;
+            }
+            Token::Symbol(")") => {
+                parens.pop();
+                i += 1;
+            }
+            Token::Word(w)
+                if !parens.iter().any(|expression| *expression)
+                    && ["FROM", "JOIN", "INTO", "UPDATE", "COPY"]
+                        .iter()
+                        .any(|k| k.eq_ignore_ascii_case(w))
+                    // FOR [NO KEY] UPDATE, ON CONFLICT DO UPDATE
+                    && !(w.eq_ignore_ascii_case("UPDATE")
+                        && i > 0
+                        && (keyword(i - 1, "FOR") || keyword(i - 1, "KEY") || keyword(i - 1, "DO"))) =>
+            {
+                i += 1;
+                // FROM a, b JOIN ... - keep reading while the list goes on
+                loop {
+                    if keyword(i, "ONLY") {
+                        i += 1;
+                    }
+                    let Some((name, next)) = qualified_name(&tokens, i) else {
+                        break;
+                    };
+                    i = next;
+                    if matches!(tokens.get(i), Some(Token::Symbol("("))) {
+                        // Function call, e.g. FROM generate_series(1, 10)
+                        break;
+                    }
+                    if !relations.contains(&name) {
+                        relations.push(name);
+                    }
+                    // Optional alias
+                    if keyword(i, "AS") {
+                        i += 2;
+                    } else if matches!(tokens.get(i), Some(Token::Word(w)) if is_name(w)) {
+                        i += 1;
+                    }
+                    if matches!(tokens.get(i), Some(Token::Symbol(","))) {
+                        i += 1;
+                    } else {
+                        break;
+                    }
+                }
+            }
+            _ => i += 1,
+        }
+    }
+
+    relations
+}
+
+/// Reads `name`, `schema.name` or `"Quoted".name` starting at `start`
+fn qualified_name(tokens: &[Token], start: usize) -> Option<(String, usize)> {
+    let mut name = String::new();
+    let mut i = start;
+    loop {
+        match tokens.get(i)? {
+            Token::Word(w) if is_name(w) => name.push_str(&w.to_lowercase()),
+            Token::QuotedIdent(q) => name.push_str(q),
+            _ if name.is_empty() => return None,
+            // Trailing dot
+            _ => return None,
+        }
+        i += 1;
+        if matches!(tokens.get(i), Some(Token::Symbol("."))) {
+            name.push('.');
+            i += 1;
+        } else {
+            return Some((name, i));
+        }
+    }
+}
+
+/// Replaces `$n` bind parameter placeholders outside of string literals and quoted
+/// identifiers with the given values, e.g. `'42'`.
+pub fn inline_params(sql: &str, params: &[(usize, String)]) -> String {
+    let bytes = sql.as_bytes();
+    let mut result = String::with_capacity(sql.len());
+    let mut copied = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\'' => i = skip_string(bytes, i),
+            b'"' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            b'$' if bytes.get(i + 1).is_some_and(u8::is_ascii_digit) => {
+                let start = i;
+                i += 1;
+                while i < bytes.len() && bytes[i].is_ascii_digit() {
+                    i += 1;
+                }
+                let index: Option<usize> = sql[start + 1..i].parse().ok();
+                if let Some((_, value)) = params.iter().find(|(n, _)| Some(*n) == index) {
+                    result.push_str(&sql[copied..start]);
+                    result.push_str(value);
+                    copied = i;
+                }
+            }
+            _ => i += 1,
+        }
+    }
+
+    result.push_str(&sql[copied.min(sql.len())..]);
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        let tokens = tokenize("SELECT \"Col\", 'it''s', 1.5 FROM s.t WHERE a = $1 -- comment");
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Word("SELECT"),
+                Token::QuotedIdent("Col"),
+                Token::Symbol(","),
+                Token::String,
+                Token::Symbol(","),
+                Token::Number("1.5"),
+                Token::Word("FROM"),
+                Token::Word("s"),
+                Token::Symbol("."),
+                Token::Word("t"),
+                Token::Word("WHERE"),
+                Token::Word("a"),
+                Token::Symbol("="),
+                Token::Param("$1"),
+            ]
+        );
+
+        assert_eq!(
+            tokenize("do $body$ select 'x' $body$"),
+            vec![Token::Word("do"), Token::String]
+        );
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        assert_eq!(
+            fingerprint("select * from pgbench_accounts where aid = 334545 ;"),
+            "select * from pgbench_accounts where aid = ?"
+        );
+        assert_eq!(
+            fingerprint("SELECT abalance FROM pgbench_accounts WHERE aid = $1;"),
+            "select abalance from pgbench_accounts where aid = ?"
+        );
+        assert_eq!(
+            fingerprint("select x from t where id in (1, 2, 3) and s.name = 'a'"),
+            "select x from t where id in (?) and s.name = ?"
+        );
+    }
+
+    #[test]
+    fn test_statement_type() {
+        let cases = [
+            ("BEGIN;", StatementType::Transaction),
+            ("CREATE TABLE test (data text);", StatementType::Ddl),
+            (
+                "INSERT INTO test SELECT 'data' FROM generate_series(1, 100);",
+                StatementType::Insert,
+            ),
+            ("(SELECT 1) UNION (SELECT 2)", StatementType::Select),
+            (
+                "UPDATE test SET col1='forêt & océan' WHERE col2 = 1;",
+                StatementType::Update,
+            ),
+            (
+                "WITH wf1 AS ( SELECT id FROM t1) DELETE FROM test WHERE col1 = wf1.id;",
+                StatementType::Delete,
+            ),
+            (
+                "DECLARE CURSOR test_curs1 AS SELECT * FROM test",
+                StatementType::Select,
+            ),
+            ("FETCH 1 FROM test_curs1;", StatementType::Select),
+            (
+                "COPY country TO STDOUT (DELIMITER '|');",
+                StatementType::Copy,
+            ),
+            ("PREPARE TRANSACTION 'tx1'", StatementType::Transaction),
+            ("prepare foo (int) as select $1", StatementType::Utility),
+            ("set work_mem to '64kB';", StatementType::Utility),
+            ("COMMIT;", StatementType::Transaction),
+        ];
+        for (sql, expected) in cases {
+            assert_eq!(statement_type(sql), expected, "{sql}");
+        }
+    }
+
+    #[test]
+    fn test_referenced_relations() {
+        assert_eq!(
+            referenced_relations(
+                "SELECT * FROM public.accounts a, branches JOIN \"Orders\" o ON o.aid = a.id WHERE extract(year FROM o.ts) = 2025"
+            ),
+            vec!["public.accounts", "branches", "Orders"]
+        );
+        assert_eq!(
+            referenced_relations(
+                "WITH x AS (SELECT id FROM t1) DELETE FROM test USING x WHERE test.id = x.id"
+            ),
+            vec!["t1", "test"]
+        );
+        assert_eq!(
+            referenced_relations("INSERT INTO test SELECT 'data' FROM generate_series(1, 100)"),
+            vec!["test"]
+        );
+        assert_eq!(
+            referenced_relations(
+                "UPDATE pgbench_branches SET bbalance = bbalance + $1 WHERE bid = $2"
+            ),
+            vec!["pgbench_branches"]
+        );
+        assert_eq!(
+            referenced_relations("COPY country FROM STDIN"),
+            vec!["country"]
+        );
+        assert_eq!(
+            referenced_relations("SELECT * FROM t FOR UPDATE SKIP LOCKED"),
+            vec!["t"]
+        );
+    }
+}
@@ -0,0 +1,165 @@
+//! Saving aggregator state to a file (`--save-state`) and combining saved states (`merge`),
+//! e.g. to build a fleet-wide report out of per-host runs.
+
+use std::{fs, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    Error, Result,
+    aggregators::{
+        Aggregator, ArchiveAggregator, AuditAggregator, ConfigAggregator, ConnectionsAggregator,
+        ErrorFrequencyAggregator, ErrorHistogramAggregator, PlansAggregator, QueryIdAggregator,
+        ReplicationAggregator, SlowQueries, TablesAggregator, TopSlowQueries, WorkloadAggregator,
+    },
+};
+
+/// Bumped whenever saved aggregator state changes incompatibly
+const STATE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct StateFile {
+    version: u32,
+    aggregators: Vec<SavedAggregator>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedAggregator {
+    kind: String,
+    state: Value,
+}
+
+pub fn save(path: &PathBuf, aggregators: &[Box<dyn Aggregator>]) -> Result<()> {
+    if aggregators.is_empty() {
+        return Err(Error::NothingToSave);
+    }
+    let state = StateFile {
+        version: STATE_VERSION,
+        aggregators: aggregators
+            .iter()
+            .map(|a| -> Result<SavedAggregator> {
+                Ok(SavedAggregator {
+                    kind: a.kind().to_string(),
+                    state: a.save_state()?,
+                })
+            })
+            .collect::<Result<_>>()?,
+    };
+    fs::write(path, serde_json::to_vec(&state)?)?;
+    Ok(())
+}
+
+/// Loads saved states and merges them in the given order. All files have to be
+/// saved by the same command.
+pub fn load(paths: &[PathBuf]) -> Result<Vec<Box<dyn Aggregator>>> {
+    let mut merged: Vec<Box<dyn Aggregator>> = Vec::new();
+
+    for path in paths {
+        let state: StateFile = serde_json::from_slice(&fs::read(path)?)?;
+        if state.version != STATE_VERSION {
+            return Err(Error::StateVersionMismatch {
+                path: path.clone(),
+                version: state.version,
+            });
+        }
+
+        let aggregators = state
+            .aggregators
+            .into_iter()
+            .map(|saved| restore(&saved.kind, saved.state))
+            .collect::<Result<Vec<_>>>()?;
+
+        if merged.is_empty() {
+            merged = aggregators;
+            continue;
+        }
+        let kinds = |aggregators: &[Box<dyn Aggregator>]| -> Vec<&'static str> {
+            aggregators.iter().map(|a| a.kind()).collect()
+        };
+        if kinds(&merged) != kinds(&aggregators) {
+            return Err(Error::StateKindMismatch { path: path.clone() });
+        }
+        for (mine, theirs) in merged.iter_mut().zip(aggregators) {
+            mine.merge_box(theirs.as_ref());
+        }
+    }
+
+    Ok(merged)
+}
+
+fn restore(kind: &str, state: Value) -> Result<Box<dyn Aggregator>> {
+    Ok(match kind {
+        "archive" => Box::new(serde_json::from_value::<ArchiveAggregator>(state)?),
+        "audit" => Box::new(serde_json::from_value::<AuditAggregator>(state)?),
+        "config" => Box::new(serde_json::from_value::<ConfigAggregator>(state)?),
+        "connections" => Box::new(serde_json::from_value::<ConnectionsAggregator>(state)?),
+        "error_frequency" => Box::new(serde_json::from_value::<ErrorFrequencyAggregator>(state)?),
+        "error_histogram" => Box::new(serde_json::from_value::<ErrorHistogramAggregator>(state)?),
+        "plans" => Box::new(serde_json::from_value::<PlansAggregator>(state)?),
+        "query_id" => Box::new(serde_json::from_value::<QueryIdAggregator>(state)?),
+        "replication" => Box::new(serde_json::from_value::<ReplicationAggregator>(state)?),
+        "slow_queries" => Box::new(serde_json::from_value::<SlowQueries>(state)?),
+        "tables" => Box::new(serde_json::from_value::<TablesAggregator>(state)?),
+        "top_slow_queries" => Box::new(serde_json::from_value::<TopSlowQueries>(state)?),
+        "workload" => Box::new(serde_json::from_value::<WorkloadAggregator>(state)?),
+        _ => {
+            return Err(Error::UnknownAggregatorState {
+                kind: kind.to_string(),
+            });
+        }
+    })
+}
+
+/// Serializes a map as a list of key-value pairs, for maps keyed by something other than
+/// a string, e.g. `#[serde(with = "crate::state::pairs")]`
+pub mod pairs {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<'a, M, K, V, S>(map: &'a M, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        &'a M: IntoIterator<Item = (&'a K, &'a V)>,
+        K: Serialize + 'a,
+        V: Serialize + 'a,
+        S: Serializer,
+    {
+        serializer.collect_seq(map)
+    }
+
+    pub fn deserialize<'de, M, K, V, D>(deserializer: D) -> Result<M, D::Error>
+    where
+        M: FromIterator<(K, V)>,
+        K: Deserialize<'de>,
+        V: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        Ok(Vec::<(K, V)>::deserialize(deserializer)?
+            .into_iter()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Keyed {
+        #[serde(with = "super::pairs")]
+        map: HashMap<(String, Option<Vec<u8>>), u64>,
+    }
+
+    #[test]
+    fn test_pairs() {
+        let keyed = Keyed {
+            map: HashMap::from([
+                (("postgres".to_string(), Some(b"123".to_vec())), 1),
+                (("app".to_string(), None), 2),
+            ]),
+        };
+        let json = serde_json::to_string(&keyed).unwrap();
+        assert_eq!(serde_json::from_str::<Keyed>(&json).unwrap(), keyed);
+    }
+}
@@ -0,0 +1,531 @@
+//! Statements logged with a duration, i.e. by `log_min_duration_statement`:
+//! "duration: X ms  statement: ..." or, for the extended query protocol,
+//! "duration: X ms  parse|bind|execute <name>: ..." followed by the bind parameters
+//! in "DETAIL:  parameters: $1 = '42', $2 = NULL".
+
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use chrono::{DateTime, Local};
+use memchr::memmem;
+
+use crate::{
+    duration::extract_duration,
+    encoding,
+    format::{Format, unescape_csv_quotes},
+    sql::inline_params,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Simple query protocol
+    Statement,
+    Parse,
+    Bind,
+    Execute,
+}
+
+/// Returns the phase and the statement text of a "duration: ..." or `log_statement` message.
+pub fn statement_phase(message: &[u8]) -> Option<(Phase, &[u8])> {
+    let rest = if message.starts_with(b"duration: ") {
+        let start = memmem::find(message, b" ms  ")? + b" ms  ".len();
+        &message[start..]
+    } else {
+        message
+    };
+
+    let (phase, rest) = if let Some(sql) = rest.strip_prefix(b"statement: ") {
+        return Some((Phase::Statement, sql));
+    } else if let Some(rest) = rest.strip_prefix(b"parse ") {
+        (Phase::Parse, rest)
+    } else if let Some(rest) = rest.strip_prefix(b"bind ") {
+        (Phase::Bind, rest)
+    } else if let Some(rest) = rest.strip_prefix(b"execute ") {
+        (Phase::Execute, rest)
+    } else {
+        return None;
+    };
+
+    // Skip the prepared statement / portal name, e.g. "<unnamed>" or "S_1/C_2"
+    let name_end = memmem::find(rest, b": ")?;
+    Some((phase, &rest[name_end + 2..]))
+}
+
+/// Bind parameters logged for a statement: in the record's own DETAIL column for CSV logs,
+/// or in a separate "DETAIL:  parameters: ..." record for plain logs. `log_statement`
+/// writes "Parameters:" instead. Values are returned as SQL literals, e.g. `'42'` or `NULL`.
+pub fn bind_parameters(record: &[u8], fmt: &Format) -> Option<Vec<(usize, String)>> {
+    const NEEDLE: &[u8] = b"parameters: ";
+    let detail = match fmt {
+        Format::Csv => unescape_csv_quotes(fmt.detail_from_bytes(record)?),
+        Format::Plain => {
+            // detail is a sub-slice of record, the values can span more lines
+            let start = fmt.detail_from_bytes(record)?.as_ptr() as usize - record.as_ptr() as usize;
+            if !record[start..]
+                .get(..NEEDLE.len())
+                .is_some_and(|p| p.eq_ignore_ascii_case(NEEDLE))
+            {
+                return None;
+            }
+            // Continuation lines of multi-line values are indented with a tab
+            let mut detail = Vec::with_capacity(record.len() - start);
+            for (i, line) in record[start..]
+                .trim_ascii_end()
+                .split(|&b| b == b'\n')
+                .enumerate()
+            {
+                if i > 0 {
+                    detail.push(b'\n');
+                }
+                detail.extend_from_slice(line.strip_prefix(b"\t").unwrap_or(line));
+            }
+            detail
+        }
+    };
+
+    if !detail
+        .get(..NEEDLE.len())
+        .is_some_and(|p| p.eq_ignore_ascii_case(NEEDLE))
+    {
+        return None;
+    }
+    parse_parameters(&detail[NEEDLE.len()..])
+}
+
+fn parse_parameters(text: &[u8]) -> Option<Vec<(usize, String)>> {
+    let mut params = Vec::new();
+    let mut i = 0;
+
+    while i < text.len() {
+        if text[i] != b'$' {
+            return None;
+        }
+        let digits = text[i + 1..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        let index: usize = std::str::from_utf8(&text[i + 1..i + 1 + digits])
+            .ok()?
+            .parse()
+            .ok()?;
+        i += 1 + digits;
+        i += text[i..].strip_prefix(b" = ").map(|_| 3)?;
+
+        let value_start = i;
+        if text[i..].starts_with(b"NULL") {
+            i += 4;
+        } else if text[i] == b'\'' {
+            i += 1;
+            while i < text.len() {
+                if text[i] == b'\'' {
+                    if text.get(i + 1) == Some(&b'\'') {
+                        i += 2;
+                        continue;
+                    }
+                    break;
+                }
+                i += 1;
+            }
+            i = (i + 1).min(text.len());
+        } else {
+            return None;
+        }
+        params.push((index, encoding::decode(&text[value_start..i]).to_string()));
+
+        if text[i..].starts_with(b", ") {
+            i += 2;
+        }
+    }
+
+    if params.is_empty() {
+        None
+    } else {
+        Some(params)
+    }
+}
+
+/// Returns the record with its statement text having `$n` placeholders replaced by `params`.
+pub fn inline_record(record: &[u8], fmt: &Format, params: &[(usize, String)]) -> Vec<u8> {
+    let Some((_, sql)) = fmt.message_from_bytes(record).and_then(statement_phase) else {
+        return record.to_vec();
+    };
+    // Both are sub-slices of record
+    let start = sql.as_ptr() as usize - record.as_ptr() as usize;
+    let mut end = start + sql.len();
+    if matches!(fmt, Format::Plain) {
+        // Plain message stops at the first newline, the statement can span more lines
+        end += record[end..].trim_ascii_end().len();
+    }
+
+    let inlined = match fmt {
+        Format::Csv => {
+            let sql = encoding::decode(&unescape_csv_quotes(&record[start..end])).to_string();
+            inline_params(&sql, params).replace('"', "\"\"")
+        }
+        Format::Plain => inline_params(&encoding::decode(&record[start..end]), params),
+    };
+
+    let mut result = Vec::with_capacity(record.len() + inlined.len());
+    result.extend_from_slice(&record[..start]);
+    result.extend_from_slice(inlined.as_bytes());
+    result.extend_from_slice(&record[end..]);
+    result
+}
+
+/// Record's process id, statements and their durations / parameters are paired per backend
+type Pid = Option<Vec<u8>>;
+
+/// A statement with its duration, as it should be shown
+pub struct TimedStatement<'a> {
+    pub log_time: DateTime<Local>,
+    pub duration: Duration,
+    pub record: Cow<'a, [u8]>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+enum Waiting {
+    /// "duration: X ms  statement: ..." record
+    Timed(DateTime<Local>, Duration, Vec<u8>),
+    /// log_statement record, its duration is logged later on in a separate record
+    Statement(Vec<u8>),
+}
+
+/// Turns records, fed in file order, into statements with durations:
+///  - pairs bare "duration: X ms" records (`log_duration`) with the preceding
+///    `log_statement` record of the same backend
+///  - optionally inlines bind parameters of the "DETAIL:  parameters:" record that
+///    plain logs write right after the statement
+///
+/// Processing happens per chunk, so durations of statements logged in the preceding chunk
+/// and parameters at the very start of a chunk are kept aside for `merge`.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct StatementTracker {
+    inline_params: bool,
+    fmt: Option<Format>,
+    /// Record waiting for a possible parameters record
+    pending: Option<(Pid, Waiting)>,
+    leading_params: Option<(Pid, Vec<(usize, String)>)>,
+    /// Message of the last log_statement record per backend, not yet paired with a duration
+    #[serde(with = "crate::state::pairs")]
+    statements: HashMap<Pid, Vec<u8>>,
+    seen_pids: HashSet<Pid>,
+    /// Bare durations of backends not seen before in this chunk
+    orphans: Vec<(Pid, DateTime<Local>, Duration, Vec<u8>)>,
+    seen_records: bool,
+}
+
+impl StatementTracker {
+    pub fn new(inline_params: bool) -> Self {
+        Self {
+            inline_params,
+            ..Default::default()
+        }
+    }
+
+    /// Format of the records fed so far
+    pub fn format(&self) -> Option<Format> {
+        self.fmt
+    }
+
+    pub fn push<'a>(
+        &mut self,
+        record: &'a [u8],
+        fmt: &Format,
+        log_time: DateTime<Local>,
+        mut emit: impl FnMut(TimedStatement<'a>),
+    ) {
+        let first_record = !self.seen_records;
+        self.seen_records = true;
+        self.fmt = Some(*fmt);
+        let pid = fmt.pid_from_bytes(record).map(<[u8]>::to_vec);
+
+        if self.inline_params
+            && *fmt == Format::Plain
+            && let Some(params) = bind_parameters(record, fmt)
+        {
+            match self.pending.take() {
+                Some((pending_pid, waiting)) if pending_pid == pid => {
+                    self.complete(pid, waiting, Some(&params), &mut emit);
+                }
+                Some((pending_pid, waiting)) => {
+                    self.complete(pending_pid, waiting, None, &mut emit);
+                }
+                None if first_record => self.leading_params = Some((pid, params)),
+                None => {}
+            }
+            return;
+        }
+
+        if let Some((pending_pid, waiting)) = self.pending.take() {
+            self.complete(pending_pid, waiting, None, &mut emit);
+        }
+
+        let Some(message) = fmt.message_from_bytes(record) else {
+            return;
+        };
+        let first_of_backend = self.seen_pids.insert(pid.clone());
+        let params = match fmt {
+            // Parameters are in the same record
+            Format::Csv if self.inline_params => bind_parameters(record, fmt),
+            _ => None,
+        };
+
+        if let Some(duration) = extract_duration(record) {
+            if is_bare_duration(message) {
+                if let Some(statement) = self.statements.remove(&pid) {
+                    emit(TimedStatement {
+                        log_time,
+                        duration,
+                        record: Cow::Owned(attach_statement(record, fmt, &statement)),
+                    });
+                } else if first_of_backend && pid.is_some() {
+                    self.orphans
+                        .push((pid, log_time, duration, record.to_vec()));
+                } else {
+                    emit(TimedStatement {
+                        log_time,
+                        duration,
+                        record: Cow::Borrowed(record),
+                    });
+                }
+            } else if let Some(params) = params {
+                emit(TimedStatement {
+                    log_time,
+                    duration,
+                    record: Cow::Owned(inline_record(record, fmt, &params)),
+                });
+            } else if self.inline_params && *fmt == Format::Plain {
+                self.pending = Some((pid, Waiting::Timed(log_time, duration, record.to_vec())));
+            } else {
+                emit(TimedStatement {
+                    log_time,
+                    duration,
+                    record: Cow::Borrowed(record),
+                });
+            }
+        } else if is_logged_statement(message) {
+            if let Some(params) = params {
+                let inlined = inline_record(record, fmt, &params);
+                self.statements
+                    .insert(pid, statement_message(&inlined, fmt).to_vec());
+            } else if self.inline_params && *fmt == Format::Plain {
+                self.pending = Some((pid, Waiting::Statement(record.to_vec())));
+            } else {
+                self.statements
+                    .insert(pid, statement_message(record, fmt).to_vec());
+            }
+        }
+    }
+
+    fn complete<'a>(
+        &mut self,
+        pid: Pid,
+        waiting: Waiting,
+        params: Option<&[(usize, String)]>,
+        emit: &mut impl FnMut(TimedStatement<'a>),
+    ) {
+        let fmt = self.fmt.unwrap_or(Format::Plain);
+        match waiting {
+            Waiting::Timed(log_time, duration, record) => emit(TimedStatement {
+                log_time,
+                duration,
+                record: Cow::Owned(match params {
+                    Some(params) => inline_record(&record, &fmt, params),
+                    None => record,
+                }),
+            }),
+            Waiting::Statement(record) => {
+                let record = match params {
+                    Some(params) => inline_record(&record, &fmt, params),
+                    None => record,
+                };
+                self.statements
+                    .insert(pid, statement_message(&record, &fmt).to_vec());
+            }
+        }
+    }
+
+    /// Emits whatever is still waiting, to be called once all records are fed
+    pub fn finish(&mut self, mut emit: impl FnMut(TimedStatement)) {
+        if let Some((pid, waiting)) = self.pending.take() {
+            self.complete(pid, waiting, None, &mut emit);
+        }
+        for (_, log_time, duration, record) in self.orphans.drain(..) {
+            emit(TimedStatement {
+                log_time,
+                duration,
+                record: Cow::Owned(record),
+            });
+        }
+    }
+
+    /// Continues with the state of the chunk following this one
+    pub fn merge(&mut self, next: &StatementTracker, mut emit: impl FnMut(TimedStatement)) {
+        if !next.seen_records {
+            return;
+        }
+        if !self.seen_records {
+            *self = next.clone();
+            return;
+        }
+
+        if let Some((pid, waiting)) = self.pending.take() {
+            let params = match &next.leading_params {
+                Some((params_pid, params)) if *params_pid == pid => Some(params.as_slice()),
+                _ => None,
+            };
+            self.complete(pid, waiting, params, &mut emit);
+        }
+
+        let fmt = next.fmt.unwrap_or(Format::Plain);
+        for (pid, log_time, duration, record) in &next.orphans {
+            let record = match self.statements.remove(pid) {
+                Some(statement) => attach_statement(record, &fmt, &statement),
+                None => record.clone(),
+            };
+            emit(TimedStatement {
+                log_time: *log_time,
+                duration: *duration,
+                record: Cow::Owned(record),
+            });
+        }
+
+        // Backends seen in the next chunk have moved on from their earlier statements
+        self.statements
+            .retain(|pid, _| !next.seen_pids.contains(pid));
+        self.statements.extend(next.statements.clone());
+        self.seen_pids.extend(next.seen_pids.iter().cloned());
+        self.pending.clone_from(&next.pending);
+        self.fmt = next.fmt;
+    }
+}
+
+/// "duration: 1.23 ms" without the statement, as logged by `log_duration`
+pub fn is_bare_duration(message: &[u8]) -> bool {
+    message
+        .strip_prefix(b"duration: ")
+        .and_then(|rest| rest.trim_ascii_end().strip_suffix(b" ms"))
+        .is_some_and(|number| number.iter().all(|b| b.is_ascii_digit() || *b == b'.'))
+}
+
+/// `log_statement` output: "statement: ..." or "execute <name>: ..."
+pub fn is_logged_statement(message: &[u8]) -> bool {
+    message.starts_with(b"statement: ") || message.starts_with(b"execute ")
+}
+
+/// Message of a statement record, up to the end of a multi-line plain record
+pub fn statement_message<'a>(record: &'a [u8], fmt: &Format) -> &'a [u8] {
+    let Some(message) = fmt.message_from_bytes(record) else {
+        return &[];
+    };
+    match fmt {
+        Format::Csv => message,
+        Format::Plain => {
+            let start = message.as_ptr() as usize - record.as_ptr() as usize;
+            record[start..].trim_ascii_end()
+        }
+    }
+}
+
+/// Appends the statement to the message of a bare duration record,
+/// as if it was logged by `log_min_duration_statement`
+fn attach_statement(record: &[u8], fmt: &Format, statement: &[u8]) -> Vec<u8> {
+    let Some(message) = fmt.message_from_bytes(record) else {
+        return record.to_vec();
+    };
+    // message is a sub-slice of record
+    let end = message.as_ptr() as usize - record.as_ptr() as usize + message.len();
+    let message_end = end - (message.len() - message.trim_ascii_end().len());
+
+    let mut result = Vec::with_capacity(record.len() + statement.len() + 2);
+    result.extend_from_slice(&record[..message_end]);
+    result.extend_from_slice(b"  ");
+    result.extend_from_slice(statement);
+    result.extend_from_slice(&record[end..]);
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_statement_phase() {
+        assert_eq!(
+            statement_phase(b"duration: 0.700 ms  bind S_1/C_2: SELECT $1"),
+            Some((Phase::Bind, b"SELECT $1".as_slice()))
+        );
+        assert_eq!(
+            statement_phase(b"duration: 3.032 ms  statement: SELECT 1"),
+            Some((Phase::Statement, b"SELECT 1".as_slice()))
+        );
+        assert_eq!(statement_phase(b"duration: 3.032 ms"), None);
+    }
+
+    #[test]
+    fn test_bind_parameters_plain() {
+        let record = b"2021-12-03 07:14:48.594 UTC [4913] user=user1,db=mydb DETAIL:  parameters: $1 = '1234', $2 = 'nj''char\n\thello', $3 = NULL\n";
+        assert_eq!(
+            bind_parameters(record, &Format::Plain),
+            Some(vec![
+                (1, "'1234'".to_string()),
+                (2, "'nj''char\nhello'".to_string()),
+                (3, "NULL".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_inline_record() {
+        let record = b"2021-12-03 07:14:48.594 UTC [4913] LOG:  duration: 0.215 ms  execute S_1: SELECT '$1' AS a, $1 AS b WHERE c = $10\n";
+        let params = vec![(1, "'x'".to_string()), (10, "NULL".to_string())];
+        assert_eq!(
+            String::from_utf8(inline_record(record, &Format::Plain, &params)).unwrap(),
+            "2021-12-03 07:14:48.594 UTC [4913] LOG:  duration: 0.215 ms  execute S_1: SELECT '$1' AS a, 'x' AS b WHERE c = NULL\n"
+        );
+    }
+
+    #[test]
+    fn test_tracker_pairs_across_chunks() {
+        let time = Local::now();
+        let mut first = StatementTracker::new(true);
+        let mut second = StatementTracker::new(true);
+        let mut emitted = Vec::new();
+
+        first.push(
+            b"2025-06-03 14:57:02.002 EEST [3714795] LOG:  execute P_1: SELECT $1\n",
+            &Format::Plain,
+            time,
+            |s| emitted.push(s.record.into_owned()),
+        );
+        second.push(
+            b"2025-06-03 14:57:02.002 EEST [3714795] DETAIL:  Parameters: $1 = '42'\n",
+            &Format::Plain,
+            time,
+            |s| emitted.push(s.record.into_owned()),
+        );
+        second.push(
+            b"2025-06-03 14:57:02.480 EEST [3714795] LOG:  duration: 478.213 ms\n",
+            &Format::Plain,
+            time,
+            |s| emitted.push(s.record.into_owned()),
+        );
+        assert!(emitted.is_empty());
+
+        let mut merged = StatementTracker::default();
+        merged.merge(&first, |s| emitted.push(s.record.into_owned()));
+        merged.merge(&second, |s| emitted.push(s.record.into_owned()));
+        merged.finish(|s| emitted.push(s.record.into_owned()));
+        assert_eq!(
+            emitted,
+            vec![
+                b"2025-06-03 14:57:02.480 EEST [3714795] LOG:  duration: 478.213 ms  execute P_1: SELECT '42'\n"
+                    .to_vec()
+            ]
+        );
+    }
+}
@@ -0,0 +1,554 @@
+//! Decoding of the log time at the start of each record (`%m`, `%t` or `%n` in
+//! `log_line_prefix`, the `log_time` column of CSV logs), without allocating. Consecutive
+//! records mostly share the second, so its conversion to local time, the expensive part,
+//! is cached per thread. The time can also be ISO-8601 with a `T` and an offset, as
+//! written by container log collectors, and can come after the pid or session id.
+//!
+//! Postgres names the zone of `log_timezone` by its abbreviation, like `EEST` or `PDT`,
+//! or by the offset (`+0530`) for zones without one. Abbreviations are looked up in a
+//! built-in table, `--log-tz` overrides ambiguous ones. `--tz` and `--utc` set the zone
+//! times are printed in, and zoneless `--begin` / `--end` times are read in.
+
+use std::{
+    cell::Cell,
+    str::FromStr,
+    sync::{Once, OnceLock},
+};
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, TimeDelta, TimeZone};
+use chrono_tz::Tz;
+use log::warn;
+
+/// `YYYY-MM-DD HH:MM:SS`
+const SECOND_LEN: usize = 19;
+/// Unix epoch seconds of `%n`, `1716384909`
+const EPOCH_LEN: usize = 10;
+/// Longest zone name read, `+05:30`
+const ZONE_LEN: usize = 6;
+/// Longest pid or session id before the time, `682db26c.535`
+const ID_LEN: usize = 24;
+
+/// Zone abbreviations and their UTC offset in minutes. Ambiguous ones mean what they
+/// mean in PostgreSQL's `Default` set of `timezone_abbreviations`.
+const ABBREVIATIONS: &[(&str, i32)] = &[
+    ("ACDT", 630),
+    ("ACST", 570),
+    ("ADT", -180),
+    ("AEDT", 660),
+    ("AEST", 600),
+    ("AKDT", -480),
+    ("AKST", -540),
+    ("AST", -240),
+    ("AWST", 480),
+    ("BST", 60),
+    ("CAT", 120),
+    ("CDT", -300),
+    ("CEST", 120),
+    ("CET", 60),
+    ("CST", -360),
+    ("EAT", 180),
+    ("EDT", -240),
+    ("EEST", 180),
+    ("EET", 120),
+    ("EST", -300),
+    ("GMT", 0),
+    ("HDT", -540),
+    ("HKT", 480),
+    ("HST", -600),
+    ("IDT", 180),
+    ("IST", 120),
+    ("JST", 540),
+    ("KST", 540),
+    ("MDT", -360),
+    ("MSK", 180),
+    ("MST", -420),
+    ("NDT", -150),
+    ("NST", -210),
+    ("NZDT", 780),
+    ("NZST", 720),
+    ("PDT", -420),
+    ("PKT", 300),
+    ("PST", -480),
+    ("SAST", 120),
+    ("SGT", 480),
+    ("UCT", 0),
+    ("UT", 0),
+    ("UTC", 0),
+    ("WAT", 60),
+    ("WEST", 60),
+    ("WET", 0),
+    ("WIB", 420),
+    ("WIT", 540),
+    ("WITA", 480),
+    ("Z", 0),
+];
+
+/// Abbreviations used by more than one zone, e.g. IST for Israel, India and Ireland
+const AMBIGUOUS: &[&str] = &["AST", "BST", "CST", "IST"];
+
+/// Zone of log times or of the output
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Zone {
+    /// An offset like `+05:30`, or an abbreviation
+    Fixed(FixedOffset),
+    /// An IANA name like `Europe/Helsinki`
+    Named(Tz),
+    Local,
+}
+
+impl FromStr for Zone {
+    type Err = String;
+
+    /// `+05:30`, `+0530`, `-03`, `UTC`, `EEST`, `Europe/Helsinki` or `local`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(offset) = fixed_offset(s.as_bytes()) {
+            return Ok(Zone::Fixed(offset));
+        }
+        if s.eq_ignore_ascii_case("local") {
+            return Ok(Zone::Local);
+        }
+        s.parse::<Tz>()
+            .map(Zone::Named)
+            .map_err(|_| format!("Unknown time zone: {s}"))
+    }
+}
+
+impl Zone {
+    /// Earliest time with the wall clock time, None in a DST gap
+    fn at_wall_clock(self, time: &NaiveDateTime) -> Option<DateTime<Local>> {
+        match self {
+            Zone::Fixed(offset) => offset.from_local_datetime(time).earliest(),
+            Zone::Named(tz) => tz
+                .from_local_datetime(time)
+                .earliest()
+                .map(|t| t.fixed_offset()),
+            Zone::Local => Local
+                .from_local_datetime(time)
+                .earliest()
+                .map(|t| t.fixed_offset()),
+        }
+        .map(|t| t.with_timezone(&Local))
+    }
+}
+
+/// Zone settings from the command line
+#[derive(Default)]
+pub struct Zones {
+    /// Abbreviations overridden with `--log-tz ABBR=ZONE`
+    pub abbreviations: Vec<(String, FixedOffset)>,
+    /// Zone of records without a known zone name, `--log-tz ZONE`
+    pub log: Option<Zone>,
+    /// Zone times are printed in and zoneless times on the command line are read in,
+    /// `--tz` or `--utc`, and the name printed after the rewritten record times
+    pub output: Option<(Zone, String)>,
+}
+
+static ZONES: OnceLock<Zones> = OnceLock::new();
+
+/// Sets the zone settings, before any time is parsed
+pub fn configure(zones: Zones) {
+    let _ = ZONES.set(zones);
+}
+
+fn zones() -> &'static Zones {
+    ZONES.get_or_init(Zones::default)
+}
+
+/// Parts of the timestamp of a record
+struct Timestamp<'a> {
+    /// Offset of the timestamp, after the pid or session id if they come first
+    start: usize,
+    /// `YYYY-MM-DD HH:MM:SS`, with a `T` between date and time in ISO-8601, or the
+    /// epoch seconds
+    second: &'a [u8],
+    /// `.123`, or empty
+    fraction: &'a [u8],
+    /// `EEST`, `+0530` or `Z`, or empty
+    zone: &'a [u8],
+    /// Offset of the first byte after the timestamp
+    end: usize,
+}
+
+impl<'a> Timestamp<'a> {
+    /// Timestamp at the start of a record, or after `[12345] ` or `682db26c.535 `
+    fn find(record: &'a [u8]) -> Option<Self> {
+        Self::at(record, 0).or_else(|| Self::at(record, id_len(record)?))
+    }
+
+    fn at(record: &'a [u8], start: usize) -> Option<Self> {
+        let rest = &record[start..];
+        let second_len = if is_date_time(rest) {
+            SECOND_LEN
+        } else if is_epoch(rest) {
+            EPOCH_LEN
+        } else {
+            return None;
+        };
+        let fraction_len = rest[second_len..].strip_prefix(b".").map_or(0, |digits| {
+            1 + digits.iter().take_while(|d| d.is_ascii_digit()).count()
+        });
+        let zone_start = second_len + fraction_len;
+        let (zone, zone_len) = match second_len {
+            EPOCH_LEN => (&[][..], 0),
+            _ => zone_name(&rest[zone_start..], rest[10] == b'T'),
+        };
+        Some(Timestamp {
+            start,
+            second: &rest[..second_len],
+            fraction: &rest[second_len..zone_start],
+            zone,
+            end: start + zone_start + zone_len,
+        })
+    }
+}
+
+/// Whether a line starts a record: a timestamp, maybe after the pid or session id, and
+/// then the rest of the log line prefix
+pub fn is_record_start(line: &[u8]) -> bool {
+    Timestamp::find(line).is_some_and(|timestamp| {
+        line.get(timestamp.end)
+            .is_some_and(|b| !b.is_ascii_alphanumeric())
+    })
+}
+
+thread_local! {
+    /// Second and zone name of the last record, with the decoded second
+    static LAST_SECOND: Cell<Option<([u8; SECOND_LEN + ZONE_LEN], DateTime<Local>)>> =
+        const { Cell::new(None) };
+}
+
+/// Log time of a record starting with `YYYY-MM-DD HH:MM:SS[.fff] [zone]`, the ISO-8601
+/// `YYYY-MM-DDTHH:MM:SS[.fff][zone]` or `1716384909.392`, maybe after the pid or session id
+pub fn log_time(record: &[u8]) -> Option<DateTime<Local>> {
+    let timestamp = Timestamp::find(record)?;
+    let mut key = [0; SECOND_LEN + ZONE_LEN];
+    key[..timestamp.second.len()].copy_from_slice(timestamp.second);
+    key[SECOND_LEN..SECOND_LEN + timestamp.zone.len()].copy_from_slice(timestamp.zone);
+    let at_second = match LAST_SECOND.get() {
+        Some((cached, at_second)) if cached == key => at_second,
+        _ => {
+            let at_second = decode_second(timestamp.second, zone(timestamp.zone, zones().log))?;
+            LAST_SECOND.set(Some((key, at_second)));
+            at_second
+        }
+    };
+    Some(at_second + fraction(timestamp.fraction))
+}
+
+/// A time given on the command line, like the log times. Without zone it's in the `--tz`
+/// zone.
+pub fn parse_time(input: &str) -> Option<DateTime<Local>> {
+    let timestamp = Timestamp::at(input.as_bytes(), 0)?;
+    if timestamp.end != input.len() {
+        return None;
+    }
+    let zone = zone(
+        timestamp.zone,
+        zones().output.as_ref().map(|(zone, _)| *zone),
+    );
+    Some(decode_second(timestamp.second, zone)? + fraction(timestamp.fraction))
+}
+
+/// A zoneless time given on the command line, in the `--tz` zone
+pub fn from_wall_clock(time: &NaiveDateTime) -> Option<DateTime<Local>> {
+    zones()
+        .output
+        .as_ref()
+        .map_or(Zone::Local, |(zone, _)| *zone)
+        .at_wall_clock(time)
+}
+
+/// `%Y-%m-%d %H:%M:%S` in the `--tz` zone, local time by default
+pub fn display(time: DateTime<Local>) -> String {
+    const FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+    match zones().output.as_ref().map(|(zone, _)| *zone) {
+        Some(Zone::Fixed(offset)) => time.with_timezone(&offset).format(FORMAT).to_string(),
+        Some(Zone::Named(tz)) => time.with_timezone(&tz).format(FORMAT).to_string(),
+        Some(Zone::Local) | None => time.format(FORMAT).to_string(),
+    }
+}
+
+/// Appends `record` to `out`, with the log time at its start rewritten to the `--tz` zone
+pub fn write_record(record: &[u8], out: &mut Vec<u8>) {
+    let rewrite = zones()
+        .output
+        .as_ref()
+        .and_then(|(zone, name)| Some((log_time(record)?, Timestamp::find(record)?, *zone, name)));
+    let Some((time, timestamp, zone, name)) = rewrite else {
+        out.extend_from_slice(record);
+        return;
+    };
+    // `%.3f` for `.123`
+    let format = match timestamp.fraction.len() {
+        0 => "%Y-%m-%d %H:%M:%S".to_string(),
+        len => format!("%Y-%m-%d %H:%M:%S%.{}f", (len - 1).min(9)),
+    };
+    let rewritten = match zone {
+        Zone::Fixed(offset) => format!("{} {name}", time.with_timezone(&offset).format(&format)),
+        Zone::Named(tz) => time
+            .with_timezone(&tz)
+            .format(&format!("{format} %Z"))
+            .to_string(),
+        Zone::Local => time.format(&format!("{format} %Z")).to_string(),
+    };
+    out.extend_from_slice(&record[..timestamp.start]);
+    out.extend_from_slice(rewritten.as_bytes());
+    out.extend_from_slice(&record[timestamp.end..]);
+}
+
+/// Zone name after a space, letters or an offset like `+0530`, and its length with the
+/// space. An ISO-8601 time can be followed by the offset or `Z` directly.
+fn zone_name(rest: &[u8], iso: bool) -> (&[u8], usize) {
+    let (rest, space) = match rest.strip_prefix(b" ") {
+        Some(rest) => (rest, 1),
+        None if iso && matches!(rest.first(), Some(b'Z' | b'+' | b'-')) => (rest, 0),
+        None => return (&[], 0),
+    };
+    let len = match rest.first() {
+        Some(b'+' | b'-') => {
+            1 + rest[1..]
+                .iter()
+                .take_while(|b| b.is_ascii_digit() || **b == b':')
+                .count()
+        }
+        _ => rest.iter().take_while(|b| b.is_ascii_alphabetic()).count(),
+    };
+    if len == 0 || len > ZONE_LEN {
+        return (&[], 0);
+    }
+    (&rest[..len], space + len)
+}
+
+/// `YYYY-MM-DD HH:MM:SS`, or `YYYY-MM-DDTHH:MM:SS`
+fn is_date_time(bytes: &[u8]) -> bool {
+    const LAYOUT: &[u8; SECOND_LEN] = b"0000-00-00 00:00:00";
+    bytes.len() >= SECOND_LEN
+        && LAYOUT.iter().zip(bytes).all(|(&l, &b)| match l {
+            b'0' => b.is_ascii_digit(),
+            b' ' => b == b' ' || b == b'T',
+            _ => b == l,
+        })
+}
+
+/// `%n`, epoch seconds with milliseconds like `1716384909.392`
+fn is_epoch(bytes: &[u8]) -> bool {
+    bytes.len() >= EPOCH_LEN + 4
+        && bytes[..EPOCH_LEN].iter().all(u8::is_ascii_digit)
+        && bytes[EPOCH_LEN] == b'.'
+        && bytes[EPOCH_LEN + 1..EPOCH_LEN + 4]
+            .iter()
+            .all(u8::is_ascii_digit)
+        && !bytes.get(EPOCH_LEN + 4).is_some_and(u8::is_ascii_digit)
+}
+
+/// Length of a pid or session id at the start of a line with its brackets and separator,
+/// like `[12345] `, `12345:` or `682db26c.535 `
+fn id_len(line: &[u8]) -> Option<usize> {
+    let bracketed = line.first() == Some(&b'[');
+    let start = usize::from(bracketed);
+    let len = line[start..]
+        .iter()
+        .take(ID_LEN + 1)
+        .take_while(|b| b.is_ascii_hexdigit() || **b == b'.')
+        .count();
+    let id = line.get(start..start + len).filter(|_| len <= ID_LEN)?;
+    // A pid, or a session id of two hex numbers
+    let is_pid = !id.is_empty() && id.iter().all(u8::is_ascii_digit);
+    let is_session = id.iter().filter(|&&b| b == b'.').count() == 1
+        && !id.starts_with(b".")
+        && !id.ends_with(b".");
+    if !is_pid && !is_session {
+        return None;
+    }
+    let mut end = start + len;
+    if bracketed {
+        line.get(end).filter(|&&b| b == b']')?;
+        end += 1;
+    }
+    let separators = line[end..]
+        .iter()
+        .take(2)
+        .take_while(|b| matches!(b, b' ' | b':' | b'-' | b'|'))
+        .count();
+    (separators > 0).then_some(end + separators)
+}
+
+/// Decodes the second of a timestamp that matched `is_date_time` or `is_epoch`
+fn decode_second(second: &[u8], zone: Zone) -> Option<DateTime<Local>> {
+    if second.len() == EPOCH_LEN {
+        let seconds = second
+            .iter()
+            .fold(0i64, |n, &d| n * 10 + i64::from(d - b'0'));
+        return DateTime::from_timestamp(seconds, 0).map(|t| t.with_timezone(&Local));
+    }
+    let number = |digits: &[u8]| {
+        digits.iter().try_fold(0u32, |n, &d| {
+            d.is_ascii_digit().then(|| n * 10 + u32::from(d - b'0'))
+        })
+    };
+    let date = NaiveDate::from_ymd_opt(
+        number(&second[0..4])?.try_into().ok()?,
+        number(&second[5..7])?,
+        number(&second[8..10])?,
+    )?;
+    let time = date.and_hms_opt(
+        number(&second[11..13])?,
+        number(&second[14..16])?,
+        number(&second[17..19])?,
+    )?;
+    zone.at_wall_clock(&time)
+}
+
+/// Zone by the zone name logged, `zoneless` or local time if there's none or it's unknown
+fn zone(name: &[u8], zoneless: Option<Zone>) -> Zone {
+    if name.is_empty() {
+        return zoneless.unwrap_or(Zone::Local);
+    }
+    if let Some((_, offset)) = zones()
+        .abbreviations
+        .iter()
+        .find(|(abbreviation, _)| abbreviation.as_bytes().eq_ignore_ascii_case(name))
+    {
+        return Zone::Fixed(*offset);
+    }
+    if let Some(offset) = fixed_offset(name) {
+        if AMBIGUOUS.iter().any(|a| a.as_bytes() == name) {
+            static WARNED: Once = Once::new();
+            WARNED.call_once(|| {
+                let name = String::from_utf8_lossy(name);
+                warn!("Time zone {name} is ambiguous, read as UTC{offset}. Override with e.g. --log-tz {name}=+05:30");
+            });
+        }
+        return Zone::Fixed(offset);
+    }
+    static WARNED: Once = Once::new();
+    WARNED.call_once(|| {
+        warn!(
+            "Unknown time zone {}, set it with e.g. --log-tz {0}=+05:30",
+            String::from_utf8_lossy(name)
+        );
+    });
+    zoneless.unwrap_or(Zone::Local)
+}
+
+/// Offset of an abbreviation from the table, or of `+05:30`, `+0530` or `-03`
+fn fixed_offset(name: &[u8]) -> Option<FixedOffset> {
+    if let Some((_, minutes)) = ABBREVIATIONS
+        .iter()
+        .find(|(abbreviation, _)| abbreviation.as_bytes().eq_ignore_ascii_case(name))
+    {
+        return FixedOffset::east_opt(minutes * 60);
+    }
+    let (sign, digits) = match name.split_first()? {
+        (b'+', digits) => (1, digits),
+        (b'-', digits) => (-1, digits),
+        _ => return None,
+    };
+    let digit = |d: &u8| d.is_ascii_digit().then(|| i32::from(d - b'0'));
+    let (hours, minutes) = match digits {
+        [h] => (digit(h)?, 0),
+        [h1, h2] => (digit(h1)? * 10 + digit(h2)?, 0),
+        [h1, h2, m1, m2] | [h1, h2, b':', m1, m2] => {
+            (digit(h1)? * 10 + digit(h2)?, digit(m1)? * 10 + digit(m2)?)
+        }
+        _ => return None,
+    };
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Fractional seconds, `.123` right after the seconds
+fn fraction(fraction: &[u8]) -> TimeDelta {
+    let Some(digits) = fraction.strip_prefix(b".") else {
+        return TimeDelta::zero();
+    };
+    let mut nanos = 0;
+    let mut scale = 100_000_000;
+    for &d in digits.iter().take(9) {
+        nanos += i64::from(d - b'0') * scale;
+        scale /= 10;
+    }
+    TimeDelta::nanoseconds(nanos)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Timelike;
+
+    use super::*;
+
+    fn utc(time: DateTime<Local>) -> String {
+        time.naive_utc().to_string()
+    }
+
+    #[test]
+    fn test_log_time() {
+        let time = log_time(b"2025-05-22 15:15:09.392 EEST [1] LOG:  x").unwrap();
+        assert_eq!(utc(time), "2025-05-22 12:15:09.392");
+        // Cached second, other fraction
+        let time = log_time(b"2025-05-22 15:15:09.5 EEST [1] LOG:  x").unwrap();
+        assert_eq!(time.nanosecond(), 500_000_000);
+        let time = log_time(b"2025-05-21 11:01:20 UTC-682db26c.535-LOG:  x").unwrap();
+        assert_eq!(utc(time), "2025-05-21 11:01:20");
+        let time = log_time(b"2021-05-27 17:00:00 UTC:10.1.91.65:user@my_db:[24743]:LOG:").unwrap();
+        assert_eq!(utc(time), "2021-05-27 17:00:00");
+        let time = log_time(b"2025-05-22 15:15:09.392 PDT,\"user\"").unwrap();
+        assert_eq!(utc(time), "2025-05-22 22:15:09.392");
+        let time = log_time(b"2025-05-22 15:15:09 +0530 [1] LOG:  x").unwrap();
+        assert_eq!(utc(time), "2025-05-22 09:45:09");
+        let time = log_time(b"2025-05-22 15:15:09 -03 [1] LOG:  x").unwrap();
+        assert_eq!(utc(time), "2025-05-22 18:15:09");
+
+        // `%n`
+        let time = log_time(b"1690547999.456 [97674] LOG:  x").unwrap();
+        assert_eq!(utc(time), "2023-07-28 12:39:59.456");
+        // ISO-8601 of container log collectors
+        let time = log_time(b"2025-05-22T15:15:09.392+03:00 stderr F x").unwrap();
+        assert_eq!(utc(time), "2025-05-22 12:15:09.392");
+        let time = log_time(b"2025-05-22T12:15:09.392123456Z stderr F x").unwrap();
+        assert_eq!(utc(time), "2025-05-22 12:15:09.392123456");
+        let time = log_time(b"2025-05-22T15:15:09 EEST [1] LOG:  x").unwrap();
+        assert_eq!(utc(time), "2025-05-22 12:15:09");
+        // Pid or session id first
+        let time = log_time(b"[97674] 2025-05-22 15:15:09.392 EEST LOG:  x").unwrap();
+        assert_eq!(utc(time), "2025-05-22 12:15:09.392");
+        let time = log_time(b"682db26c.535 1690547999.456 LOG:  x").unwrap();
+        assert_eq!(utc(time), "2023-07-28 12:39:59.456");
+
+        assert_eq!(log_time(b"2025-13-22 15:15:09 EEST"), None);
+        assert_eq!(log_time(b"2025-05-22 15:15"), None);
+        assert_eq!(log_time(b"169054799.456 [97674] LOG:  x"), None);
+        assert_eq!(log_time(b"x 2025-05-22 15:15:09 EEST"), None);
+    }
+
+    #[test]
+    fn test_record_start() {
+        assert!(is_record_start(b"2025-05-22 15:15:09.392 EEST [1] LOG:  x"));
+        assert!(is_record_start(b"1690547999.456 [97674] LOG:  x"));
+        assert!(is_record_start(b"2025-05-22T12:15:09.392Z stderr F x"));
+        assert!(is_record_start(
+            b"[97674]: 2025-05-22 15:15:09 EEST LOG:  x"
+        ));
+        assert!(is_record_start(
+            b"682db26c.535 2025-05-22 15:15:09 EEST LOG:  x"
+        ));
+
+        assert!(!is_record_start(b"\tAND created > '2025-05-22 15:15:09'"));
+        assert!(!is_record_start(b"SELECT 2025-05-22 15:15:09 EEST"));
+        assert!(!is_record_start(b"1690547999.4567 rows"));
+        assert!(!is_record_start(b"2025-05-22 15:15:09"));
+    }
+
+    #[test]
+    fn test_zone() {
+        let hours = |h: i32| Zone::Fixed(FixedOffset::east_opt(h * 3600).unwrap());
+        assert_eq!("+03".parse(), Ok(hours(3)));
+        assert_eq!("-05:00".parse(), Ok(hours(-5)));
+        assert_eq!("cest".parse(), Ok(hours(2)));
+        assert_eq!(
+            "Europe/Helsinki".parse(),
+            Ok(Zone::Named(Tz::Europe__Helsinki))
+        );
+        assert!("Mars/Olympus".parse::<Zone>().is_err());
+        assert!("+3:00".parse::<Zone>().is_err());
+    }
+}
@@ -1,4 +1,6 @@
-use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+
+use crate::timestamp;
 use regex::Regex;
 use std::error::Error;
 use std::fmt;
@@ -26,7 +28,7 @@ impl Error for TimeParseError {}
 /// Supports:
 /// - Time intervals: "10min", "2h", "30s", "1d" (relative to ``reference_time``)
 /// - Special keywords: "today"
-/// - ISO timestamps: "2025-09-19 15:30:00", "2025-09-19T15:30:00Z"
+/// - ISO timestamps: "2025-09-19 15:30:00", "2025-09-19T15:30:00Z", "2025-09-19 15:30"
 /// - Date only: "2025-09-19" (uses local timezone)
 /// - 2006-01-02 15:04:05.000 MST
 /// - 2006-01-02 15:04:05 MST
@@ -141,11 +143,13 @@ fn parse_timestamp(
         "%Y-%m-%d %H:%M:%S",
         "%Y-%m-%dT%H:%M:%S%.3f",
         "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M",
     ];
 
     for format in &naive_formats {
         if let Ok(naive_dt) = chrono::NaiveDateTime::parse_from_str(input, format)
-            && let Some(local_dt) = Local.from_local_datetime(&naive_dt).single()
+            && let Some(local_dt) = timestamp::from_wall_clock(&naive_dt)
         {
             return Ok(local_dt);
         }
@@ -156,9 +160,7 @@ fn parse_timestamp(
         && input.chars().nth(4) == Some('-')
         && input.chars().nth(7) == Some('-')
         && let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d")
-        && let Some(datetime) = Local
-            .from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
-            .single()
+        && let Some(datetime) = timestamp::from_wall_clock(&date.and_hms_opt(0, 0, 0).unwrap())
     {
         return Ok(datetime);
     }
@@ -168,35 +170,11 @@ fn parse_timestamp(
     )))
 }
 
+/// Timestamp as logged with `log_line_prefix` `%m` (`2025-08-24 00:05:48.870 CEST`), `%t`
+/// (without milliseconds) or `%n` (`1756000000.870`), or ISO-8601 (`2025-08-24T00:05:48Z`).
 pub fn parse_timestamp_from_string(input: &str) -> Result<DateTime<Local>, String> {
     let input = input.trim();
-
-    // log_line_prefix formats to try
-    // %t	Time stamp without milliseconds 2006-01-02 15:04:05.000 MST
-    // %m	Time stamp with milliseconds 2006-01-02 15:04:05 MST
-    // %n	Time stamp with milliseconds (as a Unix epoch)  TODO
-    let formats = [
-        "%Y-%m-%d %H:%M:%S%.3f %Z", // 2025-08-24 00:05:48.870 CEST
-        "%Y-%m-%d %H:%M:%S% %Z",
-    ];
-
-    // Try parsing with timezone first
-    for format in &formats {
-        if let Ok(dt) = DateTime::parse_from_str(input, format) {
-            return Ok(dt.with_timezone(&Local));
-        }
-    }
-
-    // Try parsing as naive datetime and convert to local
-    for format in &formats {
-        if let Ok(naive_dt) = NaiveDateTime::parse_from_str(input, format)
-            && let Some(local_dt) = Local.from_local_datetime(&naive_dt).single()
-        {
-            return Ok(local_dt);
-        }
-    }
-
-    Err(format!("Unable to parse timestamp: '{input}'"))
+    timestamp::parse_time(input).ok_or_else(|| format!("Unable to parse timestamp: '{input}'"))
 }
 
 #[cfg(test)]
@@ -0,0 +1,27 @@
+use assert_cmd::cargo;
+use assert_cmd::prelude::*;
+use predicates::prelude::PredicateBooleanExt;
+use std::process::Command;
+
+#[test]
+fn archive_failures() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args(["--utc", "archive", "./tests/files/archive_failures.log"])
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::contains("Archive command failures: 4")
+                .and(predicates::str::contains(
+                    "[2025-06-10 10:05:01] - [2025-06-10 10:05:03]      3 failures  000000010000000000000002",
+                ))
+                .and(predicates::str::contains(
+                    "3  test ! -f /mnt/archive/%f && cp pg_wal/%f /mnt/archive/%f",
+                ))
+                .and(predicates::str::contains(
+                    "last:  [2025-06-10 10:20:00] 000000010000000000000002",
+                )),
+        );
+
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use assert_cmd::cargo;
+use assert_cmd::prelude::*;
+use predicates::prelude::PredicateBooleanExt;
+use std::process::Command;
+
+#[test]
+fn audit_filter_by_class() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args(["audit", "--class", "DDL,ROLE", "./tests/files/pgaudit.log"])
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::contains("alter table accounts add column note text")
+                .and(predicates::str::contains("create role carol login"))
+                .and(predicates::str::contains("select * from accounts").not()),
+        );
+
+    Ok(())
+}
+
+#[test]
+fn audit_filter_by_object_and_user() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args([
+        "audit",
+        "--object",
+        "public.accounts",
+        "--user",
+        "bob",
+        "./tests/files/pgaudit.log",
+    ])
+    .assert()
+    .success()
+    .stdout(
+        predicates::str::contains("alter table accounts")
+            .and(predicates::str::contains("select count(*) from orders").not()),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn audit_summary() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args(["audit", "summary", "./tests/files/pgaudit.log"])
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::contains("Total audit records: 5")
+                .and(predicates::str::contains("3  TABLE public.accounts"))
+                .and(predicates::str::contains("2  READ")),
+        );
+
+    Ok(())
+}
@@ -0,0 +1,27 @@
+use assert_cmd::cargo;
+use assert_cmd::prelude::*;
+use predicates::prelude::PredicateBooleanExt;
+use std::process::Command;
+
+#[test]
+fn config_history() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args(["--utc", "config", "./tests/files/config_changes.log"])
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::contains("Configuration reloads: 3")
+                .and(predicates::str::contains(
+                    "[2025-07-01 09:10:05] changed to \"64MB\" (ALTER SYSTEM by admin)",
+                ))
+                .and(predicates::str::contains(
+                    "[2025-07-01 09:20:00] change pending, requires restart",
+                ))
+                .and(predicates::str::contains(
+                    "/etc/postgresql/17/main/postgresql.conf: unaffected changes were applied",
+                )),
+        );
+
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use assert_cmd::cargo;
+use assert_cmd::prelude::*;
+use predicates::prelude::PredicateBooleanExt;
+use std::process::Command;
+
+#[test]
+fn encoding_mixed_utf8_by_default() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args(["errors", "top", "./tests/files/mixed_encoding.log"])
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::contains("     2  division by zero")
+                .and(predicates::str::contains(
+                    "     1  relation \"größe\" does not exist",
+                ))
+                .and(predicates::str::contains(
+                    "     1  relation \"gr\u{fffd}\u{fffd}e\" does not exist",
+                )),
+        );
+
+    // Records are printed as they are
+    let output = Command::new(cargo::cargo_bin!("pgweasel"))
+        .args(["errors", "./tests/files/mixed_encoding.log"])
+        .output()?;
+    assert!(output.status.success());
+    let latin1 = b"postgres@kasse ERROR:  relation \"gr\xf6\xdfe\" does not exist";
+    assert!(output.stdout.windows(latin1.len()).any(|w| w == latin1));
+
+    Ok(())
+}
+
+#[test]
+fn encoding_latin1_and_win1252() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args([
+        "--encoding",
+        "latin1",
+        "errors",
+        "top",
+        "./tests/files/mixed_encoding.log",
+    ])
+    .assert()
+    .success()
+    .stdout(predicates::str::contains(
+        "     2  relation \"größe\" does not exist",
+    ));
+
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args([
+        "--encoding",
+        "WIN1252",
+        "errors",
+        "./tests/files/mixed_encoding.log",
+    ])
+    .assert()
+    .success()
+    .stdout(
+        predicates::str::contains("syntax error at or near “select”")
+            .and(predicates::str::contains(
+                "postgres@kasse ERROR:  relation \"größe\" does not exist",
+            ))
+            .and(predicates::str::contains(
+                "postgres@shop ERROR:  relation \"größe\" does not exist",
+            )),
+    );
+
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args([
+        "--encoding",
+        "ebcdic",
+        "errors",
+        "./tests/files/mixed_encoding.log",
+    ])
+    .assert()
+    .failure();
+
+    Ok(())
+}
+
+#[test]
+fn encoding_crlf_line_ends() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args([
+        "--encoding",
+        "latin1",
+        "errors",
+        "top",
+        "./tests/files/crlf_encoding.csv",
+    ])
+    .assert()
+    .success()
+    .stdout(
+        predicates::str::contains("     2  division by zero\n")
+            .and(predicates::str::contains("größe"))
+            .and(predicates::str::contains("\r").not()),
+    );
+
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args([
+        "--encoding",
+        "latin1",
+        "errors",
+        "./tests/files/mixed_encoding.log",
+    ])
+    .assert()
+    .success()
+    .stdout(
+        predicates::str::contains("\"'caf\n\t\tselect 1\"\n")
+            .and(predicates::str::contains("\r").not()),
+    );
+
+    Ok(())
+}
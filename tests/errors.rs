@@ -174,6 +174,8 @@ fn simple_filter_with_hist_subcommand() -> Result<(), Box<dyn std::error::Error>
     let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
 
     cmd.args([
+        "--tz",
+        "EEST",
         "err",
         "hist",
         "-b",
@@ -202,3 +204,288 @@ fn non_existing_file() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn errors_from_stdin() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args(["err", "top", "-"])
+        .write_stdin(std::fs::read("./tests/files/debian_default2.log")?)
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "8  new row for relation \"pgbench_accounts\" violates check constraint \"posbal\"",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn errors_top_multiple_files() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args([
+        "err",
+        "top",
+        "./tests/files/debian_default2.log",
+        "./tests/files/debian_default2.log",
+    ])
+    .assert()
+    .success()
+    .stdout(predicates::str::contains(
+        "16  new row for relation \"pgbench_accounts\" violates check constraint \"posbal\"",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn errors_directory_with_subdirectory() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    std::fs::copy(
+        "./tests/files/debian_default2.log",
+        dir.path().join("postgresql.log"),
+    )?;
+    // Subdirectories aren't read
+    std::fs::create_dir(dir.path().join("archive"))?;
+    std::fs::copy(
+        "./tests/files/queryid.log",
+        dir.path().join("archive").join("postgresql.log"),
+    )?;
+
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args(["errors", "top"])
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::contains(
+                "     8  new row for relation \"pgbench_accounts\" violates check constraint \"posbal\"",
+            )
+            .and(predicates::str::contains("LOCK TABLE can only be used in transaction blocks").not()),
+        );
+
+    Ok(())
+}
+
+#[test]
+fn errors_from_nested_archive() -> Result<(), Box<dyn std::error::Error>> {
+    let csv = zstd::encode_all(&std::fs::read("./tests/files/csvlog1.csv")?[..], 0)?;
+    let mut tar = tar::Builder::new(Vec::new());
+    let mut header = tar::Header::new_gnu();
+    header.set_size(csv.len() as u64);
+    header.set_cksum();
+    tar.append_data(&mut header, "pg/csvlog1.csv.zst", &csv[..])?;
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+    gz.write_all(&tar.into_inner()?)?;
+
+    // Detected by content, not by the extension
+    let mut bundle = Builder::new().suffix(".bundle").tempfile()?;
+    bundle.write_all(&gz.finish()?)?;
+
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+    cmd.args(["err", bundle.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("2025-05-08 12:24:37.731 EEST"));
+
+    Ok(())
+}
+
+#[test]
+fn csv_detected_by_content() -> Result<(), Box<dyn std::error::Error>> {
+    let mut log = Builder::new().suffix(".log").tempfile()?;
+    log.write_all(&std::fs::read("./tests/files/csvlog1.csv")?)?;
+
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+    cmd.args(["err", "top", log.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("violates foreign key constraint"));
+
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+    cmd.args(["--format", "plain", "err", log.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicates::str::is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn errors_same_output_for_any_jobs() -> Result<(), Box<dyn std::error::Error>> {
+    let output = |jobs: &str| -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        Ok(Command::new(cargo::cargo_bin!("pgweasel"))
+            .args([
+                "--jobs",
+                jobs,
+                "errors",
+                "./tests/files/debian_default2.log",
+                "./tests/files/csvlog1.csv",
+                "./tests/files/debian_default2.log",
+            ])
+            .output()?
+            .stdout)
+    };
+    let single = output("1")?;
+    assert!(!single.is_empty());
+    assert_eq!(output("3")?, single);
+    assert_eq!(output("8")?, single);
+
+    Ok(())
+}
+
+#[test]
+fn errors_epoch_and_pid_prefixes() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    // `%n [%p] `
+    cmd.args([
+        "--utc",
+        "err",
+        "./testdata/pg-archives/hackers_1918_sub_100_0_2_1.log.gz",
+    ])
+    .assert()
+    .success()
+    .stdout(predicates::str::contains(
+        "2023-07-28 12:40:10.576 UTC [97766] ERROR:  could not create replication slot",
+    ));
+
+    let mut tmp = Builder::new().suffix(".log").tempfile()?;
+    write!(
+        tmp,
+        "[123] 2025-05-22T15:15:09.392+03:00 ERROR:  boom\n\tmore\n[124] 2025-05-22T15:16:09.392+03:00 LOG:  fine\n"
+    )?;
+    tmp.flush()?;
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+    cmd.args(["--utc", "err"])
+        .arg(tmp.path())
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::contains("[123] 2025-05-22 12:15:09.392 UTC ERROR:  boom\n\tmore")
+                .and(predicates::str::contains("fine").not()),
+        );
+
+    Ok(())
+}
+
+#[test]
+fn errors_relative_to_log_end() -> Result<(), Box<dyn std::error::Error>> {
+    // The last record is logged at 15:19:38.520 EEST
+    for args in [
+        ["--begin", "last:4m"].as_slice(),
+        ["--anchor", "log-end", "--begin", "4m"].as_slice(),
+    ] {
+        let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+        cmd.args(args)
+            .args(["err", "./tests/files/debian_default2.log"])
+            .assert()
+            .success()
+            .stdout(
+                predicates::str::contains("2025-05-22 15:15:51.119")
+                    .and(predicates::str::contains("2025-05-22 15:15:09.392").not()),
+            );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn errors_window_and_around() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+    cmd.args([
+        "--tz",
+        "EEST",
+        "--window",
+        "2025-05-22 15:15:30 +1m",
+        "err",
+        "./tests/files/debian_default2.log",
+    ])
+    .assert()
+    .success()
+    .stdout(
+        predicates::str::contains("2025-05-22 15:15:51.119")
+            .and(predicates::str::contains("2025-05-22 15:15:09.392").not()),
+    );
+
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+    cmd.args([
+        "--tz",
+        "EEST",
+        "--window",
+        "2025-05-22 15:15:30 -1m",
+        "err",
+        "./tests/files/debian_default2.log",
+    ])
+    .assert()
+    .success()
+    .stdout(
+        predicates::str::contains("2025-05-22 15:15:09.392")
+            .and(predicates::str::contains("2025-05-22 15:15:51.119").not()),
+    );
+
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+    cmd.args([
+        "--tz",
+        "EEST",
+        "--around",
+        "2025-05-22 15:15:10 ±5s",
+        "err",
+        "./tests/files/debian_default2.log",
+    ])
+    .assert()
+    .success()
+    .stdout(
+        predicates::str::contains("2025-05-22 15:15:09.392")
+            .and(predicates::str::contains("2025-05-22 15:15:51.119").not()),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn errors_malformed_records() -> Result<(), Box<dyn std::error::Error>> {
+    let mut log = Builder::new().suffix(".log").tempfile()?;
+    write!(
+        log,
+        "2025-05-22 10:00:00.123 UTC [1] ERROR:  one\n2025-13-45 10:00:00.123 UTC [1] ERROR:  bad date\n2025-05-22 10:00:02.123 UTC [1] ERROR:  two\n"
+    )?;
+    log.flush()?;
+
+    // Skipped and reported with the byte offset
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+    cmd.arg("err")
+        .arg(log.path())
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("one").and(predicates::str::contains("two")))
+        .stderr(
+            predicates::str::contains("Malformed records skipped: 1").and(
+                predicates::str::contains(format!("{}:44:", log.path().display())),
+            ),
+        );
+
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+    cmd.args(["--strict", "err"])
+        .arg(log.path())
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("MalformedRecord"));
+
+    let dump = tempfile::NamedTempFile::new()?;
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+    cmd.arg("--dump-bad")
+        .arg(dump.path())
+        .arg("err")
+        .arg(log.path())
+        .assert()
+        .success();
+    assert_eq!(
+        std::fs::read_to_string(dump.path())?,
+        "2025-13-45 10:00:00.123 UTC [1] ERROR:  bad date\n"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use assert_cmd::cargo;
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    process::{Command, Stdio},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+#[test]
+fn follow_appended_and_rotated_records() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let log = dir.path().join("postgresql.log");
+    let lines: Vec<&str> = include_str!("files/debian_default2.log")
+        .split_inclusive('\n')
+        .collect();
+    fs::write(&log, lines[..20].concat())?;
+
+    let mut child = Command::new(cargo::cargo_bin!("pgweasel"))
+        .args(["--follow", "errors"])
+        .arg(&log)
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let stdout = child.stdout.take().ok_or("no stdout")?;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    let next_error = || -> Option<String> {
+        loop {
+            let line = rx.recv_timeout(Duration::from_secs(10)).ok()?;
+            if !line.is_empty() {
+                return Some(line);
+            }
+        }
+    };
+
+    let first = next_error();
+
+    fs::OpenOptions::new()
+        .append(true)
+        .open(&log)?
+        .write_all(lines[20..24].concat().as_bytes())?;
+    let appended = next_error();
+
+    // Rotation to a new file
+    fs::rename(&log, dir.path().join("postgresql.log.1"))?;
+    fs::write(&log, lines[24..40].concat())?;
+    let rotated = next_error();
+
+    child.kill()?;
+    child.wait()?;
+
+    assert!(first.is_some_and(|l| l.starts_with("2025-05-22 15:15:09.392 EEST [3239131]")));
+    assert!(appended.is_some_and(|l| l.starts_with("2025-05-22 15:15:51.119 EEST [3239914]")));
+    assert!(rotated.is_some_and(|l| l.starts_with("2025-05-22 15:15:52.039 EEST [3239935]")));
+
+    Ok(())
+}
+
+#[test]
+fn follow_parameters_of_next_poll_and_new_files() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let prefix = "2021-12-03 07:14:48.594 UTC [4913] user=user1,db=mydb";
+    fs::write(
+        dir.path().join("postgresql.log"),
+        format!("{prefix} LOG:  duration: 5.215 ms  execute q: SELECT $1\n"),
+    )?;
+
+    // A bare file name, new files show up in the current directory
+    let mut child = Command::new(cargo::cargo_bin!("pgweasel"))
+        .current_dir(dir.path())
+        .args(["--follow", "slow", "--params", "1ms", "postgresql.log"])
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let stdout = child.stdout.take().ok_or("no stdout")?;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    let next_record = || -> Option<String> {
+        loop {
+            let line = rx.recv_timeout(Duration::from_secs(10)).ok()?;
+            if !line.is_empty() {
+                return Some(line);
+            }
+        }
+    };
+
+    // Parameters logged after a few polls
+    thread::sleep(Duration::from_secs(1));
+    fs::OpenOptions::new()
+        .append(true)
+        .open(dir.path().join("postgresql.log"))?
+        .write_all(
+            format!("{prefix} DETAIL:  parameters: $1 = '42'\n{prefix} LOG:  statement: BEGIN\n")
+                .as_bytes(),
+        )?;
+    let completed = next_record();
+
+    fs::write(
+        dir.path().join("postgresql-2.log"),
+        format!(
+            "{prefix} LOG:  duration: 7.000 ms  statement: SELECT 2\n{prefix} LOG:  statement: BEGIN\n"
+        ),
+    )?;
+    let new_file = next_record();
+
+    child.kill()?;
+    child.wait()?;
+
+    assert!(completed.is_some_and(|l| l.ends_with("execute q: SELECT '42'")));
+    assert!(new_file.is_some_and(|l| l.ends_with("duration: 7.000 ms  statement: SELECT 2")));
+
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use assert_cmd::cargo;
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+fn output(args: &[&str]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let output = Command::new(cargo::cargo_bin!("pgweasel"))
+        .args(args)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    Ok(output)
+}
+
+#[test]
+fn index_gives_same_results() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let log = dir.path().join("postgresql.log");
+    std::fs::copy("./tests/files/debian_default2.log", &log)?;
+    let log = log.to_str().unwrap();
+
+    let errors = output(&["errors", log])?;
+    let top = output(&["errors", "top", log])?;
+    let since = output(&["--begin", "2025-05-22 15:18:00 EEST", "errors", log])?;
+
+    Command::new(cargo::cargo_bin!("pgweasel"))
+        .args(["index", log])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Indexed"));
+    assert!(dir.path().join("postgresql.log.pgwidx").exists());
+
+    assert_eq!(output(&["errors", log])?, errors);
+    // Ties of the top list are in no particular order
+    let sorted = |output: Vec<u8>| {
+        let mut lines: Vec<_> = output.split(|&b| b == b'\n').map(<[u8]>::to_vec).collect();
+        lines.sort();
+        lines
+    };
+    assert_eq!(sorted(output(&["errors", "top", log])?), sorted(top));
+    assert_eq!(
+        output(&["--begin", "2025-05-22 15:18:00 EEST", "errors", log])?,
+        since
+    );
+
+    Ok(())
+}
+
+#[test]
+fn index_skips_by_database_and_sql_state() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let log = dir.path().join("postgresql.csv");
+    std::fs::copy("./tests/files/csvlog1.csv", &log)?;
+    let log = log.to_str().unwrap();
+
+    let code = output(&["errors", "--code", "23503", log])?;
+    let db = output(&["--db", "postgres", "errors", log])?;
+    assert!(String::from_utf8(code.clone())?.contains("violates foreign key constraint"));
+    assert_eq!(code, db);
+
+    Command::new(cargo::cargo_bin!("pgweasel"))
+        .args(["index", log])
+        .assert()
+        .success();
+
+    assert_eq!(output(&["errors", "--code", "23503", log])?, code);
+    assert_eq!(output(&["--db", "postgres", "errors", log])?, db);
+    // Only the record left out of the index is read
+    for args in [
+        ["--debug", "errors", "--code", "40P01"],
+        ["--debug", "--db", "other", "errors"],
+    ] {
+        Command::new(cargo::cargo_bin!("pgweasel"))
+            .args(args)
+            .arg(log)
+            .assert()
+            .success()
+            .stdout(predicates::str::is_empty())
+            .stderr(predicates::str::contains(
+                "Index of 1 blocks leaves 379 of 5462 bytes",
+            ));
+    }
+
+    Ok(())
+}
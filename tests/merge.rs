@@ -0,0 +1,128 @@
+use assert_cmd::cargo;
+use assert_cmd::prelude::*;
+use predicates::prelude::PredicateBooleanExt;
+use std::process::Command;
+
+#[test]
+fn merge_saved_error_states() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let host1 = dir.path().join("host1.state");
+    let host2 = dir.path().join("host2.state");
+
+    Command::new(cargo::cargo_bin!("pgweasel"))
+        .arg("--save-state")
+        .arg(&host1)
+        .args(["errors", "top", "./tests/files/debian_default2.log"])
+        .assert()
+        .success()
+        .stdout(predicates::str::is_empty());
+    Command::new(cargo::cargo_bin!("pgweasel"))
+        .arg("--save-state")
+        .arg(&host2)
+        .args(["errors", "top", "./tests/files/queryid.log"])
+        .assert()
+        .success();
+
+    Command::new(cargo::cargo_bin!("pgweasel"))
+        .arg("merge")
+        .arg(&host1)
+        .arg(&host2)
+        .arg(&host1)
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::contains(
+                "    16  new row for relation \"pgbench_accounts\" violates check constraint \"posbal\"",
+            )
+            .and(predicates::str::contains(
+                "     3  canceling statement due to user request",
+            ))
+            .and(predicates::str::contains(
+                "     1  LOCK TABLE can only be used in transaction blocks",
+            )),
+        );
+
+    Ok(())
+}
+
+#[test]
+fn merge_slow_top_states() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let state = dir.path().join("slow.state");
+
+    Command::new(cargo::cargo_bin!("pgweasel"))
+        .arg("--save-state")
+        .arg(&state)
+        .args(["slow", "top", "./tests/files/log_duration.log"])
+        .assert()
+        .success();
+
+    Command::new(cargo::cargo_bin!("pgweasel"))
+        .arg("merge")
+        .arg(&state)
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::contains("Top 6 slowest queries:").and(predicates::str::contains(
+                "--- 478.213ms ---\n2025-06-03 14:57:02.480 EEST [3714795] LOG:  duration: 478.213 ms  execute P_1: UPDATE pgbench_accounts SET abalance = abalance + $1 WHERE aid = $2;",
+            )),
+        );
+
+    Ok(())
+}
+
+#[test]
+fn merge_slow_threshold_states() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let state = dir.path().join("slow.state");
+
+    // Records are kept in the state instead of printed
+    Command::new(cargo::cargo_bin!("pgweasel"))
+        .arg("--save-state")
+        .arg(&state)
+        .args(["slow", "0s", "./tests/files/duration.log"])
+        .assert()
+        .success()
+        .stdout(predicates::str::is_empty());
+
+    Command::new(cargo::cargo_bin!("pgweasel"))
+        .arg("merge")
+        .arg(&state)
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "2025-05-21 11:00:40.291 UTC [675]: [1-1] db=postgres,user=cloudsqladmin,host=127.0.0.1 LOG:  duration: 4.053 ms  statement: SELECT",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn merge_states_of_different_commands() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let errors = dir.path().join("errors.state");
+    let workload = dir.path().join("workload.state");
+
+    Command::new(cargo::cargo_bin!("pgweasel"))
+        .arg("--save-state")
+        .arg(&errors)
+        .args(["errors", "top", "./tests/files/debian_default2.log"])
+        .assert()
+        .success();
+    Command::new(cargo::cargo_bin!("pgweasel"))
+        .arg("--save-state")
+        .arg(&workload)
+        .args(["workload", "./tests/files/stmt_type.log"])
+        .assert()
+        .success();
+
+    Command::new(cargo::cargo_bin!("pgweasel"))
+        .arg("merge")
+        .arg(&errors)
+        .arg(&workload)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("StateKindMismatch"));
+
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use assert_cmd::cargo;
+use assert_cmd::prelude::*;
+use predicates::prelude::PredicateBooleanExt;
+use std::process::Command;
+
+#[test]
+fn plans_summary() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args(["plans", "./tests/files/auto_explain.log"])
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::contains("Plans for top 2 queries by total duration:")
+                .and(predicates::str::contains(
+                    "=== select * from orders where customer_id = 42",
+                ))
+                .and(
+                    predicates::str::contains("executions: 3")
+                        .and(predicates::str::contains("plans: 2  (plan flip)")),
+                )
+                .and(predicates::str::contains(
+                    "1000000  Seq Scan on public.orders",
+                ))
+                .and(predicates::str::contains(
+                    "Index Scan using orders_customer_id_idx on public.orders",
+                ))
+                .and(predicates::str::contains("10 -> 150000       Nested Loop")),
+        );
+
+    Ok(())
+}
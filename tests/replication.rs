@@ -0,0 +1,33 @@
+use assert_cmd::cargo;
+use assert_cmd::prelude::*;
+use predicates::prelude::PredicateBooleanExt;
+use std::process::Command;
+
+#[test]
+fn replication_summary() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args([
+        "--tz",
+        "AEDT",
+        "replication",
+        "./tests/files/replication_standby.log",
+    ])
+    .assert()
+    .success()
+    .stdout(
+        predicates::str::contains("[2023-11-06 19:02:52] 1 -> 2")
+            .and(predicates::str::contains("Peer 127.0.0.1:5433:"))
+            .and(predicates::str::contains("1  bufferpin"))
+            .and(predicates::str::contains("1  snapshot"))
+            .and(predicates::str::contains("000000010000000000000005"))
+            .and(predicates::str::contains(
+                "1  max_slot_wal_keep_size exceeded",
+            ))
+            .and(predicates::str::contains(
+                "1  could not start WAL streaming: ERROR:  replication slot \"sub\" does not exist",
+            )),
+    );
+
+    Ok(())
+}
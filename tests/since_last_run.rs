@@ -0,0 +1,48 @@
+use assert_cmd::cargo;
+use assert_cmd::prelude::*;
+use predicates::prelude::PredicateBooleanExt;
+use std::{fs, io::Write, process::Command};
+
+#[test]
+fn since_last_run_processes_appended_records() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let log = dir.path().join("postgresql.log");
+    let state = dir.path().join("bookmarks.state");
+    let lines: Vec<&str> = include_str!("files/debian_default2.log")
+        .split_inclusive('\n')
+        .collect();
+
+    fs::write(&log, lines[..20].concat())?;
+    let run = || {
+        let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+        cmd.arg("--since-last-run")
+            .arg(&state)
+            .arg("errors")
+            .arg(&log);
+        cmd
+    };
+
+    run().assert().success().stdout(predicates::str::contains(
+        "2025-05-22 15:15:09.392 EEST [3239131] krl@postgres ERROR:",
+    ));
+
+    // Nothing new
+    run().assert().success().stdout(predicates::str::is_empty());
+
+    fs::OpenOptions::new()
+        .append(true)
+        .open(&log)?
+        .write_all(lines[20..25].concat().as_bytes())?;
+    run().assert().success().stdout(
+        predicates::str::contains("2025-05-22 15:15:51.119 EEST [3239914] krl@postgres ERROR:")
+            .and(predicates::str::contains("15:15:09.392").not()),
+    );
+
+    // Truncated, e.g. by copytruncate log rotation
+    fs::write(&log, lines[..20].concat())?;
+    run().assert().success().stdout(predicates::str::contains(
+        "2025-05-22 15:15:09.392 EEST [3239131] krl@postgres ERROR:",
+    ));
+
+    Ok(())
+}
@@ -56,3 +56,146 @@ fn aggregate_top_slow_with_filter() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn slow_with_inlined_params() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args(["slow", "--params", "0.2ms", "./tests/files/multiline_param.log"])
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::contains(
+                "duration: 0.215 ms  execute njTypeOidQuery_Name: SELECT t.oid FROM pg_catalog.pg_type t, pg_catalog.pg_namespace n WHERE (n.nspname = 'njcat') AND (t.typnamespace = '1234') AND (t.typname = 'njchar\nhello') AND t.test = 'aaaa'",
+            )
+            .and(predicates::str::contains("duration: 0.131 ms").not())
+            .and(predicates::str::contains("parameters:").not()),
+        );
+
+    Ok(())
+}
+
+#[test]
+fn top_slow_phase_durations() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args(["slow", "top", "./tests/files/multiline_param.log"])
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::contains(
+                "--- parse: 1 x 1.163ms  bind: 2 x 845µs  execute: 2 x 346µs ---",
+            )
+            .and(predicates::str::contains("AND t.test = $3")),
+        );
+
+    Ok(())
+}
+
+#[test]
+fn slow_pairs_log_duration_with_statement() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args(["slow", "5ms", "./tests/files/log_duration.log"])
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::contains(
+                "[3714783] LOG:  duration: 12.486 ms  statement: truncate pgbench_history",
+            )
+            .and(predicates::str::contains(
+                "[3714790] LOG:  duration: 506.870 ms  statement: select pg_sleep(0.5)",
+            ))
+            .and(predicates::str::contains(
+                "[3714795] LOG:  duration: 478.213 ms  execute P_1: UPDATE pgbench_accounts SET abalance = abalance + $1 WHERE aid = $2;",
+            ))
+            // No statement logged since the truncate was paired
+            .and(predicates::str::contains("duration: 7.003 ms\n")),
+        );
+
+    Ok(())
+}
+
+#[test]
+fn slow_times_in_output_zone() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args(["--tz", "+02:00", "slow", "5ms", "./tests/files/log_duration.log"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "2025-06-03 13:57:02.463 +02:00 [3714790] LOG:  duration: 506.870 ms  statement: select pg_sleep(0.5)",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn top_slow_pairs_log_duration_with_params() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args(["slow", "top", "--params", "./tests/files/log_duration.log"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "--- 478.213ms ---\n2025-06-03 14:57:02.480 EEST [3714795] LOG:  duration: 478.213 ms  execute P_1: UPDATE pgbench_accounts SET abalance = abalance + '479' WHERE aid = '1011363';",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn slow_per_query_id() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args(["slow", "queryid", "./tests/files/queryid.log"])
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::contains(
+                "--- query_id -4623036638694764959  calls: 1  total: 13.556339s  avg: 13.556339s  max: 13.556339s ---\nselect * from t1;",
+            )
+            .and(predicates::str::contains(
+                "--- query_id 1081270519976314062  calls: 4",
+            ))
+            .and(predicates::str::contains("query_id 0 ").not()),
+        );
+
+    Ok(())
+}
+
+#[test]
+fn slow_query_id_lookup() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args([
+        "slow",
+        "queryid",
+        "--id=-7951334099980996853",
+        "./tests/files/queryid.log",
+    ])
+    .assert()
+    .success()
+    .stdout(
+        predicates::str::contains("duration: 1.907 ms")
+            .and(predicates::str::contains("duration: 1.758 ms"))
+            .and(predicates::str::contains("STATEMENT:  SELECT name FROM"))
+            .and(predicates::str::contains("queryid=1081270519976314062").not()),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn top_slow_times_in_utc() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args(["--utc", "slow", "top", "./tests/files/log_duration.log"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "--- 478.213ms ---\n2025-06-03 11:57:02.480 UTC [3714795] LOG:  duration: 478.213 ms  execute P_1:",
+        ));
+
+    Ok(())
+}
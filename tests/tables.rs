@@ -0,0 +1,85 @@
+use assert_cmd::cargo;
+use assert_cmd::prelude::*;
+use predicates::prelude::PredicateBooleanExt;
+use std::process::Command;
+
+#[test]
+fn tables_by_duration() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args(["tables", "./tests/files/stmt_type.log"])
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::contains("Top 4 tables by total duration:")
+                .and(predicates::str::contains(
+                    "        17.604ms          6          0  test\n",
+                ))
+                .and(predicates::str::contains(
+                    "         9.068ms          2          0  country\n",
+                )),
+        );
+
+    Ok(())
+}
+
+#[test]
+fn tables_by_lock_waits() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args([
+        "tables",
+        "--sort",
+        "locks",
+        "--max",
+        "2",
+        "./tests/files/locking.log",
+    ])
+    .assert()
+    .success()
+    .stdout(
+        predicates::str::contains("Top 2 tables by lock waits:")
+            .and(predicates::str::contains(
+                "             0ns          0          2  pgbench_branches\n",
+            ))
+            .and(predicates::str::contains(
+                "             0ns          0          1  pgbench_tellers\n",
+            ))
+            .and(predicates::str::contains("pgbench_accounts").not()),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn tables_csv() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args(["tables", "--sort", "calls", "./tests/files/csvlog_pg14.csv"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "       3.643976s          5          0  pgbench_accounts\n",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn tables_with_pgbadger_prefix() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    // "%m [%p]: [%l-1] db=%d,user=%u,host=%h"
+    cmd.args(["tables", "./tests/files/duration.log"])
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::contains("        14.344ms          4          0  pg_class\n").and(
+                predicates::str::contains(
+                    "         5.217ms          3          0  pg_catalog.pg_settings\n",
+                ),
+            ),
+        );
+
+    Ok(())
+}
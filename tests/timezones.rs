@@ -0,0 +1,77 @@
+use assert_cmd::cargo;
+use assert_cmd::prelude::*;
+use predicates::prelude::PredicateBooleanExt;
+use std::{io::Write, process::Command};
+use tempfile::Builder;
+
+#[test]
+fn timezones_rewritten() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args([
+        "--tz",
+        "+02:00",
+        "errors",
+        "-l",
+        "log",
+        "./testdata/pgbadger/pg-timezones.log",
+    ])
+    .assert()
+    .success()
+    .stdout(
+        predicates::str::contains("2021-05-27 19:00:00 +02:00:10.1.91.65:user@my_db:[24743]").and(
+            predicates::str::contains("2021-05-27 19:46:39 +02:00:10.1.17.194:user@my_db:[19003]"),
+        ),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn timezones_begin_across_zones() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args([
+        "--tz",
+        "Europe/Helsinki",
+        "--begin",
+        "2021-05-27 17:30:00 UTC",
+        "errors",
+        "-l",
+        "log",
+        "./testdata/pgbadger/pg-timezones.log",
+    ])
+    .assert()
+    .success()
+    .stdout(
+        predicates::str::contains("2021-05-27 20:46:39 EEST:10.1.17.194")
+            .and(predicates::str::contains("[24743]").not()),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn timezones_ambiguous_override() -> Result<(), Box<dyn std::error::Error>> {
+    let mut log = Builder::new().suffix(".log").tempfile()?;
+    writeln!(log, "2025-05-22 10:00:00.123 IST [1] ERROR:  boom")?;
+    log.flush()?;
+
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+    cmd.arg("--utc")
+        .args(["--log-tz", "IST=+05:30", "errors"])
+        .arg(log.path())
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "2025-05-22 04:30:00.123 UTC [1] ERROR:  boom",
+        ));
+
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+    cmd.args(["--log-tz", "IST=Asia/Kolkata", "errors"])
+        .arg(log.path())
+        .assert()
+        .failure();
+
+    Ok(())
+}
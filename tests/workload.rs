@@ -0,0 +1,67 @@
+use assert_cmd::cargo;
+use assert_cmd::prelude::*;
+use predicates::prelude::PredicateBooleanExt;
+use std::process::Command;
+
+#[test]
+fn workload_by_statement_type() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args(["workload", "./tests/files/stmt_type.log"])
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::contains("  SELECT          3   25.0%  12.6ms")
+                .and(predicates::str::contains(
+                    "  DELETE          2   16.7%  3.815ms",
+                ))
+                .and(predicates::str::contains(
+                    "  COPY            2   16.7%  9.068ms",
+                ))
+                .and(predicates::str::contains(
+                    "  DDL             1    8.3%  7.209ms",
+                ))
+                .and(predicates::str::contains(
+                    "  TCL             2   16.7%  1.014ms",
+                )),
+        );
+
+    Ok(())
+}
+
+#[test]
+fn workload_with_log_duration() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    cmd.args(["workload", "./tests/files/log_duration.log"])
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::contains("  SELECT          1   33.3%  506.87ms")
+                .and(predicates::str::contains(
+                    "  UPDATE          1   33.3%  478.213ms",
+                ))
+                .and(predicates::str::contains(
+                    "  DDL             1   33.3%  12.486ms",
+                )),
+        );
+
+    Ok(())
+}
+
+#[test]
+fn workload_with_pgbadger_prefix() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::new(cargo::cargo_bin!("pgweasel"));
+
+    // "%m [%p]: [%l-1] db=%d,user=%u,host=%h"
+    cmd.args(["workload", "./tests/files/duration.log"])
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::contains("  SELECT         17  100.0%  73.932ms").and(
+                predicates::str::contains("  cloudsqladmin              14  65.775ms"),
+            ),
+        );
+
+    Ok(())
+}